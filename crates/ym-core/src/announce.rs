@@ -0,0 +1,41 @@
+use std::process::{
+    Command,
+    Stdio,
+};
+use std::time::Duration;
+
+// Tried in order when no command is configured
+const SYNTHESIZERS: [&str; 4] = ["spd-say", "espeak-ng", "espeak", "say"];
+
+// Roughly how long the synthesizer takes over the text, music stays ducked meanwhile
+pub fn estimate(text: &str) -> Duration {
+    Duration::from_millis(500 + 400 * text.split_whitespace().count() as u64)
+}
+
+// The text goes last, after the arguments of the configured command like "espeak-ng -v en"
+pub fn speak(text: &str, command: Option<&str>) -> bool {
+    let spawn = |program: &str, args: &[&str]| {
+        Command::new(program)
+            .args(args)
+            .arg(text)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    };
+    let child = match command {
+        Some(command) => {
+            let mut parts = command.split_whitespace();
+            let Some(program) = parts.next() else {
+                return false;
+            };
+            spawn(program, &parts.collect::<Vec<_>>()).ok()
+        },
+        None => SYNTHESIZERS.iter().find_map(|program| spawn(program, &[]).ok()),
+    };
+    let Some(mut child) = child else {
+        return false;
+    };
+    // Reaped in the background so finished synthesizers don't linger
+    std::thread::spawn(move || child.wait());
+    true
+}
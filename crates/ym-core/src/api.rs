@@ -0,0 +1,1653 @@
+use reqwest::{
+    Client,
+    Error,
+    header,
+    Url,
+};
+use std::io::Cursor;
+use crate::translit;
+use hmac::{
+    Hmac,
+    Mac,
+};
+use sha2::Sha256;
+use tracing::instrument;
+use futures::StreamExt;
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Deserializer,
+    Serialize,
+    de,
+};
+
+#[derive(Debug)]
+pub enum ApiError {
+    // The token is missing, expired or revoked
+    Unauthorized(String),
+    NotFound(String),
+    RateLimited,
+    // Any other error Yandex named in its response
+    Api { name: String, message: String },
+    // An error status without an explanation
+    Status(reqwest::StatusCode),
+    // The response didn't have the expected shape
+    Decode(serde_json::Error),
+    Http(Error),
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Unauthorized(message) => write!(f, "Yandex rejected the token ({}), set a new one with the token command", message),
+            ApiError::NotFound(message) => write!(f, "Not found on Yandex Music ({})", message),
+            ApiError::RateLimited => write!(f, "Too many requests to Yandex Music, try again in a minute"),
+            ApiError::Api { name, message } => write!(f, "Yandex Music error {}: {}", name, message),
+            ApiError::Status(status) => write!(f, "Yandex Music responded with {}", status),
+            ApiError::Decode(err) => write!(f, "Unexpected response from Yandex Music: {}", err),
+            ApiError::Http(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Decode(err) => Some(err),
+            ApiError::Http(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        ApiError::Http(err)
+    }
+}
+
+// Like {"error": {"name": "session-expired", "message": "..."}}
+#[derive(Debug, Deserialize)]
+struct ErrorEnvelope {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    name: String,
+    #[serde(default)]
+    message: String,
+}
+
+fn api_error(status: reqwest::StatusCode, body: &[u8]) -> Option<ApiError> {
+    let envelope = serde_json::from_slice::<ErrorEnvelope>(body).ok().map(|envelope| envelope.error);
+    let (name, message) = envelope
+        .map(|error| (error.name, error.message))
+        .unwrap_or_default();
+    let error = match (status.as_u16(), name.as_str()) {
+        (401 | 403, _) | (_, "session-expired" | "unauthorized" | "invalid-token") => ApiError::Unauthorized(message),
+        (404, _) | (_, "not-found") => ApiError::NotFound(message),
+        (429, _) | (_, "too-many-requests") => ApiError::RateLimited,
+        _ if !name.is_empty() => ApiError::Api { name, message },
+        _ if !status.is_success() => ApiError::Status(status),
+        _ => return None,
+    };
+    Some(error)
+}
+
+trait ApiResponse: Sized {
+    // Error statuses become an ApiError, explained by the error envelope when there is one
+    async fn checked(self) -> Result<reqwest::Response, ApiError>;
+
+    async fn api_json<T: DeserializeOwned>(self) -> Result<T, ApiError>;
+}
+
+impl ApiResponse for reqwest::Response {
+    async fn checked(self) -> Result<reqwest::Response, ApiError> {
+        let status = self.status();
+        if status.is_success() {
+            return Ok(self);
+        }
+        let body = self.bytes().await?;
+        Err(api_error(status, &body).unwrap_or(ApiError::Status(status)))
+    }
+
+    async fn api_json<T: DeserializeOwned>(self) -> Result<T, ApiError> {
+        let status = self.status();
+        let body = self.bytes().await?;
+        match serde_json::from_slice::<T>(&body) {
+            Ok(value) if status.is_success() => Ok(value),
+            Ok(_) => Err(ApiError::Status(status)),
+            Err(err) => Err(api_error(status, &body).unwrap_or(ApiError::Decode(err))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountStatus {
+    pub uid: Uid,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    pub login: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TrackInfo {
+    pub id: TrackId,
+}
+
+// Ids of different things are distinct types so one can't be passed for another
+macro_rules! id_type {
+    ($name:ident) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(pub u64);
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = std::num::ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map($name)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                u64_from_str_or_int(deserializer).map($name)
+            }
+        }
+    };
+}
+
+id_type!(TrackId);
+id_type!(AlbumId);
+id_type!(ArtistId);
+id_type!(PlaylistKind);
+id_type!(Uid);
+
+const JINGLE_MAX_MS: u64 = 30_000;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Track {
+    pub id: TrackId,
+    pub title: String,
+    // Like "Remastered 2011" or "feat. X", tells apart tracks sharing a title
+    pub version: Option<String>,
+    pub major: Option<Major>,
+    pub albums: Vec<AlbumInfo>,
+    pub artists: Vec<ArtistInfo>,
+    #[serde(rename = "durationMs")]
+    pub duration: Option<u64>,
+    #[serde(rename = "coverUri")]
+    pub cover_uri: Option<String>,
+    #[serde(default)]
+    pub r128: Option<Loudness>,
+    // False once the track is pulled in the account's region or by the label
+    #[serde(default = "available")]
+    pub available: bool,
+}
+
+fn available() -> bool {
+    true
+}
+
+// EBU R128 measurements of the track, both in dB
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct Loudness {
+    #[serde(rename = "i")]
+    pub integrated: f32,
+    #[serde(rename = "tp")]
+    pub true_peak: f32,
+}
+
+impl Loudness {
+    // Linear factor bringing the track to the target loudness, never boosting peaks past clipping
+    pub fn gain(&self, target: f32) -> f32 {
+        let db = (target - self.integrated).min(-self.true_peak);
+        10f32.powf(db / 20.0)
+    }
+}
+
+impl Track {
+    pub fn cover_uri(&self) -> Option<&str> {
+        self.cover_uri
+            .as_deref()
+            .or_else(|| self.albums.first().and_then(|album| album.cover_uri.as_deref()))
+    }
+
+    // Station idents and promos come as short tracks of an album that says it isn't music,
+    // a missing album or type alone is too common among real tracks to go by
+    pub fn is_jingle(&self) -> bool {
+        let short = self.duration.is_some_and(|ms| ms < JINGLE_MAX_MS);
+        let album = self.albums.first().map(|album| &album.meta_type);
+        short && matches!(album, Some(AlbumType::Other))
+    }
+
+    pub fn full_title(&self) -> String {
+        match &self.version {
+            Some(version) if !version.is_empty() => format!("{} ({})", self.title, version),
+            _ => self.title.clone(),
+        }
+    }
+
+    // Case-insensitive substring match on the title, artists and albums, in either script
+    pub fn matches(&self, query: &str) -> bool {
+        let query = translit::fold(query);
+        std::iter::once(&self.title)
+            .chain(&self.version)
+            .chain(self.artists.iter().map(|artist| &artist.name))
+            .chain(self.albums.iter().map(|album| &album.title))
+            .any(|field| translit::fold(field).contains(&query))
+    }
+}
+
+impl std::fmt::Display for Track {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.full_title(), Artists(&self.artists))
+    }
+}
+
+#[derive(PartialEq, Debug, Deserialize, Serialize)]
+pub enum AlbumType {
+    #[serde(rename="music")]
+    Music,
+    #[serde(rename="podcast")]
+    Podcast,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AlbumInfo {
+    pub id: AlbumId,
+    pub title: String,
+    #[serde(rename="metaType")]
+    pub meta_type: AlbumType,
+    #[serde(rename="trackCount")]
+    pub track_count: u32,
+    #[serde(rename="likesCount")]
+    likes_count: Option<u32>,
+    #[serde(rename="coverUri")]
+    pub cover_uri: Option<String>,
+    pub year: Option<i32>,
+    // Like "2019-03-15T00:00:00+03:00"
+    #[serde(rename="releaseDate")]
+    pub release_date: Option<String>,
+    #[serde(default)]
+    pub artists: Vec<ArtistInfo>,
+    // "compilation", "single" and so on, absent for regular albums
+    #[serde(rename="type")]
+    pub kind: Option<String>,
+    // A genre id like "rusrock" or "podcasts"
+    pub genre: Option<String>,
+}
+
+impl AlbumInfo {
+    // Falls back to the start of the year when only that is known
+    pub fn released(&self) -> Option<chrono::NaiveDate> {
+        self.release_date
+            .as_deref()
+            .and_then(|date| chrono::NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok())
+            .or_else(|| chrono::NaiveDate::from_ymd_opt(self.year?, 1, 1))
+    }
+
+    pub fn is_compilation(&self) -> bool {
+        self.kind.as_deref() == Some("compilation") || self.artists.iter().any(|artist| artist.various)
+    }
+}
+
+impl std::fmt::Display for AlbumInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{} ({})", self.title, year),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ArtistInfo {
+    pub id: ArtistId,
+    pub name: String,
+    // The placeholder artist compilations are credited to
+    #[serde(default)]
+    pub various: bool,
+    #[serde(default)]
+    pub genres: Vec<String>,
+}
+
+impl std::fmt::Display for ArtistInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct Artists<'a> (pub &'a Vec<ArtistInfo>);
+
+impl std::ops::Deref for Artists<'_> {
+    type Target = Vec<ArtistInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Artists<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for i in 0..self.len() - 1 {
+            write!(f, "{}, ", self[i])?; 
+        }
+        write!(f, "{}", self[self.len() - 1])?;
+        write!(f, ")")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadInfo {
+    codec: Codec,
+    #[serde(rename="downloadInfoUrl")]
+    url: String,
+    #[serde(rename="bitrateInKbps")]
+    bitrate: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackData {
+    pub id: TrackId,
+    pub codec: Codec,
+    pub bitrate: u32,
+    pub loaded: std::time::Instant,
+    pub data: Cursor<bytes::Bytes>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Codec {
+    #[serde(rename="mp3")]
+    Mp3,
+    #[serde(rename="aac")]
+    Aac,
+}
+
+impl std::fmt::Display for Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Codec::Mp3 => write!(f, "mp3"),
+            Codec::Aac => write!(f, "aac"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Quality {
+    pub codec: Codec,
+    pub bitrate: u32,
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Quality {
+            codec: Codec::Mp3,
+            bitrate: 320,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Major {
+    id: u64,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountStatusResponse {
+    result: AccountStatusResponseResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountStatusResponseResult {
+    account: AccountStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct TracksInfoResponse {
+    result: TracksInfoResponseResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct TracksInfoResponseResult {
+    library: TracksInfoLibrary,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TracksInfoLibrary {
+    #[serde(default)]
+    pub revision: u64,
+    pub tracks: Vec<TrackInfo>,
+}
+
+// Some endpoints send ids as numbers, others as strings
+fn u64_from_str_or_int<'de, D>(deserializer: D) -> Result<u64, D::Error>
+    where D: Deserializer<'de>
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrInt {
+        Str(String),
+        Int(u64),
+    }
+
+    match StrOrInt::deserialize(deserializer)? {
+        StrOrInt::Str(s) => s.parse::<u64>().map_err(de::Error::custom),
+        StrOrInt::Int(n) => Ok(n),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackQueryResponse {
+    result: Vec<Track>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadInfoResponse {
+    result: Vec<DownloadInfo>,
+}
+
+
+// An authorized session, every request function takes the reqwest client it derefs to.
+// Clones share the connection pool
+#[derive(Debug, Clone)]
+pub struct YandexClient {
+    client: Client,
+}
+
+impl YandexClient {
+    pub fn new(token: &str) -> Result<Self, Error> {
+        Ok(YandexClient { client: authorized_client(token)? })
+    }
+}
+
+impl std::ops::Deref for YandexClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+// The client background tasks use, swapped when the user authorizes again mid-session
+#[derive(Debug, Clone)]
+pub struct SharedClient(std::sync::Arc<std::sync::RwLock<Client>>);
+
+impl SharedClient {
+    pub fn new(client: Client) -> Self {
+        SharedClient(std::sync::Arc::new(std::sync::RwLock::new(client)))
+    }
+
+    pub fn get(&self) -> Client {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, client: Client) {
+        *self.0.write().unwrap() = client;
+    }
+}
+
+fn authorized_client(token: &str) -> Result<Client, Error> {
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        "Authorization",
+        header::HeaderValue::from_str(
+            &(format!("OAuth {}", token))
+        ).unwrap()
+    );
+
+    Client::builder()
+        .default_headers(headers)
+        .build()
+}
+
+#[instrument(skip_all)]
+pub async fn account_status(client: &Client) -> Result<AccountStatus, ApiError> {
+    Ok(
+        client
+            .get("https://api.music.yandex.net/account/status/")
+            .send()
+            .await?
+            .api_json::<AccountStatusResponse>()
+            .await?
+            .result
+            .account
+    )
+}
+
+#[instrument(skip_all)]
+pub async fn account_uid(client: &Client) -> Result<Uid, ApiError> {
+    Ok(
+        client
+            .get("https://api.music.yandex.net/account/status/")
+            .send()
+            .await?
+            .api_json::<AccountStatusResponse>()
+            .await?
+            .result
+            .account
+            .uid
+    )
+}
+
+// The revision changes with every like or unlike, so it tells whether a local copy is stale
+#[instrument(skip_all)]
+pub async fn liked_library(uid: Uid, client: &Client) -> Result<TracksInfoLibrary, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/users/{}/likes/tracks", uid))
+            .send()
+            .await?
+            .api_json::<TracksInfoResponse>()
+            .await?
+            .result
+            .library
+    )
+}
+
+pub async fn liked_tracks_infos(uid: Uid, client: &Client) -> Result<Vec<TrackInfo>, ApiError> {
+    Ok(liked_library(uid, client).await?.tracks)
+}
+
+#[instrument(skip_all)]
+pub async fn fetch_track(track_id: TrackId, client: &Client, attempts: Option<usize>) -> Result<Track, ApiError> {
+    let mut left = attempts.unwrap_or(1);
+    let mut error = None;
+    while left > 0 {
+        match client
+                .post("https://api.music.yandex.net/tracks")
+                .query(&[("trackIds", track_id)])
+                .send()
+                .await
+        {
+
+            Ok(resp) => {
+                return resp
+                    .api_json::<TrackQueryResponse>()
+                    .await?
+                    .result
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| ApiError::NotFound(format!("track {}", track_id)));
+            }
+            Err(err) => {
+                left -= 1;
+                error = Some(err);
+                continue;
+            }
+        }
+    }
+    Err(error.unwrap().into())
+}
+
+// At most `concurrency` requests are in flight, results keep the order of the ids
+pub async fn fetch_tracks_bounded(
+    ids: &[TrackId],
+    client: &Client,
+    concurrency: usize,
+    mut progress: impl FnMut(usize, usize),
+) -> Vec<Result<Track, ApiError>> {
+    let mut fetches = futures::stream::iter(ids.iter().map(|&id| fetch_track(id, client, Some(2))))
+        .buffered(concurrency.max(1));
+
+    let mut results = Vec::with_capacity(ids.len());
+    while let Some(result) = fetches.next().await {
+        results.push(result);
+        progress(results.len(), ids.len());
+    }
+    results
+}
+
+#[instrument(skip_all)]
+async fn direct_link(info: &DownloadInfo, client: &Client) -> Result<String, ApiError> {
+    let bytes = client
+        .get(&info.url)
+        .send()
+        .await?
+        .bytes()
+        .await?;
+    
+    let xml = xmltree::Element::parse(&*bytes).unwrap();
+
+    let host = xml.get_child("host").unwrap().get_text().unwrap();
+    let path = xml.get_child("path").unwrap().get_text().unwrap();
+    let s = xml.get_child("s").unwrap().get_text().unwrap();
+    let ts = xml.get_child("ts").unwrap().get_text().unwrap();
+    let sign = hex::encode::<[u8; 16]>(
+        md5::compute(
+            ("XGRlBW9FXlekgbPrRHuSiA".to_owned() + &path[1..] + &s).as_bytes()
+        ).into()
+    );
+
+    Ok(
+        format!(
+            "https://{}/get-{}/{}/{}{}",
+            host,
+            info.codec,
+            sign,
+            ts,
+            path
+        )
+    )
+}
+
+// Best bitrate of the preferred codec not exceeding the preferred bitrate,
+// falling back to the closest available stream
+fn pick_download_info(infos: &[DownloadInfo], quality: Quality) -> &DownloadInfo {
+    let same_codec = || infos.iter().filter(move |info| info.codec == quality.codec);
+    same_codec()
+        .filter(|info| info.bitrate <= quality.bitrate)
+        .max_by_key(|info| info.bitrate)
+        .or_else(|| same_codec().min_by_key(|info| info.bitrate))
+        .or_else(|| infos.iter().min_by_key(|info| info.bitrate.abs_diff(quality.bitrate)))
+        .unwrap_or(&infos[0])
+}
+
+pub struct TrackStream {
+    pub id: TrackId,
+    pub codec: Codec,
+    pub bitrate: u32,
+    pub response: reqwest::Response,
+}
+
+#[instrument(skip_all)]
+pub async fn open_track_stream(id: TrackId, quality: Quality, client: &Client) -> Result<TrackStream, ApiError> {
+    let infos = client
+        .get(format!("https://api.music.yandex.net/tracks/{}/download-info", id))
+        .send()
+        .await?
+        .api_json::<DownloadInfoResponse>()
+        .await?
+        .result;
+
+    let info = pick_download_info(&infos, quality);
+    let link = direct_link(info, client).await?;
+    let response = client
+        .get(link)
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(
+        TrackStream {
+            id,
+            codec: info.codec,
+            bitrate: info.bitrate,
+            response,
+        }
+    )
+}
+
+pub async fn download_data(id: TrackId, quality: Quality, client: &Client) -> Result<TrackData, ApiError> {
+    let stream = open_track_stream(id, quality, client).await?;
+    let bytes = stream.response.bytes().await?;
+
+    Ok(
+        TrackData {
+            id,
+            codec: stream.codec,
+            bitrate: stream.bitrate,
+            data: std::io::Cursor::new(bytes),
+            loaded: std::time::Instant::now(),
+        }
+    )
+}
+
+#[instrument(skip_all)]
+pub async fn dislike_track(uid: Uid, track_id: TrackId, client: &Client) -> Result<(), ApiError> {
+    client
+        .post(format!("https://api.music.yandex.net/users/{}/dislikes/tracks/add-multiple", uid))
+        .form(&[("track-ids", track_id)])
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn like_tracks(uid: Uid, track_ids: &[TrackId], client: &Client) -> Result<(), ApiError> {
+    let track_ids = track_ids.iter().map(ToString::to_string).collect::<Vec<_>>().join(",");
+    client
+        .post(format!("https://api.music.yandex.net/users/{}/likes/tracks/add-multiple", uid))
+        .form(&[("track-ids", track_ids)])
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    result: SearchResponseResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponseResult {
+    // Missing when nothing was found
+    tracks: Option<SearchTracks>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchTracks {
+    results: Vec<Track>,
+}
+
+// Best matches first, nocorrect keeps Yandex from searching for what it guesses was meant
+#[instrument(skip_all)]
+pub async fn search_tracks(text: &str, client: &Client) -> Result<Vec<Track>, ApiError> {
+    Ok(
+        client
+            .get("https://api.music.yandex.net/search")
+            .query(&[("text", text), ("type", "track"), ("page", "0"), ("nocorrect", "true")])
+            .send()
+            .await?
+            .api_json::<SearchResponse>()
+            .await?
+            .result
+            .tracks
+            .map_or_else(Vec::new, |tracks| tracks.results)
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayAudio {
+    #[serde(rename = "track-id")]
+    pub track_id: TrackId,
+    #[serde(rename = "album-id")]
+    pub album_id: Option<AlbumId>,
+    #[serde(rename = "playlist-id")]
+    pub playlist_id: Option<String>,
+    pub from: &'static str,
+    #[serde(rename = "from-cache")]
+    pub from_cache: bool,
+    pub uid: Uid,
+    pub timestamp: String,
+    #[serde(rename = "client-now")]
+    pub client_now: String,
+    #[serde(rename = "track-length-seconds")]
+    pub track_length_seconds: f64,
+    #[serde(rename = "total-played-seconds")]
+    pub total_played_seconds: f64,
+    #[serde(rename = "end-position-seconds")]
+    pub end_position_seconds: f64,
+}
+
+#[instrument(skip_all)]
+pub async fn send_play_audio(play: &PlayAudio, client: &Client) -> Result<(), ApiError> {
+    client
+        .post("https://api.music.yandex.net/play-audio")
+        .form(play)
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+// Cover uris come without a scheme and with a "%%" placeholder for the size
+#[instrument(skip_all)]
+pub async fn download_cover(uri: &str, size: u32, client: &Client) -> Result<bytes::Bytes, ApiError> {
+    let url = format!("https://{}", uri.replace("%%", &format!("{}x{}", size, size)));
+    Ok(
+        client
+            .get(url)
+            .send()
+            .await?
+            .checked()
+            .await?
+            .bytes()
+            .await?
+    )
+}
+
+#[derive(Debug)]
+pub enum RawLyrics {
+    Synced(String),
+    Plain(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricsResponse {
+    result: LyricsResponseResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricsResponseResult {
+    #[serde(rename = "downloadUrl")]
+    download_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SupplementResponse {
+    result: Supplement,
+}
+
+#[derive(Debug, Deserialize)]
+struct Supplement {
+    lyrics: Option<SupplementLyrics>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SupplementLyrics {
+    #[serde(rename = "fullLyrics")]
+    full_lyrics: String,
+}
+
+const LYRICS_SIGN_KEY: &[u8] = b"p93jhgh689SBReK6ghtw62";
+
+#[instrument(skip_all)]
+async fn synced_lyrics(track_id: TrackId, client: &Client) -> Result<Option<String>, ApiError> {
+    let timestamp = chrono::Utc::now().timestamp();
+    let mut mac = Hmac::<Sha256>::new_from_slice(LYRICS_SIGN_KEY).unwrap();
+    mac.update(format!("{}{}", track_id, timestamp).as_bytes());
+    let sign = base64::encode(mac.finalize().into_bytes());
+
+    let resp = client
+        .get(format!("https://api.music.yandex.net/tracks/{}/lyrics", track_id))
+        .query(&[("format", "LRC"), ("timeStamp", &timestamp.to_string()), ("sign", &sign)])
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Ok(None);
+    }
+
+    let url = resp
+        .api_json::<LyricsResponse>()
+        .await?
+        .result
+        .download_url;
+
+    Ok(Some(client.get(url).send().await?.text().await?))
+}
+
+#[instrument(skip_all)]
+async fn supplement_lyrics(track_id: TrackId, client: &Client) -> Result<Option<String>, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/tracks/{}/supplement", track_id))
+            .send()
+            .await?
+            .api_json::<SupplementResponse>()
+            .await?
+            .result
+            .lyrics
+            .map(|lyrics| lyrics.full_lyrics)
+    )
+}
+
+pub async fn track_lyrics(track_id: TrackId, client: &Client) -> Result<Option<RawLyrics>, ApiError> {
+    if let Some(lrc) = synced_lyrics(track_id, client).await? {
+        return Ok(Some(RawLyrics::Synced(lrc)));
+    }
+
+    Ok(supplement_lyrics(track_id, client).await?.map(RawLyrics::Plain))
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistsResponse {
+   result: Vec<PlaylistInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistInfo {
+    pub title: String,
+    #[serde(rename = "trackCount")]
+    pub track_count: usize,
+    pub kind: PlaylistKind,
+    pub uid: Uid,
+    #[serde(default)]
+    pub revision: u64,
+    pub description: Option<String>,
+    #[serde(rename = "durationMs")]
+    pub duration: Option<u64>,
+    pub cover: Option<PlaylistCover>,
+    pub owner: Option<PlaylistOwner>,
+}
+
+impl PlaylistInfo {
+    // Mosaic covers have no image of their own, the first of the tiles stands in
+    pub fn cover_uri(&self) -> Option<&str> {
+        let cover = self.cover.as_ref()?;
+        cover.uri.as_deref().or_else(|| cover.items_uri.first().map(String::as_str))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistCover {
+    pub uri: Option<String>,
+    #[serde(rename = "itemsUri", default)]
+    pub items_uri: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistOwner {
+    pub uid: Uid,
+    pub login: String,
+}
+
+
+#[instrument(skip_all)]
+pub async fn playlists(uid: Uid, client: &Client) -> Result<Vec<PlaylistInfo>, ApiError> {
+    user_playlists(&uid.to_string(), client).await
+}
+
+// A path under a user given by login, which comes from links and prompts, so it is percent-encoded
+fn user_url(owner: &str, path: &[&str]) -> Url {
+    let mut url = Url::parse("https://api.music.yandex.net/users").expect("the API base is a valid url");
+    url.path_segments_mut().expect("https urls have a path").push(owner).extend(path);
+    url
+}
+
+// Public playlists of any user, by login or uid
+#[instrument(skip_all)]
+pub async fn user_playlists(owner: &str, client: &Client) -> Result<Vec<PlaylistInfo>, ApiError> {
+    Ok(
+        client
+            .get(user_url(owner, &["playlists", "list"]))
+            .send()
+            .await?
+            .api_json::<PlaylistsResponse>()
+            .await?
+            .result
+    )
+}
+
+// Resolves several playlists of one owner at once, unknown kinds are left out of the result
+#[instrument(skip_all)]
+pub async fn playlists_by_kinds(uid: Uid, kinds: &[PlaylistKind], client: &Client) -> Result<Vec<PlaylistInfo>, ApiError> {
+    let kinds = kinds.iter().map(PlaylistKind::to_string).collect::<Vec<_>>().join(",");
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/users/{}/playlists", uid))
+            .query(&[("kinds", kinds.as_str()), ("rich-tracks", "false")])
+            .send()
+            .await?
+            .api_json::<PlaylistsResponse>()
+            .await?
+            .result
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct LikedPlaylist {
+    playlist: PlaylistInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct LikedPlaylistsResponse {
+    result: Vec<LikedPlaylist>,
+}
+
+// Playlists of other users the account follows
+#[instrument(skip_all)]
+pub async fn liked_playlists(uid: Uid, client: &Client) -> Result<Vec<PlaylistInfo>, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/users/{}/likes/playlists", uid))
+            .send()
+            .await?
+            .api_json::<LikedPlaylistsResponse>()
+            .await?
+            .result
+            .into_iter()
+            .map(|liked| liked.playlist)
+            .collect()
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistResponse {
+    result: PlaylistInfo,
+}
+
+#[instrument(skip_all)]
+pub async fn create_playlist(uid: Uid, title: &str, client: &Client) -> Result<PlaylistInfo, ApiError> {
+    Ok(
+        client
+            .post(format!("https://api.music.yandex.net/users/{}/playlists/create", uid))
+            .form(&[("title", title), ("visibility", "private")])
+            .send()
+            .await?
+            .checked()
+            .await?
+            .api_json::<PlaylistResponse>()
+            .await?
+            .result
+    )
+}
+
+#[instrument(skip_all)]
+pub async fn rename_playlist(playlist: &PlaylistInfo, title: &str, client: &Client) -> Result<(), ApiError> {
+    client
+        .post(format!("https://api.music.yandex.net/users/{}/playlists/{}/name", playlist.uid, playlist.kind))
+        .form(&[("value", title)])
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn delete_playlist(playlist: &PlaylistInfo, client: &Client) -> Result<(), ApiError> {
+    client
+        .post(format!("https://api.music.yandex.net/users/{}/playlists/{}/delete", playlist.uid, playlist.kind))
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+// Edits are diffs against a revision, a stale one gets rejected by the server
+#[instrument(skip_all)]
+pub async fn playlist_insert_track(
+    playlist: &PlaylistInfo,
+    at: usize,
+    track_id: TrackId,
+    album_id: AlbumId,
+    client: &Client,
+) -> Result<(), ApiError> {
+    let diff = serde_json::json!([{
+        "op": "insert",
+        "at": at,
+        "tracks": [{ "id": track_id.to_string(), "albumId": album_id.to_string() }],
+    }]);
+
+    client
+        .post(format!("https://api.music.yandex.net/users/{}/playlists/{}/change-relative", playlist.uid, playlist.kind))
+        .form(&[("diff", diff.to_string()), ("revision", playlist.revision.to_string())])
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn playlist_delete_track(
+    playlist: &PlaylistInfo,
+    at: usize,
+    track_id: TrackId,
+    album_id: AlbumId,
+    client: &Client,
+) -> Result<(), ApiError> {
+    let diff = serde_json::json!([{
+        "op": "delete",
+        "from": at,
+        "to": at + 1,
+        "tracks": [{ "id": track_id.to_string(), "albumId": album_id.to_string() }],
+    }]);
+
+    client
+        .post(format!("https://api.music.yandex.net/users/{}/playlists/{}/change-relative", playlist.uid, playlist.kind))
+        .form(&[("diff", diff.to_string()), ("revision", playlist.revision.to_string())])
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistWithTracks {
+    #[serde(flatten)]
+    pub info: PlaylistInfo,
+    #[serde(default)]
+    tracks: Vec<TrackWrapper>,
+}
+
+impl PlaylistWithTracks {
+    pub fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().map(|wrapper| &wrapper.track)
+    }
+
+    pub fn into_tracks(self) -> Vec<Track> {
+        self.tracks.into_iter().map(|wrapper| wrapper.track).collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistWithTracksResponse {
+    result: PlaylistWithTracks,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackWrapper {
+    track: Track,
+}
+
+#[instrument(skip_all)]
+pub async fn playlist_with_tracks(uid: Uid, kind: PlaylistKind, client: &Client) -> Result<PlaylistWithTracks, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/users/{}/playlists/{}", uid, kind))
+            .send()
+            .await?
+            .api_json::<PlaylistWithTracksResponse>()
+            .await?
+            .result
+    )
+}
+
+// Shared links name the owner by login, the api accepts it in place of the uid
+#[instrument(skip_all)]
+pub async fn playlist_by_owner(owner: &str, kind: PlaylistKind, client: &Client) -> Result<PlaylistInfo, ApiError> {
+    Ok(
+        client
+            .get(user_url(owner, &["playlists", &kind.to_string()]))
+            .query(&[("rich-tracks", "false")])
+            .send()
+            .await?
+            .api_json::<PlaylistResponse>()
+            .await?
+            .result
+    )
+}
+
+pub const TRACKS_PAGE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct TrackShort {
+    id: TrackId,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistTrackIdsResponse {
+    result: PlaylistTrackIds,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistTrackIds {
+    #[serde(default)]
+    tracks: Vec<TrackShort>,
+}
+
+// Without rich tracks only ids arrive, which stays small for playlists of any size
+#[instrument(skip_all)]
+pub async fn playlist_track_ids(info: &PlaylistInfo, client: &Client) -> Result<Vec<TrackId>, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/users/{}/playlists/{}", info.uid, info.kind))
+            .query(&[("rich-tracks", "false")])
+            .send()
+            .await?
+            .api_json::<PlaylistTrackIdsResponse>()
+            .await?
+            .result
+            .tracks
+            .into_iter()
+            .map(|track| track.id)
+            .collect()
+    )
+}
+
+#[instrument(skip_all)]
+pub async fn fetch_tracks(ids: &[TrackId], client: &Client) -> Result<Vec<Track>, ApiError> {
+    let ids = ids.iter().map(TrackId::to_string).collect::<Vec<_>>().join(",");
+    Ok(
+        client
+            .post("https://api.music.yandex.net/tracks")
+            .form(&[("track-ids", ids)])
+            .send()
+            .await?
+            .api_json::<TrackQueryResponse>()
+            .await?
+            .result
+    )
+}
+
+pub async fn tracks_from_playlist(
+    info: &PlaylistInfo,
+    client: &Client,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Vec<Track>, ApiError> {
+    let ids = playlist_track_ids(info, client).await?;
+    let mut tracks = Vec::with_capacity(ids.len());
+    for page in ids.chunks(TRACKS_PAGE) {
+        tracks.extend(fetch_tracks(page, client).await?);
+        progress(tracks.len(), ids.len());
+    }
+
+    Ok(tracks)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtistBrief {
+    pub artist: ArtistInfo,
+    #[serde(default)]
+    pub albums: Vec<AlbumInfo>,
+    #[serde(rename = "popularTracks", default)]
+    pub popular_tracks: Vec<Track>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistBriefResponse {
+    result: ArtistBrief,
+}
+
+#[instrument(skip_all)]
+pub async fn artist_info(artist_id: ArtistId, client: &Client) -> Result<ArtistBrief, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/artists/{}/brief-info", artist_id))
+            .send()
+            .await?
+            .api_json::<ArtistBriefResponse>()
+            .await?
+            .result
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistTracksResponse {
+    result: ArtistTracksResponseResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistTracksResponseResult {
+    tracks: Vec<Track>,
+}
+
+#[instrument(skip_all)]
+pub async fn artist_tracks(artist_id: ArtistId, client: &Client) -> Result<Vec<Track>, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/artists/{}/tracks", artist_id))
+            .query(&[("page", 0), ("page-size", 100)])
+            .send()
+            .await?
+            .api_json::<ArtistTracksResponse>()
+            .await?
+            .result
+            .tracks
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistAlbumsResponse {
+    result: ArtistAlbumsResponseResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistAlbumsResponseResult {
+    albums: Vec<AlbumInfo>,
+}
+
+#[instrument(skip_all)]
+pub async fn artist_albums(artist_id: ArtistId, client: &Client) -> Result<Vec<AlbumInfo>, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/artists/{}/direct-albums", artist_id))
+            .query(&[("page", "0"), ("page-size", "100"), ("sort-by", "year")])
+            .send()
+            .await?
+            .api_json::<ArtistAlbumsResponse>()
+            .await?
+            .result
+            .albums
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlbumWithTracks {
+    #[serde(flatten)]
+    pub info: AlbumInfo,
+    #[serde(default)]
+    pub volumes: Vec<Vec<Track>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlbumWithTracksResponse {
+    result: AlbumWithTracks,
+}
+
+#[instrument(skip_all)]
+pub async fn album_with_tracks(album_id: AlbumId, client: &Client) -> Result<AlbumWithTracks, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/albums/{}/with-tracks", album_id))
+            .send()
+            .await?
+            .api_json::<AlbumWithTracksResponse>()
+            .await?
+            .result
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct LikedAlbumsResponse {
+    result: Vec<LikedAlbum>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LikedAlbum {
+    album: AlbumInfo,
+}
+
+#[instrument(skip_all)]
+pub async fn liked_albums(uid: Uid, client: &Client) -> Result<Vec<AlbumInfo>, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/users/{}/likes/albums", uid))
+            .query(&[("rich", "true")])
+            .send()
+            .await?
+            .api_json::<LikedAlbumsResponse>()
+            .await?
+            .result
+            .into_iter()
+            .map(|liked| liked.album)
+            .collect()
+    )
+}
+
+#[instrument(skip_all)]
+pub async fn liked_music_albums(uid: Uid, client: &Client) -> Result<Vec<AlbumInfo>, ApiError> {
+    Ok(
+        liked_albums(uid, client)
+            .await?
+            .into_iter()
+            .filter(|album| album.meta_type == AlbumType::Music)
+            .collect()
+    )
+}
+
+#[instrument(skip_all)]
+pub async fn liked_podcasts(uid: Uid, client: &Client) -> Result<Vec<AlbumInfo>, ApiError> {
+    Ok(
+        liked_albums(uid, client)
+            .await?
+            .into_iter()
+            .filter(|album| album.meta_type == AlbumType::Podcast)
+            .collect()
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LandingBlock {
+    pub title: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub entities: Vec<LandingEntity>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum LandingEntity {
+    #[serde(rename = "personal-playlist")]
+    PersonalPlaylist(PersonalPlaylist),
+    #[serde(rename = "playlist")]
+    Playlist(PlaylistInfo),
+    #[serde(rename = "album")]
+    Album(AlbumInfo),
+    #[serde(rename = "chart-item")]
+    ChartItem(ChartItem),
+    #[serde(other)]
+    Other,
+}
+
+// A track of the chart block and where it stands
+#[derive(Debug, Deserialize)]
+pub struct ChartItem {
+    pub track: Track,
+    pub chart: ChartPosition,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChartPosition {
+    pub position: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PersonalPlaylist {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub data: PlaylistInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct LandingResponse {
+    result: LandingResponseResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct LandingResponseResult {
+    blocks: Vec<LandingBlock>,
+}
+
+#[instrument(skip_all)]
+pub async fn landing(blocks: &[&str], client: &Client) -> Result<Vec<LandingBlock>, ApiError> {
+    Ok(
+        client
+            .get("https://api.music.yandex.net/landing3")
+            .query(&[("blocks", blocks.join(","))])
+            .send()
+            .await?
+            .api_json::<LandingResponse>()
+            .await?
+            .result
+            .blocks
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct StationTracksResponse {
+    result: StationTracks,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StationTracks {
+    #[serde(rename = "batchId")]
+    pub batch_id: String,
+    sequence: Vec<TrackWrapper>,
+}
+
+impl StationTracks {
+    pub fn into_tracks(self) -> Vec<Track> {
+        self.sequence.into_iter().map(|wrapper| wrapper.track).collect()
+    }
+}
+
+// The last queued track lets the station continue from it instead of starting over
+#[instrument(skip_all)]
+pub async fn station_tracks(station: &str, last: Option<TrackId>, client: &Client) -> Result<StationTracks, ApiError> {
+    let mut request = client
+        .get(format!("https://api.music.yandex.net/rotor/station/{}/tracks", station))
+        .query(&[("settings2", "true")]);
+    if let Some(last) = last {
+        request = request.query(&[("queue", last)]);
+    }
+
+    Ok(
+        request
+            .send()
+            .await?
+            .api_json::<StationTracksResponse>()
+            .await?
+            .result
+    )
+}
+
+// Queues are kept per device, Yandex wants the one touching them named in a header
+const DEVICE: &str = "os=Linux; os_version=; manufacturer=; model=yandex_music_tui; clid=; device_id=yandex_music_tui; uuid=yandex_music_tui";
+
+fn serialize_display<T: std::fmt::Display, S: serde::Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+// What a queue was started from, like a playlist with an id of "uid:kind" or an album
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QueueContext {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueueBrief {
+    pub id: String,
+    pub context: QueueContext,
+    // Like "2023-04-01T12:00:00.000Z"
+    pub modified: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueuesResult {
+    queues: Vec<QueueBrief>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueuesResponse {
+    result: QueuesResult,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QueueTrack {
+    #[serde(rename = "trackId", serialize_with = "serialize_display")]
+    pub track_id: TrackId,
+    #[serde(rename = "albumId", skip_serializing_if = "Option::is_none")]
+    pub album_id: Option<AlbumId>,
+    pub from: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RemoteQueue {
+    #[serde(skip_serializing)]
+    pub id: Option<String>,
+    pub context: QueueContext,
+    pub tracks: Vec<QueueTrack>,
+    #[serde(rename = "currentIndex")]
+    pub current_index: Option<usize>,
+    pub from: String,
+    #[serde(rename = "isInteractive", default)]
+    pub is_interactive: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteQueueResponse {
+    result: RemoteQueue,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedQueue {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedQueueResponse {
+    result: CreatedQueue,
+}
+
+// Most recently modified first
+#[instrument(skip_all)]
+pub async fn queues(client: &Client) -> Result<Vec<QueueBrief>, ApiError> {
+    let mut queues = client
+        .get("https://api.music.yandex.net/queues")
+        .header("X-Yandex-Music-Device", DEVICE)
+        .send()
+        .await?
+        .api_json::<QueuesResponse>()
+        .await?
+        .result
+        .queues;
+    queues.sort_by(|a, b| b.modified.cmp(&a.modified));
+    Ok(queues)
+}
+
+#[instrument(skip_all)]
+pub async fn queue(id: &str, client: &Client) -> Result<RemoteQueue, ApiError> {
+    Ok(
+        client
+            .get(format!("https://api.music.yandex.net/queues/{}", id))
+            .header("X-Yandex-Music-Device", DEVICE)
+            .send()
+            .await?
+            .api_json::<RemoteQueueResponse>()
+            .await?
+            .result
+    )
+}
+
+// Returns the id of the new queue
+#[instrument(skip_all)]
+pub async fn create_queue(queue: &RemoteQueue, client: &Client) -> Result<String, ApiError> {
+    Ok(
+        client
+            .post("https://api.music.yandex.net/queues")
+            .header("X-Yandex-Music-Device", DEVICE)
+            .json(queue)
+            .send()
+            .await?
+            .api_json::<CreatedQueueResponse>()
+            .await?
+            .result
+            .id
+    )
+}
+
+#[instrument(skip_all)]
+pub async fn update_queue_position(id: &str, index: usize, client: &Client) -> Result<(), ApiError> {
+    client
+        .post(format!("https://api.music.yandex.net/queues/{}/update-position", id))
+        .header("X-Yandex-Music-Device", DEVICE)
+        .query(&[("currentIndex", index.to_string().as_str()), ("isInteractive", "false")])
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct StationFeedback {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub timestamp: String,
+    pub from: &'static str,
+    #[serde(rename = "trackId", skip_serializing_if = "Option::is_none")]
+    pub track_id: Option<TrackId>,
+    #[serde(rename = "totalPlayedSeconds", skip_serializing_if = "Option::is_none")]
+    pub total_played_seconds: Option<f64>,
+}
+
+#[instrument(skip_all)]
+pub async fn send_station_feedback(station: &str, batch_id: &str, feedback: &StationFeedback, client: &Client) -> Result<(), ApiError> {
+    client
+        .post(format!("https://api.music.yandex.net/rotor/station/{}/feedback", station))
+        .query(&[("batch-id", batch_id)])
+        .json(feedback)
+        .send()
+        .await?
+        .checked()
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use image::{
+    imageops::FilterType,
+    DynamicImage,
+    GenericImageView,
+    RgbImage,
+};
+
+use serde::Deserialize;
+
+use std::io::Write;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtMode {
+    #[default]
+    Off,
+    Auto,
+    Kitty,
+    Iterm,
+    Sixel,
+    Blocks,
+}
+
+impl ArtMode {
+    // Sixel support can't be queried without a terminal round-trip, so it is never guessed
+    pub fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if term == "xterm-kitty" || std::env::var_os("KITTY_WINDOW_ID").is_some() {
+            ArtMode::Kitty
+        } else if program == "iTerm.app" || program == "WezTerm" {
+            ArtMode::Iterm
+        } else {
+            ArtMode::Blocks
+        }
+    }
+
+    fn resolve(self) -> Self {
+        match self {
+            ArtMode::Auto => ArtMode::detect(),
+            mode => mode,
+        }
+    }
+}
+
+const COLUMNS: u32 = 32;
+const PIXELS: u32 = 256;
+
+// The escape codes that draw the cover, empty when art is off
+pub fn render(bytes: &[u8], mode: ArtMode) -> Result<Vec<u8>, image::ImageError> {
+    let mode = mode.resolve();
+    if mode == ArtMode::Off {
+        return Ok(Vec::new());
+    }
+
+    let image = image::load_from_memory(bytes)?;
+    let mut out = Vec::new();
+    match mode {
+        ArtMode::Kitty => kitty(&image, &mut out)?,
+        ArtMode::Iterm => iterm(bytes, &mut out)?,
+        ArtMode::Sixel => sixel(&image, &mut out)?,
+        _ => blocks(&image, &mut out)?,
+    }
+    writeln!(out)?;
+
+    Ok(out)
+}
+
+fn kitty(image: &DynamicImage, out: &mut impl Write) -> std::io::Result<()> {
+    let image = image.resize(PIXELS, PIXELS, FilterType::Triangle).to_rgb8();
+    let encoded = base64::encode(image.as_raw());
+    let chunks = encoded.as_bytes().chunks(4096).collect::<Vec<_>>();
+    for (n, chunk) in chunks.iter().enumerate() {
+        let more = (n + 1 < chunks.len()) as u8;
+        if n == 0 {
+            write!(out, "\x1b_Gf=24,a=T,s={},v={},c={},m={};", image.width(), image.height(), COLUMNS, more)?;
+        } else {
+            write!(out, "\x1b_Gm={};", more)?;
+        }
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+
+    Ok(())
+}
+
+fn iterm(bytes: &[u8], out: &mut impl Write) -> std::io::Result<()> {
+    write!(
+        out,
+        "\x1b]1337;File=inline=1;width={};preserveAspectRatio=1:{}\x07",
+        COLUMNS,
+        base64::encode(bytes)
+    )
+}
+
+// Quantizes to a 6x6x6 colour cube and emits one sixel band per six pixel rows
+fn sixel(image: &DynamicImage, out: &mut impl Write) -> std::io::Result<()> {
+    let image = image.resize(PIXELS, PIXELS, FilterType::Triangle).to_rgb8();
+    let level = |c: u8| (c as u32 * 5 + 127) / 255;
+    let index = |p: &image::Rgb<u8>| (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as usize;
+
+    write!(out, "\x1bPq\"1;1;{};{}", image.width(), image.height())?;
+    for i in 0..216 {
+        let (r, g, b) = (i / 36, i / 6 % 6, i % 6);
+        write!(out, "#{};2;{};{};{}", i, r * 20, g * 20, b * 20)?;
+    }
+
+    for band in (0..image.height()).step_by(6) {
+        let mut used = [false; 216];
+        for y in band..(band + 6).min(image.height()) {
+            for x in 0..image.width() {
+                used[index(image.get_pixel(x, y))] = true;
+            }
+        }
+        for color in (0..216).filter(|&color| used[color]) {
+            write!(out, "#{}", color)?;
+            for x in 0..image.width() {
+                let mut bits = 0u8;
+                for dy in 0..6 {
+                    let y = band + dy;
+                    if y < image.height() && index(image.get_pixel(x, y)) == color {
+                        bits |= 1 << dy;
+                    }
+                }
+                write!(out, "{}", (b'?' + bits) as char)?;
+            }
+            write!(out, "$")?;
+        }
+        write!(out, "-")?;
+    }
+
+    write!(out, "\x1b\\")
+}
+
+// Two pixels per cell: the upper half block takes the top one as foreground
+fn blocks(image: &DynamicImage, out: &mut impl Write) -> std::io::Result<()> {
+    let (width, height) = image.dimensions();
+    let pixel_rows = (COLUMNS * height / width.max(1)).max(2);
+    let image: RgbImage = image
+        .resize_exact(COLUMNS, pixel_rows, FilterType::Triangle)
+        .to_rgb8();
+
+    for y in (0..image.height()).step_by(2) {
+        for x in 0..image.width() {
+            let top = image.get_pixel(x, y);
+            let bottom = image.get_pixel(x, (y + 1).min(image.height() - 1));
+            write!(
+                out,
+                "{}{}▀",
+                termion::color::Fg(termion::color::Rgb(top[0], top[1], top[2])),
+                termion::color::Bg(termion::color::Rgb(bottom[0], bottom[1], bottom[2])),
+            )?;
+        }
+        writeln!(out, "{}{}", termion::color::Fg(termion::color::Reset), termion::color::Bg(termion::color::Reset))?;
+    }
+
+    Ok(())
+}
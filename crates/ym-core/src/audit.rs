@@ -0,0 +1,59 @@
+use crate::api::TrackId;
+use crate::cache::AudioCache;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Stale,
+    Changed,
+    Orphaned,
+}
+
+impl Category {
+    pub const ALL: [Category; 3] = [Category::Stale, Category::Changed, Category::Orphaned];
+}
+
+impl FromStr for Category {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stale" => Ok(Category::Stale),
+            "changed" => Ok(Category::Changed),
+            "orphaned" => Ok(Category::Orphaned),
+            _ => Err("Audit categories are stale, changed, orphaned or all"),
+        }
+    }
+}
+
+// What an audit found, nothing changes until a category is reconciled
+#[derive(Debug, Default)]
+pub struct Report {
+    // Audio playback cached for this account, of tracks that are neither liked, queued nor in
+    // any playlist. Downloads and other accounts' files are never stale
+    pub stale: Vec<PathBuf>,
+    pub stale_bytes: u64,
+    // Source keys and titles of playlists edited remotely, the track saved to resume from is gone
+    pub changed: Vec<(String, String)>,
+    // Source keys of saved positions whose playlist was deleted
+    pub orphaned: Vec<String>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.stale.is_empty() && self.changed.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+pub fn stale_audio(cache: &AudioCache, keep: &HashSet<TrackId>, report: &mut Report) {
+    for (id, path) in cache.prefetched() {
+        if keep.contains(&id) {
+            continue;
+        }
+        report.stale_bytes += path.metadata().map_or(0, |metadata| metadata.len());
+        report.stale.push(path);
+    }
+}
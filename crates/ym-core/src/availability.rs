@@ -0,0 +1,120 @@
+use crate::api::{
+    fetch_tracks,
+    liked_tracks_infos,
+    ApiError,
+    SharedClient,
+    Track,
+    TrackId,
+    Uid,
+    TRACKS_PAGE,
+};
+use crate::config;
+
+use reqwest::Client;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use tokio::{
+    runtime::Handle,
+    time::{
+        interval,
+        Duration,
+    },
+};
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+// Liked tracks that were unavailable at the last check
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AvailabilityStore {
+    unavailable: HashSet<TrackId>,
+}
+
+fn availability_path(uid: Uid) -> PathBuf {
+    config::account_path(config::data_dir(), uid, "availability.json")
+}
+
+impl AvailabilityStore {
+    fn load(uid: Uid) -> Self {
+        std::fs::read(availability_path(uid))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, uid: Uid) -> std::io::Result<()> {
+        let path = availability_path(uid);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    // Gone since the last check
+    pub lost: Vec<Track>,
+    // Titles of the tracks back since the last check
+    pub restored: Vec<String>,
+    pub available: Vec<Track>,
+}
+
+// Fetches the metadata of every liked track anew, the liked cache never goes stale on its own
+pub async fn check(uid: Uid, client: &Client) -> Result<Report, ApiError> {
+    let ids = liked_tracks_infos(uid, client).await?
+        .into_iter()
+        .map(|info| info.id)
+        .collect::<Vec<_>>();
+    let mut tracks = Vec::with_capacity(ids.len());
+    for page in ids.chunks(TRACKS_PAGE) {
+        tracks.extend(fetch_tracks(page, client).await?);
+    }
+
+    let mut store = AvailabilityStore::load(uid);
+    let mut report = Report::default();
+    let mut unavailable = HashSet::new();
+    for track in tracks {
+        let known = store.unavailable.contains(&track.id);
+        if track.available {
+            if known {
+                report.restored.push(track.to_string());
+            }
+            report.available.push(track);
+        } else {
+            unavailable.insert(track.id);
+            if !known {
+                report.lost.push(track);
+            }
+        }
+    }
+
+    store.unavailable = unavailable;
+    if let Err(err) = store.save(uid) {
+        tracing::warn!("Failed to save track availability: {}", err);
+    }
+
+    Ok(report)
+}
+
+// Checks right away and then every period, stops once `report` returns false
+pub fn watch(client: SharedClient, uid: Uid, period: Duration, mut report: impl FnMut(Report) -> bool + Send + 'static) {
+    Handle::current().spawn(async move {
+        let mut timer = interval(period);
+        loop {
+            timer.tick().await;
+            match check(uid, &client.get()).await {
+                Ok(checked) => {
+                    if !report(checked) {
+                        return;
+                    }
+                },
+                Err(err) => tracing::warn!("Failed to check track availability: {}", err),
+            }
+        }
+    });
+}
@@ -0,0 +1,44 @@
+use crate::api::TrackId;
+use crate::config;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Blacklist {
+    tracks: HashSet<TrackId>,
+}
+
+fn blacklist_path() -> PathBuf {
+    config::data_dir().join("blacklist.json")
+}
+
+impl Blacklist {
+    pub fn load() -> Self {
+        std::fs::read(blacklist_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = blacklist_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn contains(&self, id: TrackId) -> bool {
+        self.tracks.contains(&id)
+    }
+
+    pub fn insert(&mut self, id: TrackId) {
+        self.tracks.insert(id);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::api::{
+    Quality,
+    Track,
+    TrackId,
+};
+use crate::cache::AudioCache;
+
+use serde::Serialize;
+
+use std::fmt::Write;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+struct BundleTrack<'a> {
+    file: String,
+    id: TrackId,
+    title: String,
+    artists: Vec<&'a str>,
+    album: Option<&'a str>,
+    duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Exported {
+    pub copied: usize,
+    // Titles of tracks left out for not being cached
+    pub missing: Vec<String>,
+}
+
+// FAT formatted players reject these, so they never make it into file names
+pub(crate) fn file_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_owned()
+}
+
+// Cached audio numbered in order, next to an M3U and a metadata.json, copyable onto any player as is
+pub fn export(dir: &Path, tracks: &[&Track], cache: &AudioCache, quality: Quality) -> std::io::Result<Exported> {
+    std::fs::create_dir_all(dir)?;
+    let mut exported = Exported::default();
+    let mut playlist = String::from("#EXTM3U\n");
+    let mut metadata = Vec::new();
+    for track in tracks {
+        let Some(cached) = cache.file(track.id, quality) else {
+            exported.missing.push(track.full_title());
+            continue;
+        };
+        let artists = track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>();
+        let name = format!("{} - {}", artists.join(", "), track.full_title());
+        let file = format!("{:03} {}.{}", exported.copied + 1, file_safe(&name), quality.codec);
+        std::fs::copy(cached, dir.join(&file))?;
+
+        let seconds = track.duration.map_or(-1, |ms| (ms / 1000) as i64);
+        let _ = writeln!(playlist, "#EXTINF:{},{}\n{}", seconds, name, file);
+        metadata.push(BundleTrack {
+            file,
+            id: track.id,
+            title: track.full_title(),
+            artists,
+            album: track.albums.first().map(|album| album.title.as_str()),
+            duration_ms: track.duration,
+        });
+        exported.copied += 1;
+    }
+
+    std::fs::write(dir.join("playlist.m3u"), playlist)?;
+    std::fs::write(dir.join("metadata.json"), serde_json::to_vec_pretty(&metadata)?)?;
+    Ok(exported)
+}
@@ -0,0 +1,256 @@
+use crate::api::{
+    Quality,
+    TrackData,
+    TrackId,
+    Uid,
+};
+use crate::downloads::DownloadKind;
+
+use rodio::Decoder;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::io::Cursor;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::Duration;
+
+// The same stream, length and decoded samples means the same recording under another id.
+// All of them, an opening window alone is silence often enough to match unrelated tracks
+fn fingerprint(data: &TrackData, duration: Duration) -> Option<String> {
+    let decoder = Decoder::new(Cursor::new(data.data.get_ref().clone())).ok()?;
+    let mut context = md5::Context::new();
+    let mut decoded = 0;
+    for sample in decoder {
+        context.consume(sample.to_le_bytes());
+        decoded += 1;
+    }
+    (decoded > 0).then(|| format!("{}-{}-{}-{:x}", data.codec, data.bitrate, duration.as_secs(), context.compute()))
+}
+
+// Hard links share it, so linked duplicates only count once towards the size
+#[cfg(unix)]
+fn file_key(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_key(_: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// Why a file is in the cache and for whom, profiles share the cache
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Origin {
+    kind: DownloadKind,
+    uid: Uid,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioCache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+    // The account files stored from here are recorded for
+    uid: Uid,
+    // Fingerprints and origins are read and written by the download worker and the player alike
+    index_lock: Arc<Mutex<()>>,
+}
+
+impl AudioCache {
+    pub fn new(dir: PathBuf, max_size: Option<u64>, uid: Uid) -> Self {
+        AudioCache { dir, max_size, uid, index_lock: Arc::default() }
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("fingerprints.json")
+    }
+
+    fn origins_path(&self) -> PathBuf {
+        self.dir.join("origins.json")
+    }
+
+    // Origins by file name, files cached before they were recorded have none
+    fn load_origins(&self) -> HashMap<String, Origin> {
+        std::fs::read(self.origins_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_origins(&self, origins: &HashMap<String, Origin>) -> std::io::Result<()> {
+        std::fs::write(self.origins_path(), serde_json::to_vec(origins)?)
+    }
+
+    fn set_origin(&self, path: &Path, kind: DownloadKind) -> std::io::Result<()> {
+        let Some(name) = path.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+            return Ok(());
+        };
+        let mut origins = self.load_origins();
+        origins.insert(name, Origin { kind, uid: self.uid });
+        self.save_origins(&origins)
+    }
+
+    // A cached track someone asked to download is theirs to keep, however it got cached
+    pub fn pin(&self, id: TrackId, quality: Quality) {
+        let _index = self.index_lock.lock().unwrap();
+        let path = self.path(id, quality);
+        if path.is_file() {
+            if let Err(err) = self.set_origin(&path, DownloadKind::Explicit) {
+                tracing::warn!("Failed to record {} as downloaded: {}", path.display(), err);
+            }
+        }
+    }
+
+    // Files this account's playback cached by itself, the only ones an audit may call stale
+    pub fn prefetched(&self) -> Vec<(TrackId, PathBuf)> {
+        let origins = self.load_origins();
+        self.entries()
+            .into_iter()
+            .filter(|(_, path)| {
+                let name = path.file_name().map(|name| name.to_string_lossy());
+                name.and_then(|name| origins.get(name.as_ref()))
+                    .is_some_and(|origin| origin.kind == DownloadKind::Prefetch && origin.uid == self.uid)
+            })
+            .collect()
+    }
+
+    // File names by the fingerprint of their audio
+    fn load_index(&self) -> HashMap<String, String> {
+        std::fs::read(self.index_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    // Keyed by the requested quality, so changing the preference never serves stale streams
+    fn path(&self, id: TrackId, quality: Quality) -> PathBuf {
+        self.dir.join(format!("{}-{}-{}", id, quality.codec, quality.bitrate))
+    }
+
+    pub fn contains(&self, id: TrackId, quality: Quality) -> bool {
+        self.path(id, quality).is_file()
+    }
+
+    pub fn file(&self, id: TrackId, quality: Quality) -> Option<PathBuf> {
+        Some(self.path(id, quality)).filter(|path| path.is_file())
+    }
+
+    // Every cached file with the track it holds, in any quality
+    pub fn entries(&self) -> Vec<(TrackId, PathBuf)> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let id = path.file_name()?.to_str()?.split('-').next()?.parse().ok()?;
+                Some((id, path))
+            })
+            .collect()
+    }
+
+    // Forgets the fingerprint along with the file, so nothing gets linked to it later
+    pub fn remove(&self, path: &Path) -> std::io::Result<()> {
+        let _index = self.index_lock.lock().unwrap();
+        std::fs::remove_file(path)?;
+        let Some(name) = path.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+            return Ok(());
+        };
+        let mut index = self.load_index();
+        let before = index.len();
+        index.retain(|_, file| *file != name);
+        if index.len() != before {
+            std::fs::write(self.index_path(), serde_json::to_vec(&index)?)?;
+        }
+        let mut origins = self.load_origins();
+        if origins.remove(&name).is_some() {
+            self.save_origins(&origins)?;
+        }
+        Ok(())
+    }
+
+    pub fn load(&self, id: TrackId, quality: Quality) -> Option<TrackData> {
+        let bytes = std::fs::read(self.path(id, quality)).ok()?;
+        Some(
+            TrackData {
+                id,
+                codec: quality.codec,
+                bitrate: quality.bitrate,
+                loaded: std::time::Instant::now(),
+                data: Cursor::new(bytes.into()),
+            }
+        )
+    }
+
+    pub fn size(&self) -> u64 {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        let mut seen = HashSet::new();
+        entries
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|metadata| file_key(metadata).is_none_or(|key| seen.insert(key)))
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    // Bytes left before hitting the size limit, None when there is no limit
+    pub fn remaining(&self) -> Option<u64> {
+        self.max_size.map(|max_size| max_size.saturating_sub(self.size()))
+    }
+
+    // Audio already cached under another id gets hard linked instead of stored twice.
+    // Decoding the whole track and the file work happen off the async runtime
+    pub async fn store(&self, data: &TrackData, quality: Quality, duration: Option<Duration>, kind: DownloadKind) -> std::io::Result<()> {
+        let (cache, data) = (self.clone(), data.clone());
+        tokio::task::spawn_blocking(move || cache.store_blocking(&data, quality, duration, kind))
+            .await
+            .map_err(std::io::Error::other)?
+    }
+
+    fn store_blocking(&self, data: &TrackData, quality: Quality, duration: Option<Duration>, kind: DownloadKind) -> std::io::Result<()> {
+        let path = self.path(data.id, quality);
+        let fingerprint = duration.and_then(|duration| fingerprint(data, duration));
+        let _index = self.index_lock.lock().unwrap();
+        let mut index = self.load_index();
+        if let Some(fingerprint) = &fingerprint {
+            let existing = index.get(fingerprint).map(|name| self.dir.join(name));
+            if let Some(existing) = existing.filter(|existing| existing.is_file() && *existing != path) {
+                match std::fs::hard_link(&existing, &path) {
+                    Ok(()) => {
+                        tracing::info!("Track {} is the same audio as {}, linked it", data.id, existing.display());
+                        return self.set_origin(&path, kind);
+                    },
+                    Err(err) => tracing::debug!("Failed to link {} to {}: {}", path.display(), existing.display(), err),
+                }
+            }
+        }
+
+        if self.remaining().is_some_and(|remaining| remaining < data.data.get_ref().len() as u64) {
+            return Err(std::io::Error::other("cache size limit reached"));
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&path, data.data.get_ref())?;
+        self.set_origin(&path, kind)?;
+
+        if let (Some(fingerprint), Some(name)) = (fingerprint, path.file_name()) {
+            index.insert(fingerprint, name.to_string_lossy().into_owned());
+            std::fs::write(self.index_path(), serde_json::to_vec(&index)?)?;
+        }
+        Ok(())
+    }
+}
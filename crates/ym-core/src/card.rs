@@ -0,0 +1,165 @@
+use ab_glyph::{
+    Font,
+    FontVec,
+    PxScale,
+    ScaleFont,
+};
+
+use image::{
+    imageops::{
+        self,
+        FilterType,
+    },
+    Rgb,
+    RgbImage,
+};
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::Duration;
+
+const WIDTH: u32 = 900;
+const HEIGHT: u32 = 300;
+const MARGIN: u32 = 32;
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 28]);
+const FOREGROUND: Rgb<u8> = Rgb([240, 240, 240]);
+const MUTED: Rgb<u8> = Rgb([150, 150, 160]);
+const ACCENT: Rgb<u8> = Rgb([255, 204, 0]);
+
+// Tried in order when the config doesn't name a font
+const FONT_PATHS: [&str; 4] = [
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+];
+
+pub struct CardInfo<'a> {
+    pub title: &'a str,
+    pub artist: &'a str,
+    pub position: Duration,
+    pub duration: Option<Duration>,
+}
+
+pub fn load_font(configured: Option<&Path>) -> Option<FontVec> {
+    configured
+        .into_iter()
+        .chain(FONT_PATHS.iter().map(Path::new))
+        .find_map(|path| FontVec::try_from_vec(std::fs::read(path).ok()?).ok())
+}
+
+// Cover on the left, title, artist and a progress bar on the right.
+// Without a font the card still gets the cover and the bar
+pub fn render(cover: Option<&[u8]>, info: &CardInfo, font: Option<&FontVec>) -> Result<RgbImage, image::ImageError> {
+    let mut card = RgbImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+
+    let mut left = MARGIN;
+    if let Some(bytes) = cover {
+        let size = HEIGHT - 2 * MARGIN;
+        let cover = image::load_from_memory(bytes)?.resize_to_fill(size, size, FilterType::Triangle).to_rgb8();
+        imageops::replace(&mut card, &cover, MARGIN as i64, MARGIN as i64);
+        left += size + MARGIN;
+    }
+    let width = WIDTH - left - MARGIN;
+
+    if let Some(font) = font {
+        let line = |y, size, color| Line { x: left, y, width, size, color };
+        draw_text(&mut card, font, info.title, line(MARGIN + 20, 44.0, FOREGROUND));
+        draw_text(&mut card, font, info.artist, line(MARGIN + 90, 30.0, MUTED));
+        let time = |time: Duration| format!("{}:{:02}", time.as_secs() / 60, time.as_secs() % 60);
+        let progress = match info.duration {
+            Some(duration) => format!("{} / {}", time(info.position), time(duration)),
+            None => time(info.position),
+        };
+        draw_text(&mut card, font, &progress, line(HEIGHT - MARGIN - 60, 24.0, MUTED));
+    }
+
+    let bar_y = HEIGHT - MARGIN - 12;
+    let filled = info.duration
+        .filter(|duration| !duration.is_zero())
+        .map_or(0.0, |duration| (info.position.as_secs_f32() / duration.as_secs_f32()).min(1.0));
+    fill(&mut card, left, bar_y, width, 6, MUTED);
+    fill(&mut card, left, bar_y, (width as f32 * filled) as u32, 6, ACCENT);
+
+    Ok(card)
+}
+
+fn fill(card: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>) {
+    for py in y..(y + height).min(card.height()) {
+        for px in x..(x + width).min(card.width()) {
+            card.put_pixel(px, py, color);
+        }
+    }
+}
+
+// Where a line of text goes and how it looks, y is its top
+struct Line {
+    x: u32,
+    y: u32,
+    width: u32,
+    size: f32,
+    color: Rgb<u8>,
+}
+
+// Characters past the line's width are cut with an ellipsis
+fn draw_text(card: &mut RgbImage, font: &FontVec, text: &str, line: Line) {
+    let Line { x, y, width, size, color } = line;
+    let font = font.as_scaled(PxScale::from(size));
+    let ellipsis = font.h_advance(font.glyph_id('…'));
+    let mut caret = x as f32;
+    let baseline = y as f32 + font.ascent();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let id = font.glyph_id(c);
+        let advance = font.h_advance(id);
+        let last = chars.peek().is_none();
+        let (c, id) = if !last && caret + advance + ellipsis > (x + width) as f32 {
+            ('…', font.glyph_id('…'))
+        } else {
+            (c, id)
+        };
+        let glyph = id.with_scale_and_position(font.scale(), ab_glyph::point(caret, baseline));
+        if let Some(outline) = font.outline_glyph(glyph) {
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let (px, py) = (bounds.min.x as i32 + gx as i32, bounds.min.y as i32 + gy as i32);
+                if px < 0 || py < 0 || px as u32 >= card.width() || py as u32 >= card.height() {
+                    return;
+                }
+                let pixel = card.get_pixel_mut(px as u32, py as u32);
+                for channel in 0..3 {
+                    let blended = pixel[channel] as f32 * (1.0 - coverage) + color[channel] as f32 * coverage;
+                    pixel[channel] = blended as u8;
+                }
+            });
+        }
+        if c == '…' {
+            break;
+        }
+        caret += advance;
+    }
+}
+
+pub fn card_path() -> PathBuf {
+    std::env::temp_dir().join("yandex_music_tui_card.png")
+}
+
+// Whichever clipboard tool the session has, Wayland first
+pub fn copy_to_clipboard(path: &Path) -> bool {
+    let commands: [(&str, &[&str]); 2] = [
+        ("wl-copy", &["--type", "image/png"]),
+        ("xclip", &["-selection", "clipboard", "-t", "image/png"]),
+    ];
+    commands.iter().any(|(program, args)| {
+        let Ok(file) = std::fs::File::open(path) else {
+            return false;
+        };
+        std::process::Command::new(program)
+            .args(*args)
+            .stdin(file)
+            .status()
+            .is_ok_and(|status| status.success())
+    })
+}
@@ -0,0 +1,431 @@
+use crate::api::{
+    Quality,
+    Uid,
+};
+use crate::art::ArtMode;
+use crate::eq::Band;
+use crate::player::SwitchMode;
+use crate::presets::AutoPreset;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // OAuth token of the account to play from, the login assistant fills it in unless the
+    // token goes to the keyring. YANDEX_MUSIC_TOKEN overrides both
+    pub token: Option<String>,
+    // Other accounts by name, picked with --profile or the profile command
+    pub profiles: HashMap<String, ProfileConfig>,
+    pub login: LoginConfig,
+    pub quality: Quality,
+    pub cache: CacheConfig,
+    pub availability: AvailabilityConfig,
+    pub limits: LimitsConfig,
+    pub cover_art: ArtMode,
+    // now or after-current, commands loading a source take a trailing now or after instead
+    pub switch_mode: SwitchMode,
+    pub skip_played: bool,
+    // Leaves out the promos and idents stations mix in between tracks
+    pub skip_jingles: bool,
+    // Quits when a playlist or the liked tracks fail to load instead of keeping the current queue and offering retry
+    pub strict_loading: bool,
+    pub announce: AnnounceConfig,
+    pub ynison: YnisonConfig,
+    pub no_repeats: bool,
+    // Evens out loudness between tracks using their R128 measurements
+    pub normalize: bool,
+    // In percent like vp, otherwise the volume of the last session is restored
+    pub startup_volume: Option<f32>,
+    pub equalizer: EqualizerConfig,
+    // Checked in order on every track change, like [[auto_presets]] genres = ["classicalmusic"] volume = 80
+    pub auto_presets: Vec<AutoPreset>,
+    // Commands run instead when the key command is entered twice in a row, like next = "next 5"
+    pub double_press: HashMap<String, String>,
+    // In milliseconds
+    pub double_press_window: u64,
+    // Font for share cards, a common system one is looked up otherwise
+    pub card_font: Option<PathBuf>,
+    pub fetch_concurrency: usize,
+    // Tracks past this many aren't queued, radio forgets its oldest played tracks instead
+    pub max_queue_length: usize,
+    // In MiB, tracks that would take the audio held in memory past it aren't prefetched
+    pub max_audio_memory: u64,
+    // Like "127.0.0.1:6600", MPD clients are served only when set.
+    // Anyone who can reach it controls the player, so keep it on loopback unless mpd_password is set
+    pub mpd_address: Option<String>,
+    // Clients send it with the password command before they may run anything outside mpd_allowed
+    pub mpd_password: Option<String>,
+    // What clients may run without the password, by default they can look but not change anything
+    pub mpd_allowed: Vec<String>,
+    // A file or FIFO that gets the status as a JSON line on every change, for waybar or polybar
+    pub status_file: Option<PathBuf>,
+    // Per-module levels like "info,ym_core::downloads=debug"
+    pub log_filter: String,
+    pub yandex_feedback: bool,
+    pub lastfm: Option<LastFmConfig>,
+    pub listenbrainz: Option<ListenBrainzConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            token: None,
+            profiles: HashMap::new(),
+            login: LoginConfig::default(),
+            quality: Quality::default(),
+            cache: CacheConfig::default(),
+            availability: AvailabilityConfig::default(),
+            limits: LimitsConfig::default(),
+            cover_art: ArtMode::default(),
+            switch_mode: SwitchMode::AfterCurrent,
+            skip_played: false,
+            skip_jingles: false,
+            strict_loading: false,
+            announce: AnnounceConfig::default(),
+            ynison: YnisonConfig::default(),
+            no_repeats: false,
+            normalize: false,
+            startup_volume: None,
+            equalizer: EqualizerConfig::default(),
+            auto_presets: Vec::new(),
+            double_press: HashMap::new(),
+            double_press_window: 400,
+            card_font: None,
+            fetch_concurrency: 16,
+            max_queue_length: 10_000,
+            max_audio_memory: 512,
+            mpd_address: None,
+            mpd_password: None,
+            mpd_allowed: [
+                "status", "currentsong", "playlistinfo", "ping", "clearerror", "outputs", "tagtypes", "commands",
+                "notcommands", "urlhandlers", "decoders",
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+            status_file: None,
+            log_filter: "info".to_owned(),
+            yandex_feedback: true,
+            lastfm: None,
+            listenbrainz: None,
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub dir: Option<PathBuf>,
+    // In MiB
+    pub max_size: Option<u64>,
+    // Bulk downloads estimated above this many MiB wait for a confirmation
+    pub confirm_above: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AvailabilityConfig {
+    // In hours, liked tracks are only watched when set
+    pub check_every: Option<u64>,
+    // Saves the liked tracks still available to the cache as soon as any disappear
+    pub auto_download: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    // Playback pauses once this much was played today
+    pub daily_minutes: Option<u64>,
+    // Like "22:00" and "07:00", playback stays paused in between
+    pub quiet_from: Option<String>,
+    pub quiet_until: Option<String>,
+    // Lifts the limits until midnight with the unlock command
+    pub unlock_code: Option<String>,
+}
+
+// Only used when built with the ynison feature
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct YnisonConfig {
+    pub enabled: bool,
+    // The name the official apps list this player under
+    pub device_name: String,
+}
+
+impl Default for YnisonConfig {
+    fn default() -> Self {
+        YnisonConfig {
+            enabled: true,
+            device_name: "yandex_music_tui".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AnnounceConfig {
+    // Speaks the artist and title as each track starts, the announce command toggles it
+    pub enabled: bool,
+    // Like "espeak-ng -v en", the text is passed as the last argument. spd-say, espeak-ng,
+    // espeak and say are tried otherwise
+    pub command: Option<String>,
+    // Music volume while speaking, relative to the current one
+    pub duck_level: f32,
+}
+
+impl Default for AnnounceConfig {
+    fn default() -> Self {
+        AnnounceConfig {
+            enabled: false,
+            command: None,
+            duck_level: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct EqualizerConfig {
+    pub preset: String,
+    // Extra presets by name, each a list of { frequency, gain, q } bands
+    pub presets: HashMap<String, Vec<Band>>,
+}
+
+impl Default for EqualizerConfig {
+    fn default() -> Self {
+        EqualizerConfig {
+            preset: "flat".to_owned(),
+            presets: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LoginConfig {
+    // The app registered for Yandex Music clients, one of your own works too
+    pub client_id: String,
+    // Catches the redirect on localhost instead of asking to paste it, the app has to allow
+    // http://localhost:<port>/ as a redirect
+    pub redirect_port: Option<u16>,
+}
+
+impl Default for LoginConfig {
+    fn default() -> Self {
+        LoginConfig {
+            client_id: "23cabbbdc6cd418abb4b39c32c41195d".to_owned(),
+            redirect_port: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LastFmConfig {
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    pub api_key: String,
+    pub api_secret: String,
+    pub session_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListenBrainzConfig {
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    pub token: String,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "failed to read config: {}", err),
+            ConfigError::Parse(err) => write!(f, "failed to parse config: {}", err),
+        }
+    }
+}
+
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default()
+        .join("yandex_music_tui")
+}
+
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_default()
+        .join("yandex_music_tui")
+}
+
+// Sockets live here, falling back to the temp dir where XDG_RUNTIME_DIR isn't set
+pub fn runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+pub fn cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_default()
+        .join("yandex_music_tui")
+}
+
+// Files of one account live under its uid, so profiles never read each other's.
+// One saved before that is taken over by the first account to look for it
+pub fn account_path(dir: PathBuf, uid: Uid, name: &str) -> PathBuf {
+    let path = dir.join("accounts").join(uid.to_string()).join(name);
+    let shared = dir.join(name);
+    if !path.exists() && shared.is_file() {
+        let moved = std::fs::create_dir_all(path.parent().unwrap_or(&dir)).and_then(|_| std::fs::rename(&shared, &path));
+        if let Err(err) = moved {
+            tracing::warn!("Failed to move {} to {}: {}", shared.display(), path.display(), err);
+        }
+    }
+    path
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+pub fn load() -> Result<Config, ConfigError> {
+    let text = match std::fs::read_to_string(config_path()) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(ConfigError::Io(err)),
+    };
+
+    toml::from_str(&text).map_err(ConfigError::Parse)
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(profile: Option<&str>) -> keyring::Result<keyring::Entry> {
+    match profile {
+        Some(profile) => keyring::Entry::new("yandex_music_tui", &format!("token:{}", profile)),
+        None => keyring::Entry::new("yandex_music_tui", "token"),
+    }
+}
+
+// The environment wins over the keyring, which wins over the config file.
+// None is the default profile, the environment only stands in for it
+pub fn token(config: &Config, profile: Option<&str>) -> Option<String> {
+    if profile.is_none() {
+        if let Some(token) = std::env::var("YANDEX_MUSIC_TOKEN").ok().filter(|token| !token.is_empty()) {
+            return Some(token);
+        }
+    }
+    #[cfg(feature = "keyring")]
+    match keyring_entry(profile).and_then(|entry| entry.get_password()) {
+        Ok(token) => return Some(token),
+        Err(keyring::Error::NoEntry) => {},
+        Err(err) => tracing::warn!("Failed to read the token from the keyring: {}", err),
+    }
+    match profile {
+        Some(profile) => config.profiles.get(profile)?.token.clone(),
+        None => config.token.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenLocation {
+    Keyring,
+    Config,
+}
+
+impl std::fmt::Display for TokenLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenLocation::Keyring => write!(f, "the system keyring"),
+            TokenLocation::Config => write!(f, "{}", config_path().display()),
+        }
+    }
+}
+
+// Goes to the keyring when there is one, the config file is the fallback
+pub fn save_token(token: &str, profile: Option<&str>) -> std::io::Result<TokenLocation> {
+    #[cfg(feature = "keyring")]
+    match keyring_entry(profile).and_then(|entry| entry.set_password(token)) {
+        Ok(()) => {
+            // A plaintext copy left behind would defeat the point
+            write_token_line(None, profile)?;
+            return Ok(TokenLocation::Keyring);
+        },
+        Err(err) => tracing::warn!("No keyring to keep the token in, saving it to the config: {}", err),
+    }
+    write_token_line(Some(token), profile)?;
+    Ok(TokenLocation::Config)
+}
+
+// Edits the token line in place so the rest of the file keeps its comments and layout.
+// Other tables have tokens of their own, so only the top level or the profile's table is looked at
+fn write_token_line(token: Option<&str>, profile: Option<&str>) -> std::io::Result<()> {
+    let path = config_path();
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    let target = profile.map(|profile| format!("[profiles.{}]", profile));
+    let line = token.map(|token| format!("token = {}", toml::Value::String(token.to_owned())));
+    let mut replaced = false;
+    let mut table = None;
+    let mut header = None;
+    let mut lines = Vec::new();
+    for existing in text.lines() {
+        if existing.trim_start().starts_with('[') {
+            table = Some(existing.replace(['"', ' ', '\t'], ""));
+            if table == target {
+                header = Some(lines.len());
+            }
+        }
+        let key = existing.split('=').next().unwrap_or_default().trim();
+        if key == "token" && table == target && !replaced {
+            replaced = true;
+            lines.extend(line.clone());
+        } else {
+            lines.push(existing.to_owned());
+        }
+    }
+    if !replaced {
+        match (line, target, header) {
+            (None, _, _) => return Ok(()),
+            (Some(line), None, _) => lines.insert(0, line),
+            (Some(line), Some(_), Some(header)) => lines.insert(header + 1, line),
+            (Some(line), Some(target), None) => lines.extend([String::new(), target, line]),
+        }
+    }
+
+    std::fs::create_dir_all(config_dir())?;
+    std::fs::write(&path, lines.join("\n") + "\n")?;
+    // Anyone reading the token can act as the account
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,395 @@
+use crate::say;
+use crate::api::{
+    open_track_stream,
+    ApiError,
+    SharedClient,
+    Quality,
+    TrackData,
+    TrackId,
+};
+use crate::cache::AudioCache;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::sync::{
+    atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    Arc,
+    Mutex,
+};
+
+use tokio::{
+    runtime::Handle,
+    sync::{
+        oneshot,
+        Notify,
+        OwnedSemaphorePermit,
+        Semaphore,
+    },
+    time::{
+        sleep,
+        Duration,
+        Instant,
+    },
+};
+
+// Downloads running at once, a paused one gives its slot up until resumed
+const ACTIVE_DOWNLOADS: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadKind {
+    Prefetch,
+    Explicit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadState {
+    Pending,
+    Active,
+    Paused,
+    Cancelled,
+    Failed(String),
+}
+
+struct DownloadJob {
+    key: u64,
+    started: bool,
+    id: TrackId,
+    quality: Quality,
+    title: String,
+    // Lets the cache spot the same audio under another id
+    duration: Option<Duration>,
+    kind: DownloadKind,
+    state: DownloadState,
+    downloaded: u64,
+    total: Option<u64>,
+    resumed: Option<(Instant, u64)>,
+    result: Option<oneshot::Sender<Result<TrackData, ApiError>>>,
+}
+
+impl DownloadJob {
+    // The player dropped the receiver of a prefetch it no longer expects, e.g. after skipping past it
+    fn orphaned(&self) -> bool {
+        self.kind == DownloadKind::Prefetch && self.result.as_ref().is_some_and(|sender| sender.is_closed())
+    }
+
+    fn speed(&self) -> Option<f64> {
+        let (since, downloaded) = self.resumed?;
+        let elapsed = since.elapsed().as_secs_f64();
+        (elapsed > 0.0).then(|| (self.downloaded - downloaded) as f64 / elapsed)
+    }
+}
+
+impl std::fmt::Display for DownloadJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            DownloadKind::Prefetch => "prefetch",
+            DownloadKind::Explicit => "download",
+        };
+        write!(f, "[{}] {} ", kind, self.title)?;
+        match &self.state {
+            DownloadState::Pending => write!(f, "pending"),
+            DownloadState::Paused => write!(f, "paused at {} KiB", self.downloaded / 1024),
+            DownloadState::Cancelled => write!(f, "cancelled"),
+            DownloadState::Failed(err) => write!(f, "failed: {}", err),
+            DownloadState::Active => {
+                write!(f, "{} KiB", self.downloaded / 1024)?;
+                if let Some(total) = self.total {
+                    write!(f, " / {} KiB", total / 1024)?;
+                }
+                if let Some(speed) = self.speed() {
+                    write!(f, " at {:.1} KiB/s", speed / 1024.0)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DownloadManager {
+    jobs: Arc<Mutex<Vec<DownloadJob>>>,
+    next_key: Arc<AtomicU64>,
+    wake: Arc<Notify>,
+    slots: Arc<Semaphore>,
+    last_speed: Arc<Mutex<Option<f64>>>,
+}
+
+impl DownloadManager {
+    pub fn new(client: SharedClient, cache: Option<AudioCache>) -> Self {
+        let manager = DownloadManager {
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            next_key: Arc::new(AtomicU64::new(0)),
+            wake: Arc::new(Notify::new()),
+            slots: Arc::new(Semaphore::new(ACTIVE_DOWNLOADS)),
+            last_speed: Arc::new(Mutex::new(None)),
+        };
+        Handle::current().spawn(manager.clone().run(client, cache));
+        manager
+    }
+
+    // Prefetches go ahead of explicit downloads since playback is waiting on them
+    pub fn enqueue(
+        &self,
+        id: TrackId,
+        quality: Quality,
+        title: String,
+        duration: Option<Duration>,
+        kind: DownloadKind,
+    ) -> oneshot::Receiver<Result<TrackData, ApiError>> {
+        let (sender, receiver) = oneshot::channel();
+        let job = DownloadJob {
+            key: self.next_key.fetch_add(1, Ordering::Relaxed),
+            started: false,
+            id,
+            quality,
+            title,
+            duration,
+            kind,
+            state: DownloadState::Pending,
+            downloaded: 0,
+            total: None,
+            resumed: None,
+            result: Some(sender),
+        };
+
+        let mut jobs = self.jobs.lock().unwrap();
+        match kind {
+            DownloadKind::Prefetch => {
+                let position = jobs
+                    .iter()
+                    .position(|job| job.kind == DownloadKind::Explicit && job.state == DownloadState::Pending)
+                    .unwrap_or(jobs.len());
+                jobs.insert(position, job);
+            },
+            DownloadKind::Explicit => jobs.push(job),
+        }
+        drop(jobs);
+        self.wake.notify_one();
+
+        receiver
+    }
+
+    pub fn print(&self) {
+        let jobs = self.jobs.lock().unwrap();
+        if jobs.is_empty() {
+            say!("No downloads");
+        }
+        for (n, job) in jobs.iter().enumerate() {
+            say!("{}. {}", n, job);
+        }
+    }
+
+    pub fn pause(&self, n: usize) -> bool {
+        self.update(n, |job| {
+            if matches!(job.state, DownloadState::Pending | DownloadState::Active) {
+                job.state = DownloadState::Paused;
+                job.resumed = None;
+            }
+        })
+    }
+
+    pub fn resume(&self, n: usize) -> bool {
+        let found = self.update(n, |job| {
+            if job.state == DownloadState::Paused {
+                job.state = if job.started { DownloadState::Active } else { DownloadState::Pending };
+                job.resumed = Some((Instant::now(), job.downloaded));
+            }
+        });
+        self.wake.notify_one();
+        found
+    }
+
+    pub fn cancel(&self, n: usize) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        if n >= jobs.len() {
+            return false;
+        }
+        // The worker notices the cancellation and drops the job itself
+        if jobs[n].started && matches!(jobs[n].state, DownloadState::Active | DownloadState::Paused) {
+            jobs[n].state = DownloadState::Cancelled;
+        } else {
+            jobs.remove(n);
+        }
+        true
+    }
+
+    pub fn move_to_front(&self, n: usize) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        if n >= jobs.len() {
+            return false;
+        }
+        let job = jobs.remove(n);
+        jobs.insert(0, job);
+        true
+    }
+
+    // Bytes per second of the last finished download
+    pub fn last_speed(&self) -> Option<f64> {
+        *self.last_speed.lock().unwrap()
+    }
+
+    fn update(&self, n: usize, f: impl FnOnce(&mut DownloadJob)) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get_mut(n) {
+            Some(job) => {
+                f(job);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn next_pending(&self) -> Option<(u64, TrackId, Quality, Option<Duration>, DownloadKind)> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|job| !(job.orphaned() && job.state == DownloadState::Pending));
+        let job = jobs.iter_mut().find(|job| job.state == DownloadState::Pending)?;
+        job.state = DownloadState::Active;
+        job.started = true;
+        job.resumed = Some((Instant::now(), 0));
+        Some((job.key, job.id, job.quality, job.duration, job.kind))
+    }
+
+    fn state_of(&self, key: u64) -> Option<DownloadState> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter()
+            .find(|job| job.key == key)
+            .map(|job| if job.orphaned() { DownloadState::Cancelled } else { job.state.clone() })
+    }
+
+    fn progress(&self, key: u64, downloaded: u64, total: Option<u64>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.key == key) {
+            job.downloaded = downloaded;
+            job.total = total;
+        }
+    }
+
+    fn finish(&self, key: u64, result: Result<TrackData, ApiError>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(n) = jobs.iter().position(|job| job.key == key) else {
+            return;
+        };
+        let sender = jobs[n].result.take();
+        if let (Ok(_), Some(speed)) = (&result, jobs[n].speed()) {
+            *self.last_speed.lock().unwrap() = Some(speed);
+        }
+        match &result {
+            Err(err) if jobs[n].state != DownloadState::Cancelled => {
+                jobs[n].state = DownloadState::Failed(err.to_string());
+            },
+            _ => {
+                jobs.remove(n);
+            },
+        }
+        drop(jobs);
+
+        if let Some(sender) = sender {
+            let _ = sender.send(result);
+        }
+    }
+
+    // Every job runs in its own task once it gets a slot, so a paused one never holds up the rest
+    async fn run(self, client: SharedClient, cache: Option<AudioCache>) {
+        loop {
+            let permit = self.slots.clone().acquire_owned().await.unwrap();
+            let Some(next) = self.next_pending() else {
+                drop(permit);
+                self.wake.notified().await;
+                continue;
+            };
+            let (manager, client, cache) = (self.clone(), client.get(), cache.clone());
+            tokio::spawn(async move {
+                manager.process(next, permit, &client, cache.as_ref()).await;
+            });
+        }
+    }
+
+    async fn process(
+        &self,
+        (key, id, quality, duration, kind): (u64, TrackId, Quality, Option<Duration>, DownloadKind),
+        permit: OwnedSemaphorePermit,
+        client: &reqwest::Client,
+        cache: Option<&AudioCache>,
+    ) {
+        if let Some((cache, data)) = cache.and_then(|cache| Some((cache, cache.load(id, quality)?))) {
+            if kind == DownloadKind::Explicit {
+                cache.pin(id, quality);
+            }
+            self.finish(key, Ok(data));
+            return;
+        }
+
+        match self.download(key, id, quality, permit, client).await {
+            Ok(Some(data)) => {
+                if let Some(cache) = cache {
+                    if let Err(err) = cache.store(&data, quality, duration, kind).await {
+                        tracing::warn!("Failed to cache track {}: {}", id, err);
+                    }
+                }
+                self.finish(key, Ok(data));
+            },
+            Ok(None) => {
+                tracing::debug!("Dropped download of track {}", id);
+                let mut jobs = self.jobs.lock().unwrap();
+                jobs.retain(|job| job.key != key);
+            },
+            Err(err) => self.finish(key, Err(err)),
+        }
+    }
+
+    // Returns None when the job got cancelled midway
+    async fn download(
+        &self,
+        key: u64,
+        id: TrackId,
+        quality: Quality,
+        permit: OwnedSemaphorePermit,
+        client: &reqwest::Client,
+    ) -> Result<Option<TrackData>, ApiError> {
+        let mut permit = Some(permit);
+        let mut stream = open_track_stream(id, quality, client).await?;
+        let total = stream.response.content_length();
+        let mut bytes = Vec::with_capacity(total.unwrap_or(0) as usize);
+
+        loop {
+            match self.state_of(key) {
+                Some(DownloadState::Paused) => {
+                    // The slot goes to the jobs queued behind, like prefetches playback waits on
+                    permit = None;
+                    sleep(Duration::from_millis(100)).await;
+                    continue;
+                },
+                Some(DownloadState::Cancelled) | None => return Ok(None),
+                _ => {},
+            }
+            if permit.is_none() {
+                permit = Some(self.slots.clone().acquire_owned().await.unwrap());
+            }
+
+            let Some(chunk) = stream.response.chunk().await? else {
+                break;
+            };
+            bytes.extend_from_slice(&chunk);
+            self.progress(key, bytes.len() as u64, total);
+        }
+
+        Ok(
+            Some(
+                TrackData {
+                    id,
+                    codec: stream.codec,
+                    bitrate: stream.bitrate,
+                    loaded: std::time::Instant::now(),
+                    data: std::io::Cursor::new(bytes.into()),
+                }
+            )
+        )
+    }
+}
@@ -0,0 +1,224 @@
+use rodio::{
+    cpal::Sample as _,
+    Sample,
+    Source,
+};
+
+use serde::Deserialize;
+
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    Mutex,
+    atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Band {
+    // Center frequency in Hz
+    pub frequency: f32,
+    // In dB, negative cuts
+    pub gain: f32,
+    #[serde(default = "default_q")]
+    pub q: f32,
+}
+
+fn default_q() -> f32 {
+    1.0
+}
+
+const fn band(frequency: f32, gain: f32, q: f32) -> Band {
+    Band { frequency, gain, q }
+}
+
+pub const BUILTIN_PRESETS: [(&str, &[Band]); 3] = [
+    ("flat", &[]),
+    ("bass-boost", &[band(60.0, 6.0, 0.8), band(150.0, 3.0, 1.0)]),
+    ("spoken-word", &[band(100.0, -6.0, 0.7), band(3000.0, 4.0, 1.0), band(8000.0, -2.0, 1.0)]),
+];
+
+// Presets from the config take precedence over the built-in ones of the same name
+pub fn find_preset(name: &str, custom: &HashMap<String, Vec<Band>>) -> Option<Vec<Band>> {
+    custom.get(name).cloned().or_else(|| {
+        BUILTIN_PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, bands)| bands.to_vec())
+    })
+}
+
+pub fn preset_names(custom: &HashMap<String, Vec<Band>>) -> Vec<&str> {
+    let mut names: Vec<&str> = BUILTIN_PRESETS.iter().map(|(name, _)| *name).collect();
+    for name in custom.keys() {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+// Shared between the player and every source it wraps, so a new preset is heard mid-track
+#[derive(Debug, Clone, Default)]
+pub struct EqControl {
+    bands: Arc<Mutex<Vec<Band>>>,
+    generation: Arc<AtomicUsize>,
+}
+
+impl EqControl {
+    pub fn set(&self, bands: Vec<Band>) {
+        *self.bands.lock().unwrap() = bands;
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn wrap<S>(&self, source: S) -> Equalizer<S>
+    where
+        S: Source,
+        S::Item: Sample,
+    {
+        let mut equalizer = Equalizer {
+            input: source,
+            control: self.clone(),
+            generation: usize::MAX,
+            sample_rate: 0,
+            filters: Vec::new(),
+            channel: 0,
+            until_check: 0,
+        };
+        equalizer.refresh();
+        equalizer
+    }
+}
+
+// RBJ cookbook peaking filter in direct form I, one state per channel
+#[derive(Debug, Clone)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    state: Vec<[f32; 4]>,
+}
+
+impl Biquad {
+    fn peaking(band: Band, sample_rate: u32, channels: usize) -> Self {
+        let a = 10f32.powf(band.gain / 40.0);
+        let w0 = 2.0 * std::f32::consts::PI * band.frequency / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * band.q.max(0.01));
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / a;
+        Biquad {
+            b0: (1.0 + alpha * a) / a0,
+            b1: -2.0 * cos / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha / a) / a0,
+            state: vec![[0.0; 4]; channels],
+        }
+    }
+
+    fn process(&mut self, channel: usize, x: f32) -> f32 {
+        let [x1, x2, y1, y2] = self.state[channel];
+        let y = self.b0 * x + self.b1 * x1 + self.b2 * x2 - self.a1 * y1 - self.a2 * y2;
+        self.state[channel] = [x, x1, y, y1];
+        y
+    }
+}
+
+// Samples between looking for a preset change, the lock is only taken when there is one
+const CHECK_EVERY: usize = 1024;
+
+pub struct Equalizer<S> {
+    input: S,
+    control: EqControl,
+    generation: usize,
+    sample_rate: u32,
+    filters: Vec<Biquad>,
+    channel: usize,
+    until_check: usize,
+}
+
+impl<S> Equalizer<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn refresh(&mut self) {
+        let generation = self.control.generation.load(Ordering::Acquire);
+        let sample_rate = self.input.sample_rate();
+        if generation == self.generation && sample_rate == self.sample_rate {
+            return;
+        }
+        let channels = self.input.channels().max(1) as usize;
+        self.filters = self.control.bands
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|band| band.gain != 0.0 && band.frequency < sample_rate as f32 / 2.0)
+            .map(|&band| Biquad::peaking(band, sample_rate, channels))
+            .collect();
+        self.generation = generation;
+        self.sample_rate = sample_rate;
+        self.channel = 0;
+    }
+}
+
+impl<S> Iterator for Equalizer<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // Only switch filters between frames so channels stay aligned
+        if self.channel == 0 {
+            if self.until_check == 0 {
+                self.refresh();
+                self.until_check = CHECK_EVERY;
+            }
+            self.until_check -= 1;
+        }
+
+        let sample = self.input.next()?.to_f32();
+        let channel = self.channel;
+        let channels = self.input.channels().max(1) as usize;
+        self.channel = (self.channel + 1) % channels;
+
+        let filtered = self.filters
+            .iter_mut()
+            .filter(|filter| channel < filter.state.len())
+            .fold(sample, |sample, filter| filter.process(channel, sample));
+        Some(filtered.clamp(-1.0, 1.0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Equalizer<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::RwLock;
+
+// What the engine has to tell the user, the frontend decides how it's shown.
+// The library itself never writes to the terminal, without a sink it all goes nowhere
+#[derive(Debug, Clone)]
+pub enum Event {
+    // A line answering a command or reporting on playback
+    Message(String),
+    // A cover encoded for the terminal, escape codes and all
+    Art(Vec<u8>),
+}
+
+type Sink = Box<dyn Fn(Event) + Send + Sync>;
+
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+// Replaces the previous sink, events go to it from whichever thread emits them
+pub fn set_sink(sink: impl Fn(Event) + Send + Sync + 'static) {
+    *SINK.write().unwrap() = Some(Box::new(sink));
+}
+
+pub fn emit(event: Event) {
+    if let Some(sink) = SINK.read().unwrap().as_ref() {
+        sink(event);
+    }
+}
+
+// Formats like println! and emits the line as a message
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::events::emit($crate::events::Event::Message(format!($($arg)*)))
+    };
+}
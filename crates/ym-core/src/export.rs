@@ -0,0 +1,96 @@
+use crate::api::{
+    Quality,
+    Track,
+};
+use crate::cache::AudioCache;
+use crate::link::Link;
+
+use serde::Serialize;
+
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    M3u,
+    Json,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::M3u => "m3u8",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "m3u" | "m3u8" => Ok(Format::M3u),
+            "json" => Ok(Format::Json),
+            _ => Err("Export format is either m3u or json"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonPlaylist<'a> {
+    title: &'a str,
+    tracks: Vec<JsonTrack<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonTrack<'a> {
+    link: String,
+    #[serde(flatten)]
+    track: &'a Track,
+}
+
+fn link(track: &Track) -> Link {
+    Link::Track { album: track.albums.first().map(|album| album.id), track: track.id }
+}
+
+// Extended M3U8 pointing at the cached file when there is one and at the track page otherwise
+fn m3u(title: &str, tracks: &[&Track], cache: Option<&AudioCache>, quality: Quality) -> String {
+    let mut playlist = format!("#EXTM3U\n#PLAYLIST:{}\n", title);
+    for track in tracks {
+        let artists = track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", ");
+        let seconds = track.duration.map_or(-1, |ms| (ms / 1000) as i64);
+        let _ = writeln!(playlist, "#EXTINF:{},{} - {}", seconds, artists, track.full_title());
+        let _ = writeln!(playlist, "#EXTART:{}", artists);
+        if let Some(album) = track.albums.first() {
+            let _ = writeln!(playlist, "#EXTALB:{}", album.title);
+        }
+        match cache.and_then(|cache| cache.file(track.id, quality)) {
+            Some(file) => { let _ = writeln!(playlist, "{}", file.display()); },
+            None => { let _ = writeln!(playlist, "{}", link(track)); },
+        }
+    }
+    playlist
+}
+
+pub fn write(
+    path: &Path,
+    title: &str,
+    tracks: &[&Track],
+    format: Format,
+    cache: Option<&AudioCache>,
+    quality: Quality,
+) -> std::io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let contents = match format {
+        Format::M3u => m3u(title, tracks, cache, quality).into_bytes(),
+        Format::Json => {
+            let tracks = tracks.iter().map(|&track| JsonTrack { link: link(track).to_string(), track }).collect();
+            serde_json::to_vec_pretty(&JsonPlaylist { title, tracks })?
+        },
+    };
+    std::fs::write(path, contents)
+}
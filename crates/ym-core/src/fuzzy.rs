@@ -0,0 +1,33 @@
+use crate::translit;
+
+// Matches the query as a subsequence of the text, in either script. Higher scores go to
+// runs of consecutive characters and to matches at word starts, None when it doesn't match
+pub fn score(query: &str, text: &str) -> Option<u32> {
+    let query: Vec<char> = translit::fold(query).chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = translit::fold(text).chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut matched = 0;
+    let mut previous: Option<usize> = None;
+    for (i, &c) in text.iter().enumerate() {
+        if c != query[matched] {
+            continue;
+        }
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == i) {
+            score += 5;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(i);
+        matched += 1;
+        if matched == query.len() {
+            return Some(score);
+        }
+    }
+    None
+}
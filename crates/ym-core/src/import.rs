@@ -0,0 +1,134 @@
+use crate::api::{
+    like_tracks,
+    liked_tracks_infos,
+    search_tracks,
+    ApiError,
+    Track,
+    TrackId,
+    Uid,
+    TRACKS_PAGE,
+};
+use crate::translit;
+
+use futures::StreamExt;
+
+use reqwest::Client;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+// Headers of the Exportify and Spotify exports, anything else is read as plain artist,title
+const TITLE_COLUMNS: &[&str] = &["track name", "track", "title", "name"];
+const ARTIST_COLUMNS: &[&str] = &["artist name(s)", "artist name", "artists", "artist"];
+
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub artist: String,
+    pub title: String,
+}
+
+impl std::fmt::Display for Row {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {}", self.artist, self.title)
+    }
+}
+
+fn column(header: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| header.iter().position(|field| field.trim().eq_ignore_ascii_case(name)))
+}
+
+pub fn read_rows(path: &Path) -> csv::Result<Vec<Row>> {
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_path(path)?;
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = records.first() else {
+        return Ok(Vec::new());
+    };
+    let (artist, title, skip) = match (column(first, ARTIST_COLUMNS), column(first, TITLE_COLUMNS)) {
+        (Some(artist), Some(title)) => (artist, title, 1),
+        _ => (0, 1, 0),
+    };
+    Ok(
+        records
+            .iter()
+            .skip(skip)
+            .filter_map(|record| {
+                let row = Row { artist: record.get(artist)?.trim().to_owned(), title: record.get(title)?.trim().to_owned() };
+                (!row.title.is_empty()).then_some(row)
+            })
+            .collect()
+    )
+}
+
+// Spotify puts versions after a dash where Yandex keeps them apart, like "Song - Remastered 2011"
+fn bare_title(title: &str) -> String {
+    let title = translit::fold(title);
+    let end = [" - ", " ("].iter().filter_map(|separator| title.find(separator)).min().unwrap_or(title.len());
+    title[..end].trim().to_owned()
+}
+
+// A result with the same title credited to one of the row's artists, in either script
+fn best_match<'a>(row: &Row, results: &'a [Track]) -> Option<&'a Track> {
+    let title = bare_title(&row.title);
+    let artists = row.artist
+        .split([',', ';', '&'])
+        .map(|artist| translit::fold(artist.trim()))
+        .filter(|artist| !artist.is_empty())
+        .collect::<Vec<_>>();
+    results.iter().find(|track| {
+        bare_title(&track.title) == title
+            && (artists.is_empty() || track.artists.iter().any(|artist| artists.contains(&translit::fold(&artist.name))))
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub liked: usize,
+    pub already_liked: usize,
+    pub unmatched: Vec<Row>,
+    // Rows whose search failed, they are worth another try unlike the unmatched ones
+    pub failed: Vec<(Row, ApiError)>,
+}
+
+// Searches every row and likes what matched, unmatched rows are reported instead of guessed.
+// A failed search only costs its own row
+pub async fn import_likes(
+    rows: Vec<Row>,
+    uid: Uid,
+    client: &Client,
+    concurrency: usize,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Report, ApiError> {
+    let mut liked = liked_tracks_infos(uid, client).await?.into_iter().map(|info| info.id).collect::<HashSet<_>>();
+    let total = rows.len();
+    let mut searches = futures::stream::iter(rows.into_iter().map(|row| async move {
+        let results = search_tracks(&format!("{} {}", row.artist, row.title), client).await;
+        (row, results)
+    }))
+    .buffered(concurrency.max(1));
+
+    let mut report = Report::default();
+    let mut matched: Vec<TrackId> = Vec::new();
+    let mut done = 0;
+    while let Some((row, results)) = searches.next().await {
+        done += 1;
+        progress(done, total);
+        let results = match results {
+            Ok(results) => results,
+            Err(err) => {
+                tracing::warn!("Failed to search for {}: {}", row, err);
+                report.failed.push((row, err));
+                continue;
+            },
+        };
+        match best_match(&row, &results) {
+            Some(track) if !liked.insert(track.id) => report.already_liked += 1,
+            Some(track) => matched.push(track.id),
+            None => report.unmatched.push(row),
+        }
+    }
+    for page in matched.chunks(TRACKS_PAGE) {
+        like_tracks(uid, page, client).await?;
+        report.liked += page.len();
+    }
+    Ok(report)
+}
@@ -0,0 +1,38 @@
+// The Yandex Music client and player, the frontends in ym-tui and ym-daemon are built on top of it
+pub mod announce;
+pub mod api;
+pub mod art;
+pub mod audit;
+pub mod availability;
+mod blacklist;
+pub mod bundle;
+pub mod cache;
+mod card;
+pub mod config;
+pub mod downloads;
+pub mod eq;
+pub mod events;
+pub mod export;
+pub mod import;
+mod fuzzy;
+pub mod library;
+pub mod link;
+pub mod local;
+pub mod limits;
+pub mod lyrics;
+pub mod output;
+pub mod player;
+pub mod presets;
+pub mod resume;
+pub mod scrobbler;
+pub mod status;
+pub mod tagged;
+mod translit;
+pub mod trims;
+pub mod ynison;
+
+pub use api::YandexClient;
+pub use player::{
+    Player,
+    Queue,
+};
@@ -0,0 +1,216 @@
+use crate::api::{
+    fetch_tracks_bounded,
+    liked_library,
+    AlbumInfo,
+    AlbumType,
+    ApiError,
+    ArtistId,
+    ArtistInfo,
+    Track,
+    TrackId,
+    Uid,
+};
+use crate::config;
+
+use reqwest::Client;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::path::PathBuf;
+
+// Bumped whenever Track changes shape, so an older cache is refetched instead of read with missing fields
+const LIKED_CACHE_FORMAT: u32 = 1;
+
+// Full metadata of the liked tracks, valid for a single library revision
+#[derive(Debug, Serialize, Deserialize)]
+struct LikedCache {
+    // Caches written before the field existed read as 0
+    #[serde(default)]
+    format: u32,
+    revision: u64,
+    tracks: Vec<Track>,
+}
+
+impl Default for LikedCache {
+    fn default() -> Self {
+        LikedCache { format: LIKED_CACHE_FORMAT, revision: 0, tracks: Vec::new() }
+    }
+}
+
+fn liked_cache_path(uid: Uid) -> PathBuf {
+    config::account_path(config::cache_dir(), uid, "liked.json")
+}
+
+impl LikedCache {
+    fn load(uid: Uid) -> Self {
+        std::fs::read(liked_cache_path(uid))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<LikedCache>(&bytes).ok())
+            .filter(|cache| cache.format == LIKED_CACHE_FORMAT)
+            .unwrap_or_default()
+    }
+
+    fn save(&self, uid: Uid) -> std::io::Result<()> {
+        let path = liked_cache_path(uid);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+}
+
+// Only tracks liked since the cached revision get fetched, the rest comes from disk
+pub async fn liked_tracks(
+    uid: Uid,
+    client: &Client,
+    concurrency: usize,
+    progress: impl FnMut(usize, usize),
+) -> Result<Vec<Track>, ApiError> {
+    let library = liked_library(uid, client).await?;
+    let mut cache = LikedCache::load(uid);
+    if cache.revision == library.revision && !cache.tracks.is_empty() {
+        return Ok(cache.tracks);
+    }
+
+    let mut known: HashMap<TrackId, Track> = cache.tracks
+        .drain(..)
+        .map(|track| (track.id, track))
+        .collect();
+    let missing = library.tracks
+        .iter()
+        .filter(|info| !known.contains_key(&info.id))
+        .map(|info| info.id)
+        .collect::<Vec<_>>();
+    let mut complete = true;
+    for track in fetch_tracks_bounded(&missing, client, concurrency, progress).await {
+        match track {
+            Ok(track) => {
+                known.insert(track.id, track);
+            },
+            Err(_) => complete = false,
+        }
+    }
+
+    cache.tracks = library.tracks
+        .iter()
+        .filter_map(|info| known.remove(&info.id))
+        .collect();
+    // A partial fetch keeps the revision unknown, so the next start retries the gaps
+    cache.revision = if complete { library.revision } else { 0 };
+    if let Err(err) = cache.save(uid) {
+        tracing::warn!("Failed to save liked tracks: {}", err);
+    }
+
+    Ok(cache.tracks)
+}
+
+pub async fn liked_music_tracks(
+    uid: Uid,
+    client: &Client,
+    concurrency: usize,
+    progress: impl FnMut(usize, usize),
+) -> Result<Vec<Track>, ApiError> {
+    Ok(
+        liked_tracks(uid, client, concurrency, progress)
+            .await?
+            .into_iter()
+            .filter(|track| track.albums[0].meta_type == AlbumType::Music)
+            .collect()
+    )
+}
+
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+pub struct AlbumGroup<'a> {
+    pub album: &'a AlbumInfo,
+    pub tracks: Vec<&'a Track>,
+}
+
+impl AlbumGroup<'_> {
+    // Compilations and albums whose tracks disagree on the artist aren't credited to any single one
+    pub fn artist(&self, artists: &ArtistIndex) -> String {
+        if self.album.is_compilation() {
+            return VARIOUS_ARTISTS.to_owned();
+        }
+        if !self.album.artists.is_empty() {
+            return self.album.artists.iter().map(|artist| artists.label(artist)).collect::<Vec<_>>().join(", ");
+        }
+        let first = |track: &&Track| track.artists.first().map(|artist| artist.id);
+        match self.tracks.first().map(first) {
+            Some(artist) if self.tracks.iter().all(|track| first(track) == artist) => {
+                self.tracks[0].artists.first().map_or_else(String::new, |artist| artists.label(artist))
+            },
+            _ => VARIOUS_ARTISTS.to_owned(),
+        }
+    }
+}
+
+// Tells apart artists sharing a name by their genre and the album they have the most tracks on
+#[derive(Default)]
+pub struct ArtistIndex {
+    ids_by_name: HashMap<String, HashSet<ArtistId>>,
+    genres: HashMap<ArtistId, String>,
+    album_counts: HashMap<ArtistId, HashMap<String, usize>>,
+}
+
+impl ArtistIndex {
+    pub fn new(tracks: &[Track]) -> Self {
+        let mut index = ArtistIndex::default();
+        for track in tracks {
+            for artist in &track.artists {
+                index.ids_by_name.entry(artist.name.to_lowercase()).or_default().insert(artist.id);
+                if let Some(genre) = artist.genres.first() {
+                    index.genres.entry(artist.id).or_insert_with(|| genre.clone());
+                }
+                if let Some(album) = track.albums.first() {
+                    *index.album_counts.entry(artist.id).or_default().entry(album.title.clone()).or_default() += 1;
+                }
+            }
+        }
+        index
+    }
+
+    pub fn is_ambiguous(&self, artist: &ArtistInfo) -> bool {
+        self.ids_by_name.get(&artist.name.to_lowercase()).is_some_and(|ids| ids.len() > 1)
+    }
+
+    pub fn label(&self, artist: &ArtistInfo) -> String {
+        if !self.is_ambiguous(artist) {
+            return artist.name.clone();
+        }
+        let top_album = self.album_counts
+            .get(&artist.id)
+            .and_then(|counts| counts.iter().max_by_key(|(_, &count)| count))
+            .map(|(title, _)| title.as_str());
+        let details: Vec<&str> = self.genres.get(&artist.id).map(String::as_str).into_iter().chain(top_album).collect();
+        if details.is_empty() {
+            format!("{} #{}", artist.name, artist.id)
+        } else {
+            format!("{} ({})", artist.name, details.join(", "))
+        }
+    }
+}
+
+// In the order albums first appear, tracks without an album are left out
+pub fn group_by_album(tracks: &[Track]) -> Vec<AlbumGroup<'_>> {
+    let mut groups: Vec<AlbumGroup> = Vec::new();
+    let mut index = HashMap::new();
+    for track in tracks {
+        let Some(album) = track.albums.first() else {
+            continue;
+        };
+        let n = *index.entry(album.id).or_insert_with(|| {
+            groups.push(AlbumGroup { album, tracks: Vec::new() });
+            groups.len() - 1
+        });
+        groups[n].tracks.push(track);
+    }
+    groups
+}
@@ -0,0 +1,171 @@
+use crate::api::Uid;
+use crate::config::{
+    self,
+    LimitsConfig,
+};
+
+use chrono::{
+    Local,
+    NaiveDate,
+    NaiveTime,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::path::PathBuf;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+// Listening time is kept on disk so restarting the player doesn't reset the day
+#[derive(Debug, Serialize, Deserialize)]
+struct ListenedToday {
+    date: NaiveDate,
+    listened: Duration,
+}
+
+// Written this often while playing, on top of when the limit is hit
+const SAVE_EVERY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lock {
+    DailyLimit(u64),
+    QuietHours(NaiveTime),
+}
+
+impl std::fmt::Display for Lock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lock::DailyLimit(minutes) => write!(f, "Daily limit of {} min reached", minutes),
+            Lock::QuietHours(end) => write!(f, "Quiet hours until {}", end.format("%H:%M")),
+        }
+    }
+}
+
+fn parse_time(time: &Option<String>) -> Option<NaiveTime> {
+    let time = time.as_deref()?;
+    let parsed = NaiveTime::parse_from_str(time, "%H:%M");
+    if parsed.is_err() {
+        tracing::warn!("Ignoring quiet hours, {} is not a HH:MM time", time);
+    }
+    parsed.ok()
+}
+
+pub struct Limits {
+    daily: Option<u64>,
+    quiet: Option<(NaiveTime, NaiveTime)>,
+    unlock_code: Option<String>,
+    today: ListenedToday,
+    path: PathBuf,
+    counted_at: Option<Instant>,
+    saved: Duration,
+    // Limits are lifted for the rest of that day
+    unlocked: Option<NaiveDate>,
+}
+
+impl Limits {
+    pub fn new(config: &LimitsConfig, uid: Uid) -> Self {
+        let today = Local::now().date_naive();
+        let path = config::account_path(config::data_dir(), uid, "listened.json");
+        let listened = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<ListenedToday>(&bytes).ok())
+            .filter(|listened| listened.date == today)
+            .unwrap_or(ListenedToday { date: today, listened: Duration::ZERO });
+        Limits {
+            daily: config.daily_minutes,
+            quiet: parse_time(&config.quiet_from).zip(parse_time(&config.quiet_until)),
+            unlock_code: config.unlock_code.clone(),
+            saved: listened.listened,
+            today: listened,
+            path,
+            counted_at: None,
+            unlocked: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.daily.is_some() || self.quiet.is_some()
+    }
+
+    pub fn lock(&self) -> Option<Lock> {
+        let now = Local::now();
+        if self.unlocked == Some(now.date_naive()) {
+            return None;
+        }
+        if let Some((from, until)) = self.quiet {
+            let time = now.time();
+            // Quiet hours usually span midnight, like 22:00 to 07:00
+            let quiet = if from <= until {
+                from <= time && time < until
+            } else {
+                time >= from || time < until
+            };
+            if quiet {
+                return Some(Lock::QuietHours(until));
+            }
+        }
+        match self.daily {
+            Some(minutes) if self.today.listened >= Duration::from_secs(minutes * 60) => Some(Lock::DailyLimit(minutes)),
+            _ => None,
+        }
+    }
+
+    // Adds the time played since the last call, playing tells whether audio was going meanwhile
+    pub fn count(&mut self, playing: bool) {
+        let now = Instant::now();
+        let elapsed = self.counted_at.map(|at| now - at);
+        self.counted_at = playing.then_some(now);
+
+        let today = Local::now().date_naive();
+        if self.today.date != today {
+            self.today = ListenedToday { date: today, listened: Duration::ZERO };
+            self.saved = Duration::ZERO;
+        }
+        if let Some(elapsed) = elapsed {
+            self.today.listened += elapsed;
+        }
+        if self.daily.is_some() && self.today.listened >= self.saved + SAVE_EVERY {
+            self.save();
+        }
+    }
+
+    pub fn save(&mut self) {
+        let result = serde_json::to_vec(&self.today)
+            .map_err(std::io::Error::from)
+            .and_then(|bytes| {
+                if let Some(dir) = self.path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&self.path, bytes)
+            });
+        match result {
+            Ok(()) => self.saved = self.today.listened,
+            Err(err) => tracing::warn!("Failed to save listening time: {}", err),
+        }
+    }
+
+    pub fn listened(&self) -> Duration {
+        self.today.listened
+    }
+
+    pub fn daily(&self) -> Option<u64> {
+        self.daily
+    }
+
+    // Returns whether the code matched
+    pub fn unlock(&mut self, code: &str) -> Result<bool, &'static str> {
+        let Some(unlock_code) = &self.unlock_code else {
+            return Err("No unlock code is set, limits can only be changed in the config");
+        };
+        let matched = unlock_code == code;
+        if matched {
+            self.unlocked = Some(Local::now().date_naive());
+        }
+        Ok(matched)
+    }
+}
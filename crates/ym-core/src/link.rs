@@ -0,0 +1,88 @@
+use std::{fmt, str::FromStr};
+
+use crate::api::{AlbumId, ArtistId, PlaylistKind, TrackId};
+
+// Regional mirrors of music.yandex.ru, they share the same paths
+const DOMAINS: &[&str] = &["ru", "com", "by", "kz", "uz", "az", "com.tr"];
+
+// A music.yandex.ru page someone shared, playlists are addressed by their owner's login or uid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Link {
+    Playlist { owner: String, kind: PlaylistKind },
+    Album(AlbumId),
+    Track { album: Option<AlbumId>, track: TrackId },
+    Artist(ArtistId),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    NotYandexMusic,
+    Unsupported,
+    BadId,
+}
+
+impl LinkError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkError::NotYandexMusic => "Not a music.yandex link",
+            LinkError::Unsupported => "Only playlist, album, track and artist links are supported",
+            LinkError::BadId => "Malformed id in the link",
+        }
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+// The canonical form of the link, which parses back to the same one
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "https://music.yandex.ru")?;
+        match self {
+            Link::Playlist { owner, kind } => write!(f, "/users/{}/playlists/{}", owner, kind),
+            Link::Album(album) => write!(f, "/album/{}", album),
+            Link::Track { album: Some(album), track } => write!(f, "/album/{}/track/{}", album, track),
+            Link::Track { album: None, track } => write!(f, "/track/{}", track),
+            Link::Artist(artist) => write!(f, "/artist/{}", artist),
+        }
+    }
+}
+
+impl FromStr for Link {
+    type Err = LinkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s.trim();
+        let rest = rest.strip_prefix("https://").or_else(|| rest.strip_prefix("http://")).unwrap_or(rest);
+        let rest = rest.split(['?', '#']).next().unwrap_or_default();
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let host = host.to_ascii_lowercase();
+        let domain = host.strip_prefix("www.").unwrap_or(&host).strip_prefix("music.yandex.");
+        if !domain.is_some_and(|domain| DOMAINS.contains(&domain)) {
+            return Err(LinkError::NotYandexMusic);
+        }
+
+        let parts = path.split('/').filter(|part| !part.is_empty()).collect::<Vec<_>>();
+        let id = <str>::parse::<u64>;
+        let link = match parts.as_slice() {
+            ["users", owner, "playlists", kind] => Link::Playlist {
+                owner: owner.to_string(),
+                kind: PlaylistKind(id(kind).map_err(|_| LinkError::BadId)?),
+            },
+            ["album", album, "track", track] => Link::Track {
+                album: Some(AlbumId(id(album).map_err(|_| LinkError::BadId)?)),
+                track: TrackId(id(track).map_err(|_| LinkError::BadId)?),
+            },
+            ["album", album, ..] => Link::Album(AlbumId(id(album).map_err(|_| LinkError::BadId)?)),
+            ["track", track, ..] => Link::Track { album: None, track: TrackId(id(track).map_err(|_| LinkError::BadId)?) },
+            ["artist", artist, ..] => Link::Artist(ArtistId(id(artist).map_err(|_| LinkError::BadId)?)),
+            _ => return Err(LinkError::Unsupported),
+        };
+        Ok(link)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::api::{
+    ArtistId,
+    ArtistInfo,
+    Track,
+    TrackId,
+};
+
+use id3::TagLike;
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+// What rodio decodes with the features enabled here
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "oga", "wav", "aac"];
+
+// Yandex ids stay far below, so local files get ids from the top half
+const FIRST_LOCAL_ID: u64 = 1 << 63;
+
+pub fn is_local(id: TrackId) -> bool {
+    id.0 >= FIRST_LOCAL_ID
+}
+
+pub fn local_id(n: usize) -> TrackId {
+    TrackId(FIRST_LOCAL_ID + n as u64)
+}
+
+fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+// The file itself, or the audio files under the directory in name order
+pub fn scan(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        std::fs::metadata(path)?;
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(scan(&entry)?);
+        } else if is_audio(&entry) {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+// Titles from ID3 tags when the file has them, from the file name otherwise
+pub fn track(path: &Path, id: TrackId) -> Track {
+    let tag = id3::Tag::read_from_path(path).ok();
+    let name = path.file_stem().map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned());
+    let artists = tag.as_ref()
+        .and_then(|tag| tag.artist())
+        .map(|artist| vec![ArtistInfo { id: ArtistId(0), name: artist.to_owned(), various: false, genres: Vec::new() }])
+        .unwrap_or_default();
+    Track {
+        id,
+        title: tag.as_ref().and_then(|tag| tag.title()).map_or(name, str::to_owned),
+        version: None,
+        major: None,
+        albums: Vec::new(),
+        artists,
+        duration: tag.as_ref().and_then(|tag| tag.duration()).map(u64::from),
+        cover_uri: None,
+        r128: None,
+        available: true,
+    }
+}
@@ -0,0 +1,167 @@
+use crate::say;
+use crate::api::{
+    RawLyrics,
+    TrackId,
+};
+use crate::config;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+
+#[derive(Debug)]
+pub struct LyricLine {
+    pub time: Option<Duration>,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+}
+
+// Parses "mm:ss.xx" out of an LRC tag, ignoring metadata tags like "ar:Artist"
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes = minutes.parse::<u64>().ok()?;
+    let seconds = seconds.parse::<f64>().ok()?;
+    Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+impl Lyrics {
+    pub fn parse_lrc(text: &str) -> Self {
+        let mut lines = Vec::new();
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+            while let Some(tag) = rest.strip_prefix('[') {
+                let Some((tag, tail)) = tag.split_once(']') else {
+                    break;
+                };
+                if let Some(time) = parse_timestamp(tag) {
+                    times.push(time);
+                }
+                rest = tail;
+            }
+            for time in times {
+                lines.push(LyricLine { time: Some(time), text: rest.trim().to_owned() });
+            }
+        }
+        lines.sort_by_key(|line| line.time);
+
+        Lyrics { lines }
+    }
+
+    pub fn plain(text: &str) -> Self {
+        Lyrics {
+            lines: text
+                .lines()
+                .map(|line| LyricLine { time: None, text: line.to_owned() })
+                .collect(),
+        }
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.lines.iter().any(|line| line.time.is_some())
+    }
+
+    pub fn line_at(&self, position: Duration) -> Option<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| line.time.is_some_and(|time| time <= position))
+    }
+
+    pub fn print(&self, current: Option<usize>) {
+        for (n, line) in self.lines.iter().enumerate() {
+            if Some(n) == current {
+                say!("> {}", line.text);
+            } else {
+                say!("  {}", line.text);
+            }
+        }
+    }
+}
+
+impl From<RawLyrics> for Lyrics {
+    fn from(raw: RawLyrics) -> Self {
+        match raw {
+            RawLyrics::Synced(text) => Lyrics::parse_lrc(&text),
+            RawLyrics::Plain(text) => Lyrics::plain(&text),
+        }
+    }
+}
+
+// Plain text of every lyrics fetched so far, None for tracks known to have none
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LyricsStore {
+    tracks: HashMap<TrackId, Option<String>>,
+}
+
+fn store_path() -> PathBuf {
+    config::data_dir().join("lyrics.json")
+}
+
+// Lowercase words only, so punctuation and line breaks don't get in the way of a phrase
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl LyricsStore {
+    pub fn load() -> Self {
+        std::fs::read(store_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = store_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn contains(&self, id: TrackId) -> bool {
+        self.tracks.contains_key(&id)
+    }
+
+    pub fn insert(&mut self, id: TrackId, lyrics: Option<&Lyrics>) {
+        let text = lyrics.map(|lyrics| lyrics.lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join("\n"));
+        self.tracks.insert(id, text);
+    }
+
+    // The first line the phrase appears on, phrases spanning lines point at the line they start on
+    pub fn find(&self, id: TrackId, phrase: &str) -> Option<String> {
+        let phrase = normalize(phrase);
+        if phrase.is_empty() {
+            return None;
+        }
+        let lines: Vec<&str> = self.tracks.get(&id)?.as_deref()?.lines().collect();
+        (0..lines.len()).find_map(|start| {
+            let head = normalize(lines[start]);
+            if head.is_empty() {
+                return None;
+            }
+            let mut text = head.clone();
+            for line in &lines[start + 1..] {
+                if text.len() >= head.len() + phrase.len() {
+                    break;
+                }
+                text = format!("{} {}", text, normalize(line));
+            }
+            // Only matches starting on this line count, later ones get found from their own line
+            text.find(&phrase).filter(|&at| at < head.len()).map(|_| lines[start].to_owned())
+        })
+    }
+}
@@ -0,0 +1,166 @@
+use rodio::{
+    queue::SourcesQueueOutput,
+    OutputStream,
+    OutputStreamHandle,
+    Sink,
+    Source,
+};
+
+use std::sync::{
+    Arc,
+    atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+const NULL_CHUNK: Duration = Duration::from_millis(100);
+
+// Headless mode plays into nothing, at real-time pace so the queue advances as usual
+pub enum AudioOutput {
+    Device {
+        _stream: OutputStream,
+        handle: OutputStreamHandle,
+    },
+    Null,
+}
+
+impl AudioOutput {
+    pub fn open(headless: bool) -> Self {
+        if headless {
+            return AudioOutput::Null;
+        }
+        let (stream, handle) = OutputStream::try_default().unwrap();
+        AudioOutput::Device {
+            _stream: stream,
+            handle,
+        }
+    }
+
+    // Swaps in the current default device, keeping the old one if none can be opened
+    pub fn reopen(&mut self) -> bool {
+        if matches!(self, AudioOutput::Null) {
+            return true;
+        }
+        match OutputStream::try_default() {
+            Ok((stream, handle)) => {
+                *self = AudioOutput::Device {
+                    _stream: stream,
+                    handle,
+                };
+                true
+            },
+            Err(err) => {
+                tracing::warn!("Failed to open audio device: {}", err);
+                false
+            },
+        }
+    }
+
+    pub fn new_sink(&self) -> Sink {
+        match self {
+            AudioOutput::Device { handle, .. } => Sink::try_new(handle).unwrap(),
+            AudioOutput::Null => {
+                let (sink, output) = Sink::new_idle();
+                drain(output);
+                sink
+            },
+        }
+    }
+}
+
+// Ends once the sink is dropped, since that lets its queue run out
+fn drain(mut output: SourcesQueueOutput<f32>) {
+    std::thread::spawn(move || {
+        loop {
+            let samples = output.sample_rate() as f32 * output.channels() as f32 * NULL_CHUNK.as_secs_f32();
+            for _ in 0..samples as usize {
+                if output.next().is_none() {
+                    return;
+                }
+            }
+            std::thread::sleep(NULL_CHUNK);
+        }
+    });
+}
+
+// Counts samples the output pulls, the device is gone when it stops while something plays.
+// rodio only prints stream errors, so this is the one sign of them we get.
+// Also tells when a source runs out, so the player needn't poll the sink for it
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat {
+    beats: Arc<AtomicUsize>,
+    ended: Arc<Notify>,
+}
+
+impl Heartbeat {
+    pub fn beats(&self) -> usize {
+        self.beats.load(Ordering::Relaxed)
+    }
+
+    pub async fn ended(&self) {
+        self.ended.notified().await
+    }
+
+    pub fn wrap<S: Source>(&self, source: S) -> Beating<S>
+    where
+        S::Item: rodio::Sample,
+    {
+        Beating {
+            input: source,
+            heartbeat: self.clone(),
+            ended: false,
+        }
+    }
+}
+
+pub struct Beating<S> {
+    input: S,
+    heartbeat: Heartbeat,
+    ended: bool,
+}
+
+impl<S: Source> Iterator for Beating<S>
+where
+    S::Item: rodio::Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        self.heartbeat.beats.fetch_add(1, Ordering::Relaxed);
+        let sample = self.input.next();
+        if sample.is_none() && !self.ended {
+            self.ended = true;
+            self.heartbeat.ended.notify_one();
+        }
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S: Source> Source for Beating<S>
+where
+    S::Item: rodio::Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
@@ -0,0 +1,3582 @@
+use crate::api::*;
+use crate::announce;
+use crate::audit;
+use crate::art::{
+    self,
+    ArtMode,
+};
+use crate::availability::{
+    self,
+    Report,
+};
+use crate::blacklist::Blacklist;
+use crate::bundle;
+use crate::export;
+use crate::import;
+use crate::limits::Limits;
+use crate::link::Link;
+use crate::local;
+use crate::cache::AudioCache;
+use crate::card::{
+    self,
+    CardInfo,
+};
+use crate::config::Config;
+use crate::events::{
+    self,
+    Event,
+};
+use crate::say;
+use crate::eq::{
+    self,
+    EqControl,
+};
+use crate::downloads::{
+    DownloadKind,
+    DownloadManager,
+};
+use crate::ynison;
+use crate::tagged;
+use crate::trims::{
+    Trim,
+    TrimStore,
+};
+use crate::status;
+use crate::scrobbler::{
+    PlayEvent,
+    Scrobblers,
+};
+use crate::fuzzy;
+use crate::library;
+use crate::presets;
+use crate::output::{
+    AudioOutput,
+    Heartbeat,
+};
+use crate::lyrics::{
+    Lyrics,
+    LyricsStore,
+};
+use crate::resume::{
+    LastSession,
+    Levels,
+    ResumePoint,
+    ResumeStore,
+};
+
+use reqwest::Client;
+
+use serde::Deserialize;
+
+use tokio::{
+    sync::{
+        mpsc,
+        oneshot::{
+            self,
+            error::TryRecvError,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use rodio::{
+    Sink,
+    Decoder,
+    Source as _,
+};
+
+use std::collections::{
+    HashMap,
+    HashSet,
+    VecDeque,
+};
+
+use futures::StreamExt;
+
+use rand::{
+    Rng,
+    seq::SliceRandom,
+};
+
+use chrono::{
+    DateTime,
+    Local,
+    SecondsFormat,
+    Utc,
+};
+
+// How tracks lists the loaded tracks, the queue keeps its own order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Original,
+    Artist,
+    Album,
+    Title,
+    Duration,
+    // Most recent like first, liked tracks already load that way
+    Liked,
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SortKey::Original => "original order",
+            SortKey::Artist => "artist",
+            SortKey::Album => "album",
+            SortKey::Title => "title",
+            SortKey::Duration => "duration",
+            SortKey::Liked => "recently liked",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// What happens to the playing track when another source gets loaded
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SwitchMode {
+    Now,
+    AfterCurrent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Favorites,
+    Playlist { uid: Uid, kind: PlaylistKind },
+    Artist(ArtistId),
+    Album(AlbumId),
+    Radio(String),
+    // A queue picked up from another device, by its id
+    Queue(String),
+}
+
+impl Source {
+    fn playlist_id(&self) -> Option<String> {
+        match self {
+            Source::Playlist { uid, kind } => Some(format!("{}:{}", uid, kind)),
+            _ => None,
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Source> {
+        if key == "favorites" {
+            return Some(Source::Favorites);
+        }
+        let (prefix, rest) = key.split_once(':')?;
+        match prefix {
+            "artist" => rest.parse().ok().map(Source::Artist),
+            "album" => rest.parse().ok().map(Source::Album),
+            "radio" => Some(Source::Radio(rest.to_owned())),
+            "queue" => Some(Source::Queue(rest.to_owned())),
+            uid => Some(Source::Playlist { uid: uid.parse().ok()?, kind: rest.parse().ok()? }),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Source::Favorites => "liked tracks",
+            Source::Playlist { .. } => "playlist",
+            Source::Artist(_) => "artist",
+            Source::Album(_) => "album",
+            Source::Radio(_) => "radio",
+            Source::Queue(_) => "queue",
+        }
+    }
+
+    fn key(&self) -> String {
+        match self {
+            Source::Favorites => "favorites".to_owned(),
+            Source::Playlist { uid, kind } => format!("{}:{}", uid, kind),
+            Source::Artist(id) => format!("artist:{}", id),
+            Source::Album(id) => format!("album:{}", id),
+            Source::Radio(station) => format!("radio:{}", station),
+            Source::Queue(id) => format!("queue:{}", id),
+        }
+    }
+}
+
+// Why a track is in the list, so tracks added by radio or by hand can be told apart
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    Liked,
+    Playlist(String),
+    Album(String),
+    Artist(String),
+    Radio(String),
+    SimilarTo(String),
+    Queue(String),
+    Manual,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Liked => write!(f, "liked"),
+            Origin::Playlist(title) => write!(f, "playlist {}", title),
+            Origin::Album(title) => write!(f, "album {}", title),
+            Origin::Artist(name) => write!(f, "artist {}", name),
+            Origin::Radio(station) => write!(f, "radio {}", station),
+            Origin::SimilarTo(track) => write!(f, "similar to {}", track),
+            Origin::Queue(title) => write!(f, "queue {}", title),
+            Origin::Manual => write!(f, "added by hand"),
+        }
+    }
+}
+
+// Where the audio of a queued track comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueueItem {
+    Remote(TrackId),
+    Local(std::path::PathBuf),
+}
+
+struct HistoryEntry {
+    started: DateTime<Utc>,
+    track: String,
+    origin: Origin,
+}
+
+// Tracks saved as files at once
+const SAVE_JOBS: usize = 4;
+
+// Plays kept for the history command
+const HISTORY_LENGTH: usize = 50;
+
+struct NowPlaying {
+    id: TrackId,
+    album_id: Option<AlbumId>,
+    artist_ids: Vec<ArtistId>,
+    podcast: bool,
+    duration: Option<Duration>,
+    source: Source,
+    cover_uri: Option<String>,
+    gain: f32,
+    // Codec and bitrate of the stream actually playing, which may differ from the preferred quality
+    stream: Option<(Codec, u32)>,
+    artist: String,
+    title: String,
+    album: Option<String>,
+    started: DateTime<Utc>,
+    offset: Duration,
+    played_at_offset: Duration,
+    played: Duration,
+    resumed: Option<Instant>,
+}
+
+impl NowPlaying {
+    fn new(track: &Track, source: Source, offset: Duration, gain: f32) -> Self {
+        NowPlaying {
+            id: track.id,
+            album_id: track.albums.first().map(|album| album.id),
+            artist_ids: track.artists.iter().map(|artist| artist.id).collect(),
+            podcast: track.albums.first().is_some_and(|album| album.meta_type == AlbumType::Podcast),
+            duration: track.duration.map(Duration::from_millis),
+            source,
+            cover_uri: track.cover_uri().map(str::to_owned),
+            gain,
+            stream: None,
+            artist: track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", "),
+            title: track.full_title(),
+            album: track.albums.first().map(|album| album.title.clone()),
+            started: Utc::now(),
+            offset,
+            played_at_offset: Duration::ZERO,
+            played: Duration::ZERO,
+            resumed: Some(Instant::now()),
+        }
+    }
+
+    fn played(&self) -> Duration {
+        self.played + self.resumed.map_or(Duration::ZERO, |resumed| resumed.elapsed())
+    }
+
+    fn position(&self) -> Duration {
+        self.offset + (self.played() - self.played_at_offset)
+    }
+
+    fn seek(&mut self, position: Duration) {
+        self.offset = position;
+        self.played_at_offset = self.played();
+    }
+
+    fn pause(&mut self) {
+        self.played = self.played();
+        self.resumed = None;
+    }
+
+    fn resume(&mut self) {
+        if self.resumed.is_none() {
+            self.resumed = Some(Instant::now());
+        }
+    }
+
+    fn play_event(&self) -> PlayEvent {
+        PlayEvent {
+            track_id: self.id,
+            album_id: self.album_id,
+            playlist_id: self.source.playlist_id(),
+            artist: self.artist.clone(),
+            title: self.title.clone(),
+            album: self.album.clone(),
+            duration: self.duration,
+            started: self.started,
+            played: self.played(),
+            position: self.position(),
+        }
+    }
+}
+
+// What other devices were last told, a change in any of it gets published
+#[derive(Debug, Clone, PartialEq)]
+struct YnisonSnapshot {
+    track: Option<TrackId>,
+    paused: bool,
+    source: String,
+    queue_length: usize,
+    offset: Duration,
+}
+
+// Source of a queue taken over from another device through Ynison
+const YNISON_QUEUE: &str = "ynison";
+
+// Settings from before an auto preset took over
+struct Overridden {
+    preset: usize,
+    volume: f32,
+    speed: f32,
+    eq: String,
+}
+
+// A queue shared with other devices, its tracks in their order there, which the local queue
+// drifts from through the blacklist, max_queue_length, shuffling and sorting
+#[derive(Debug)]
+struct SharedQueue {
+    id: String,
+    tracks: Vec<TrackId>,
+}
+
+// Everything needed to get back to the same passage sounding the same way
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    track_id: TrackId,
+    position: Duration,
+    volume: f32,
+    speed: f32,
+}
+
+const DUCK_FADE: Duration = Duration::from_millis(300);
+const DUCK_RESTORE: Duration = Duration::from_secs(2);
+
+// Volume goes down to a fraction of the user's one, holds, then slides back up
+#[derive(Debug, Clone, Copy)]
+struct Duck {
+    level: f32,
+    started: Instant,
+    hold: Duration,
+}
+
+struct QueueSearch {
+    query: String,
+    selected: Option<usize>,
+}
+
+// Integrated loudness in LUFS tracks are brought to when normalizing
+const NORMALIZATION_TARGET: f32 = -14.0;
+
+const FAVORITES_TITLE: &str = "Liked tracks";
+
+// Going back further into a track than this restarts it instead of playing the previous one
+const RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+
+// Tracks skipped sooner than this don't get the one after them prefetched
+const PREFETCH_DELAY: Duration = Duration::from_secs(3);
+
+// Playing with no samples pulled for this long means the device is gone
+const OUTPUT_STALL: Duration = Duration::from_secs(2);
+
+const SLEEP_FADE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+enum Sleep {
+    At(Instant),
+    TrackEnd(TrackId),
+}
+
+pub struct Player {
+    account: AccountStatus,
+    source: Source,
+    source_title: String,
+    tracks: Vec<Track>,
+    // Alongside tracks, one per track
+    origins: Vec<Origin>,
+    queue: Vec<usize>,
+    queue_position: usize,
+    pending_pages: Option<mpsc::UnboundedReceiver<Vec<Track>>>,
+    // Overrides switch_mode for the next source loaded
+    switch_once: Option<SwitchMode>,
+    // The queue shared with other devices, kept pointing at the playing track
+    remote_queue: Option<SharedQueue>,
+    announce: bool,
+    ynison: Option<ynison::Link>,
+    // Latest state of the account's devices, the player publishes its own only while playing
+    ynison_state: Option<ynison::State>,
+    ynison_sent: Option<YnisonSnapshot>,
+    queue_search: Option<QueueSearch>,
+    // Narrows what tracks lists, indices stay those of the full list
+    filter: Option<String>,
+    sort: SortKey,
+    shuffled: bool,
+    skip_played: bool,
+    no_repeats: bool,
+    normalize: bool,
+    eq: EqControl,
+    eq_preset: String,
+    overridden: Option<Overridden>,
+    jumped: bool,
+    played: HashSet<TrackId>,
+    history: VecDeque<HistoryEntry>,
+    blacklist: Blacklist,
+    music_sink: Sink,
+    client: Client,
+    shared_client: SharedClient,
+    next_track_task_handle: Option<oneshot::Receiver<Result<TrackData, ApiError>>>,
+    downloads: DownloadManager,
+    pending_downloads: Vec<(TrackId, String, Option<Duration>)>,
+    // Stale cache files an audit found, removed once confirmed
+    pending_removal: Vec<std::path::PathBuf>,
+    now_playing: Option<NowPlaying>,
+    current_audio: Option<std::io::Cursor<bytes::Bytes>>,
+    // Last track too large to prefetch, so the message is printed once
+    oversized: Option<TrackId>,
+    resume: ResumeStore,
+    // Files added with add-local by the ids their tracks got
+    local_files: HashMap<TrackId, std::path::PathBuf>,
+    status_file: Option<status::StatusFile>,
+    pending_offset: Option<ResumePoint>,
+    // The source has a resume point on a page that hasn't loaded yet
+    resume_unmatched: bool,
+    // Yandex rejected the token mid-session, nothing loads until a new one is set
+    unauthorized: bool,
+    snapshot: Option<Snapshot>,
+    duck: Option<Duck>,
+    sleep: Option<Sleep>,
+    limits: Limits,
+    // The user's volume while ducking or the sleep fade keep the sink below it
+    held_volume: Option<f32>,
+    // Volume to go back to when unmuting
+    muted: Option<f32>,
+    trims: TrimStore,
+    lyrics: Option<(TrackId, Option<Lyrics>)>,
+    lyrics_store: LyricsStore,
+    artist: Option<ArtistBrief>,
+    podcasts: Vec<AlbumInfo>,
+    albums: Vec<AlbumInfo>,
+    selected_playlist: Option<PlaylistInfo>,
+    landing: Vec<LandingEntity>,
+    radio_batch: Option<String>,
+    radio_retry_at: Option<Instant>,
+    follow_lyrics: bool,
+    lyrics_line: Option<usize>,
+    scrobblers: Scrobblers,
+    config: &'static Config,
+    quality: Quality,
+    cache: Option<AudioCache>,
+    output: AudioOutput,
+    heartbeat: Heartbeat,
+    // Beat count and when it last changed
+    last_beat: (usize, Instant),
+    // Tick while anything is changing, the player otherwise sleeps until an event
+    frame_time: Duration,
+}
+
+// The play order as frontends see it, shuffling only changes the order
+pub struct Queue<'a> {
+    tracks: &'a [Track],
+    order: &'a [usize],
+    current: Option<usize>,
+}
+
+impl<'a> Queue<'a> {
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn get(&self, n: usize) -> Option<&'a Track> {
+        self.order.get(n).map(|&i| &self.tracks[i])
+    }
+
+    // Index of the playing track
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a Track> + '_ {
+        self.order.iter().map(|&i| &self.tracks[i])
+    }
+}
+
+// Volume percents map onto decibels between VOLUME_FLOOR_DB and 0, with 0% being silence
+const VOLUME_FLOOR_DB: f32 = -50.0;
+
+fn percent_to_amplitude(percent: f32) -> f32 {
+    let percent = percent.clamp(0.0, 100.0);
+    if percent == 0.0 {
+        return 0.0;
+    }
+    10f32.powf(VOLUME_FLOOR_DB * (1.0 - percent / 100.0) / 20.0)
+}
+
+fn amplitude_to_percent(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        return 0.0;
+    }
+    (100.0 * (1.0 - 20.0 * amplitude.log10() / VOLUME_FLOOR_DB)).clamp(0.0, 100.0)
+}
+
+// Stable, so tracks equal under the key keep the list order
+fn sort_tracks(tracks: &mut [(usize, &Track)], key: SortKey) {
+    let lowercase = |text: &str| text.to_lowercase();
+    match key {
+        SortKey::Original | SortKey::Liked => {},
+        SortKey::Artist => tracks.sort_by_cached_key(|(_, track)| {
+            (lowercase(&track.artists.first().map_or(String::new(), |artist| artist.name.clone())), lowercase(&track.title))
+        }),
+        SortKey::Album => tracks.sort_by_cached_key(|(_, track)| {
+            (lowercase(track.albums.first().map_or("", |album| album.title.as_str())), lowercase(&track.title))
+        }),
+        SortKey::Title => tracks.sort_by_cached_key(|(_, track)| lowercase(&track.full_title())),
+        SortKey::Duration => tracks.sort_by_key(|(_, track)| track.duration),
+    }
+}
+
+// Best score among the title, artists and album, each matched on its own
+fn fuzzy_score(query: &str, track: &Track) -> Option<u32> {
+    std::iter::once(track.full_title())
+        .chain(track.artists.iter().map(|artist| artist.name.clone()))
+        .chain(track.albums.first().map(|album| album.title.clone()))
+        .filter_map(|field| fuzzy::score(query, &field))
+        .max()
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{}:{:02}", time.as_secs() / 60, time.as_secs() % 60)
+}
+
+fn print_progress(loaded: usize, total: usize) {
+    if loaded.is_multiple_of(100) || loaded == total {
+        tracing::info!("Loaded {}/{} tracks", loaded, total);
+    }
+}
+
+pub async fn init_player(
+    client: &Client,
+    config: &'static Config,
+    frame_time: u64,
+    headless: bool,
+) -> Result<Player, ApiError> {
+    let account = account_status(client).await?;
+    let uid = account.uid;
+    let mut tracks = library::liked_music_tracks(account.uid, client, config.fetch_concurrency, print_progress).await?;
+    let blacklist = Blacklist::load();
+    tracks.retain(|track| !blacklist.contains(track.id));
+    for track in &tracks {
+        if track.duration.is_none() {
+            tracing::debug!("Track without duration: {:?}", track);
+        }
+    }
+
+    let output = AudioOutput::open(headless);
+    let sink = output.new_sink();
+
+    let cache = config.cache.enabled.then(|| {
+        AudioCache::new(
+            config.cache.dir.clone().unwrap_or_else(|| crate::config::cache_dir().join("tracks")),
+            config.cache.max_size.map(|mib| mib * 1024 * 1024),
+            uid,
+        )
+    });
+
+    let shared_client = SharedClient::new(client.clone());
+    let scrobblers = Scrobblers::from_config(config, shared_client.clone(), account.uid);
+
+    let mut player = Player {
+        account,
+        source: Source::Favorites,
+        source_title: FAVORITES_TITLE.to_owned(),
+        queue: Vec::from_iter(0..tracks.len()),
+        origins: vec![Origin::Liked; tracks.len()],
+        tracks,
+        music_sink: sink,
+        output,
+        queue_position: 0,
+        pending_pages: None,
+        switch_once: None,
+        remote_queue: None,
+        announce: config.announce.enabled,
+        ynison: None,
+        ynison_state: None,
+        ynison_sent: None,
+        queue_search: None,
+        filter: None,
+        sort: SortKey::Original,
+        shuffled: false,
+        skip_played: config.skip_played,
+        no_repeats: config.no_repeats,
+        normalize: config.normalize,
+        eq: EqControl::default(),
+        eq_preset: "flat".to_owned(),
+        overridden: None,
+        jumped: false,
+        played: HashSet::new(),
+        history: VecDeque::new(),
+        blacklist,
+        next_track_task_handle: None,
+        downloads: DownloadManager::new(shared_client.clone(), cache.clone()),
+        shared_client,
+        pending_downloads: Vec::new(),
+        pending_removal: Vec::new(),
+        now_playing: None,
+        current_audio: None,
+        oversized: None,
+        resume: ResumeStore::load(uid),
+        local_files: HashMap::new(),
+        status_file: config.status_file.clone().map(status::StatusFile::new),
+        pending_offset: None,
+        resume_unmatched: false,
+        unauthorized: false,
+        snapshot: None,
+        duck: None,
+        sleep: None,
+        limits: Limits::new(&config.limits, uid),
+        held_volume: None,
+        muted: None,
+        trims: TrimStore::load(),
+        lyrics: None,
+        lyrics_store: LyricsStore::load(),
+        artist: None,
+        podcasts: Vec::new(),
+        albums: Vec::new(),
+        selected_playlist: None,
+        landing: Vec::new(),
+        radio_batch: None,
+        radio_retry_at: None,
+        follow_lyrics: false,
+        lyrics_line: None,
+        scrobblers,
+        config,
+        quality: config.quality,
+        cache,
+        client: client.clone(),
+        heartbeat: Heartbeat::default(),
+        last_beat: (0, Instant::now()),
+        frame_time: Duration::from_millis(frame_time),
+    };
+    player.restore_position();
+    player.restore_levels();
+    if config.equalizer.preset != player.eq_preset {
+        player.set_eq_preset(&config.equalizer.preset);
+    }
+
+    Ok(player)
+}
+
+impl Player {
+    // None once the queue has run out
+    pub fn next_track(&self) -> Option<&Track> {
+        self.track_after_n(0)
+    }
+
+    pub fn track_after_n(&self, n: usize) -> Option<&Track> {
+        let index = self.queue.get(self.queue_position + n)?;
+        self.tracks.get(*index)
+    }
+
+    pub fn item(&self, track: &Track) -> QueueItem {
+        match self.local_files.get(&track.id) {
+            Some(path) => QueueItem::Local(path.clone()),
+            None => QueueItem::Remote(track.id),
+        }
+    }
+
+    // Files added with add-local are only known here, so they are kept away from Yandex
+    // and from what is saved by track id, their ids get reused by other files next session
+    fn remote_now_playing(&self) -> Option<&NowPlaying> {
+        let Some(now_playing) = &self.now_playing else {
+            say!("Nothing is playing");
+            return None;
+        };
+        if local::is_local(now_playing.id) {
+            say!("{} is a local file", now_playing.title);
+            return None;
+        }
+        Some(now_playing)
+    }
+
+    // The loaded tracks Yandex knows about
+    fn remote_tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(|track| !local::is_local(track.id))
+    }
+
+    // Files go to the end of the queue next to the tracks of the current source
+    pub fn add_local(&mut self, path: &std::path::Path) {
+        let files = match local::scan(path) {
+            Ok(files) if files.is_empty() => {
+                say!("No audio files in {}", path.display());
+                return;
+            },
+            Ok(files) => files,
+            Err(err) => {
+                say!("Failed to read {}: {}", path.display(), err);
+                return;
+            },
+        };
+        let start = self.tracks.len();
+        for file in files {
+            let id = local::local_id(self.local_files.len());
+            self.tracks.push(local::track(&file, id));
+            self.local_files.insert(id, file);
+        }
+        self.origins.resize(self.tracks.len(), Origin::Manual);
+        self.queue.extend(start..self.tracks.len());
+        say!("Added {} local files", self.tracks.len() - start);
+    }
+
+    pub fn position(&self) -> Option<Duration> {
+        self.now_playing.as_ref().map(NowPlaying::position)
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.held_volume.unwrap_or(self.music_sink.volume())
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.music_sink.speed()
+    }
+
+    pub fn volume_percent(&self) -> f32 {
+        amplitude_to_percent(self.volume())
+    }
+
+    // Any explicit volume change ends the mute
+    pub fn set_volume(&mut self, volume: f32) {
+        self.muted = None;
+        let volume = volume.clamp(0.0, 1.0);
+        match &mut self.held_volume {
+            Some(held) => *held = volume,
+            None => self.music_sink.set_volume(volume),
+        }
+    }
+
+    pub fn set_volume_percent(&mut self, percent: f32) {
+        self.set_volume(percent_to_amplitude(percent));
+    }
+
+    pub fn toggle_mute(&mut self) -> bool {
+        match self.muted.take() {
+            Some(volume) => self.set_volume(volume),
+            None => {
+                let volume = self.volume();
+                self.set_volume(0.0);
+                self.muted = Some(volume);
+            },
+        }
+        self.muted.is_some()
+    }
+
+    // Steps are even on the perceptual scale, a delta of 0.05 is 5% of it
+    pub fn change_volume(&mut self, delta: f32) {
+        self.set_volume_percent(self.volume_percent() + delta * 100.0);
+    }
+
+    pub fn duck(&mut self, hold: Duration, level: f32) {
+        self.duck = Some(Duck {
+            level: level.clamp(0.0, 1.0),
+            started: Instant::now(),
+            hold,
+        });
+    }
+
+    fn duck_factor(&mut self) -> f32 {
+        let Some(duck) = self.duck else {
+            return 1.0;
+        };
+        let elapsed = duck.started.elapsed();
+        if elapsed < DUCK_FADE {
+            1.0 - (1.0 - duck.level) * elapsed.as_secs_f32() / DUCK_FADE.as_secs_f32()
+        } else if elapsed < DUCK_FADE + duck.hold {
+            duck.level
+        } else if elapsed < DUCK_FADE + duck.hold + DUCK_RESTORE {
+            let restored = (elapsed - DUCK_FADE - duck.hold).as_secs_f32() / DUCK_RESTORE.as_secs_f32();
+            duck.level + (1.0 - duck.level) * restored
+        } else {
+            self.duck = None;
+            1.0
+        }
+    }
+
+    fn sleep_factor(&self) -> f32 {
+        match self.sleep_remaining() {
+            Some(remaining) if remaining < SLEEP_FADE => remaining.as_secs_f32() / SLEEP_FADE.as_secs_f32(),
+            _ => 1.0,
+        }
+    }
+
+    fn apply_volume(&mut self) {
+        if self.duck.is_none() && self.sleep.is_none() {
+            if let Some(volume) = self.held_volume.take() {
+                self.music_sink.set_volume(volume);
+            }
+            return;
+        }
+        let factor = self.duck_factor() * self.sleep_factor();
+        let volume = *self.held_volume.get_or_insert(self.music_sink.volume());
+        self.music_sink.set_volume(volume * factor);
+    }
+
+    pub fn set_sleep(&mut self, after: Duration) {
+        self.sleep = Some(Sleep::At(Instant::now() + after));
+        say!("Pausing in {}", format_time(after));
+    }
+
+    pub fn sleep_at_track_end(&mut self) {
+        let Some(now_playing) = &self.now_playing else {
+            say!("Nothing is playing");
+            return;
+        };
+        self.sleep = Some(Sleep::TrackEnd(now_playing.id));
+        say!("Pausing after {}", now_playing.title);
+    }
+
+    pub fn cancel_sleep(&mut self) {
+        if self.sleep.take().is_some() {
+            say!("Sleep timer cancelled");
+        } else {
+            say!("No sleep timer set");
+        }
+    }
+
+    pub fn print_sleep(&self) {
+        match (self.sleep, self.sleep_remaining()) {
+            (None, _) => say!("No sleep timer set"),
+            (Some(Sleep::TrackEnd(_)), Some(remaining)) => {
+                say!("Pausing at the end of the current track, in {}", format_time(remaining))
+            },
+            (Some(Sleep::TrackEnd(_)), None) => say!("Pausing at the end of the current track"),
+            (Some(Sleep::At(_)), remaining) => say!("Pausing in {}", format_time(remaining.unwrap_or_default())),
+        }
+    }
+
+    // None when the end of the current track isn't known yet
+    fn sleep_remaining(&self) -> Option<Duration> {
+        match self.sleep? {
+            Sleep::At(at) => Some(at.saturating_duration_since(Instant::now())),
+            Sleep::TrackEnd(id) => match &self.now_playing {
+                Some(now_playing) if now_playing.id == id => {
+                    let end = self.trims.get(id).end.or(now_playing.duration)?;
+                    Some(end.saturating_sub(now_playing.position()))
+                },
+                // The track is over, whatever plays now shouldn't
+                Some(_) => Some(Duration::ZERO),
+                None => None,
+            },
+        }
+    }
+
+    fn check_sleep(&mut self) {
+        if self.sleep_remaining() == Some(Duration::ZERO) {
+            self.sleep = None;
+            self.set_paused(true);
+            self.apply_volume();
+            say!("Sleep timer: paused");
+        }
+    }
+
+    // Counts what was played and pauses once a limit kicks in
+    fn check_limits(&mut self) {
+        if !self.limits.is_enabled() {
+            return;
+        }
+        let playing = self.now_playing.is_some() && !self.music_sink.is_paused() && !self.music_sink.empty();
+        self.limits.count(playing);
+        if !playing {
+            return;
+        }
+        if let Some(lock) = self.limits.lock() {
+            self.set_paused(true);
+            self.limits.save();
+            say!("{}, paused", lock);
+        }
+    }
+
+    pub fn print_limits(&self) {
+        if !self.limits.is_enabled() {
+            say!("No limits are set");
+            return;
+        }
+        let listened = self.limits.listened().as_secs() / 60;
+        match self.limits.daily() {
+            Some(daily) => say!("Listened {} of {} min today", listened, daily),
+            None => say!("Listened {} min today", listened),
+        }
+        if let Some(lock) = self.limits.lock() {
+            say!("{}", lock);
+        }
+    }
+
+    pub fn unlock(&mut self, code: &str) {
+        match self.limits.unlock(code) {
+            Ok(true) => say!("Limits lifted until midnight"),
+            Ok(false) => say!("Wrong unlock code"),
+            Err(message) => say!("{}", message),
+        }
+    }
+
+    pub fn save_listened(&mut self) {
+        if self.limits.is_enabled() {
+            self.limits.save();
+        }
+    }
+
+    pub fn change_speed(&self, delta: f32) {
+        self.music_sink.set_speed(self.music_sink.speed() + delta);
+    }
+
+    fn recreate_sink(&mut self) {
+        let (volume, speed) = (self.music_sink.volume(), self.music_sink.speed());
+        self.music_sink.stop();
+
+        self.music_sink = self.output.new_sink();
+        self.music_sink.set_volume(volume);
+        self.music_sink.set_speed(speed);
+    }
+
+    pub fn move_next(&mut self) {
+        self.recreate_sink();
+    }
+
+    // Restarts the current track unless it only just began, the first one always restarts
+    pub fn move_prev(&mut self) {
+        let Some(current) = self.current_index() else {
+            return;
+        };
+        if current == 0 || self.position().is_some_and(|position| position > RESTART_THRESHOLD) {
+            let id = self.tracks[self.queue[current]].id;
+            self.seek(self.trims.get(id).start.unwrap_or_default());
+        } else {
+            self.jump_to(current - 1);
+        }
+    }
+
+    // Index into the loaded list, which differs from the queue order once shuffled
+    pub fn play_index(&mut self, n: usize) {
+        match self.queue.iter().position(|&i| i == n) {
+            Some(position) => self.jump_to(position),
+            None => say!("No track with index {}", n),
+        }
+    }
+
+    pub fn print_tracks(&self) {
+        let current = self.now_playing.as_ref().map(|now_playing| now_playing.id);
+        let mut shown: Vec<(usize, &Track)> = self.tracks.iter().enumerate().collect();
+        if self.sort != SortKey::Original {
+            say!("Sorted by {}", self.sort);
+        }
+        sort_tracks(&mut shown, self.sort);
+        if let Some(query) = &self.filter {
+            let mut scored: Vec<_> = shown
+                .into_iter()
+                .filter_map(|(n, track)| Some((fuzzy_score(query, track)?, n, track)))
+                .collect();
+            // Best matches first, ties keep the list order
+            scored.sort_by_key(|&(score, n, _)| (std::cmp::Reverse(score), n));
+            shown = scored.into_iter().map(|(_, n, track)| (n, track)).collect();
+            if shown.is_empty() {
+                say!("No tracks match {}", query);
+            }
+        }
+        for (n, track) in shown {
+            let marker = if Some(track.id) == current { "▶" } else { " " };
+            say!("{} {}. {}", marker, n, track);
+        }
+    }
+
+    pub fn set_sort(&mut self, key: SortKey) {
+        if key == SortKey::Liked && self.source != Source::Favorites {
+            say!("Only liked tracks have a like date");
+            return;
+        }
+        self.sort = key;
+        self.print_tracks();
+    }
+
+    pub fn set_filter(&mut self, query: Option<String>) {
+        self.filter = query;
+        if self.filter.is_some() {
+            self.print_tracks();
+        } else {
+            say!("Filter cleared");
+        }
+    }
+
+    // Moves the track to play right after the current one
+    pub fn enqueue(&mut self, n: usize) {
+        let Some(track) = self.tracks.get(n) else {
+            say!("No track with index {}", n);
+            return;
+        };
+        say!("Playing next: {}", track);
+        self.origins[n] = Origin::Manual;
+        if let Some(position) = self.queue.iter().position(|&i| i == n) {
+            self.queue.remove(position);
+            if position < self.queue_position {
+                self.queue_position -= 1;
+            }
+        }
+        self.queue.insert(self.queue_position, n);
+        self.next_track_task_handle = None;
+        self.clear_search_selection();
+    }
+
+    // Moves n tracks ahead, stopping at the last one
+    pub fn skip(&mut self, n: usize) {
+        if n == 0 || self.queue.is_empty() {
+            return;
+        }
+        let next = self.current_index().map_or(self.queue_position, |current| current + 1);
+        self.jump_to((next + n - 1).min(self.queue.len() - 1));
+    }
+
+    // Makes the queue entry at the index play next, right away
+    fn jump_to(&mut self, index: usize) {
+        self.queue_position = index;
+        self.jumped = true;
+        self.next_track_task_handle = None;
+        self.recreate_sink();
+    }
+
+    // How long nothing needs doing unless an event comes, None when only one can change anything
+    fn idle_for(&self) -> Option<Duration> {
+        if self.music_sink.empty() || self.pending_pages.is_some() || self.duck.is_some() {
+            return Some(self.frame_time);
+        }
+        if self.music_sink.is_paused() {
+            return None;
+        }
+        if self.follow_lyrics || self.sleep_remaining().is_some_and(|remaining| remaining < SLEEP_FADE) {
+            return Some(self.frame_time);
+        }
+        let trim_end = self.now_playing.as_ref().and_then(|now_playing| {
+            let end = self.trims.get(now_playing.id).end?;
+            Some(end.saturating_sub(now_playing.position()))
+        });
+        // Often enough to notice a stalled output or prefetch the next track in time
+        let idle = Duration::from_secs(1);
+        Some(
+            [trim_end, self.sleep_remaining().map(|remaining| remaining.saturating_sub(SLEEP_FADE))]
+                .into_iter()
+                .flatten()
+                .fold(idle, Duration::min)
+                .max(self.frame_time)
+        )
+    }
+
+    pub async fn wait(&self) {
+        match self.idle_for() {
+            Some(idle) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(idle) => {},
+                    _ = self.heartbeat.ended() => {},
+                }
+            },
+            None => self.heartbeat.ended().await,
+        }
+    }
+
+    fn append_audio(&self, audio: std::io::Cursor<bytes::Bytes>, offset: Duration, gain: f32) {
+        let source = Decoder::new(audio).unwrap().skip_duration(offset).amplify(gain);
+        self.music_sink.append(self.eq.wrap(self.heartbeat.wrap(source)));
+    }
+
+    // A device that stopped pulling samples is reopened and the track picks up where it was
+    fn check_output(&mut self) {
+        let beats = self.heartbeat.beats();
+        if beats != self.last_beat.0 || self.music_sink.is_paused() || self.music_sink.empty() {
+            self.last_beat = (beats, Instant::now());
+            return;
+        }
+        if self.last_beat.1.elapsed() < OUTPUT_STALL {
+            return;
+        }
+
+        tracing::warn!("Audio output stalled, reopening the device");
+        self.last_beat = (beats, Instant::now());
+        if self.output.reopen() {
+            // The position kept running while nothing was heard
+            if let Some(position) = self.position() {
+                self.seek(position.saturating_sub(OUTPUT_STALL));
+            }
+        }
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.now_playing.as_ref().and_then(|now_playing| now_playing.duration)
+    }
+
+    // rodio can't seek a playing source, so the current track is decoded anew from memory
+    pub fn seek(&mut self, position: Duration) {
+        let Some(audio) = self.current_audio.clone() else {
+            say!("Nothing is playing");
+            return;
+        };
+        let position = match self.duration() {
+            Some(duration) => position.min(duration),
+            None => position,
+        };
+
+        let paused = self.music_sink.is_paused();
+        self.recreate_sink();
+        let gain = self.now_playing.as_ref().map_or(1.0, |now_playing| now_playing.gain);
+        self.append_audio(audio, position, gain);
+        if paused {
+            self.music_sink.pause();
+        }
+
+        if let Some(now_playing) = &mut self.now_playing {
+            now_playing.seek(position);
+        }
+        self.lyrics_line = None;
+    }
+
+    pub fn seek_percent(&mut self, percent: f32) {
+        let Some(duration) = self.duration() else {
+            say!("Track length is unknown");
+            return;
+        };
+        self.seek(duration.mul_f32(percent.clamp(0.0, 100.0) / 100.0));
+    }
+
+    pub fn seek_by(&mut self, seconds: i64) {
+        let Some(position) = self.position() else {
+            say!("Nothing is playing");
+            return;
+        };
+        let delta = Duration::from_secs(seconds.unsigned_abs());
+        let target = if seconds < 0 { position.saturating_sub(delta) } else { position + delta };
+        self.seek(target);
+    }
+
+    // The playing track keeps its quality until refetched, the following ones use the new one
+    pub fn set_quality(&mut self, quality: Quality) {
+        self.quality = quality;
+        self.next_track_task_handle = None;
+        say!("Quality set to {} {} kbps", quality.codec, quality.bitrate);
+        if self.now_playing.is_some() {
+            say!("Type refetch to reload the current track at it");
+        }
+    }
+
+    pub fn trim_current(&mut self, f: impl FnOnce(&mut Trim)) {
+        let Some((id, title)) = self.remote_now_playing().map(|now_playing| (now_playing.id, now_playing.title.clone())) else {
+            return;
+        };
+        self.trims.update(id, f);
+        if let Err(err) = self.trims.save() {
+            tracing::warn!("Failed to save trims: {}", err);
+        }
+
+        let time = |time: Option<Duration>| time.map_or("-".to_owned(), format_time);
+        let trim = self.trims.get(id);
+        say!("{} now plays from {} to {}", title, time(trim.start), time(trim.end));
+    }
+
+    // Switching accounts would leave the loaded library and caches mixed, so only the same one is accepted
+    pub async fn reauthorize(&mut self, client: &Client) -> Result<bool, ApiError> {
+        let account = account_status(client).await?;
+        if account.uid != self.account.uid {
+            say!("The token belongs to {}, add it as a profile to switch accounts", account.login);
+            return Ok(false);
+        }
+        self.client = client.clone();
+        self.shared_client.set(client.clone());
+        self.unauthorized = false;
+        Ok(true)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.music_sink.is_paused()
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.is_paused() != paused {
+            self.toggle_playback();
+        }
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffled
+    }
+
+    pub fn queue(&self) -> Queue<'_> {
+        Queue {
+            tracks: &self.tracks,
+            order: &self.queue,
+            current: self.current_index(),
+        }
+    }
+
+    // Queue index of the playing track, the position already points past it
+    pub fn current_index(&self) -> Option<usize> {
+        self.now_playing.as_ref()?;
+        self.queue_position.checked_sub(1)
+    }
+
+    pub fn status(&self) -> String {
+        let Some(now_playing) = &self.now_playing else {
+            return "Stopped".to_owned();
+        };
+        let state = if self.music_sink.is_paused() { "Paused" } else { "Playing" };
+        format!(
+            "{}: {} - {} [{}/{}] volume {:.0}%, speed {:.2}",
+            state,
+            now_playing.artist,
+            now_playing.title,
+            format_time(now_playing.position()),
+            now_playing.duration.map_or("?".to_owned(), format_time),
+            self.volume_percent(),
+            self.speed(),
+        )
+    }
+
+    pub fn status_line(&self) -> status::Status {
+        let volume = self.volume_percent().round() as u16;
+        let Some(now_playing) = &self.now_playing else {
+            return status::Status {
+                text: String::new(),
+                tooltip: self.status(),
+                class: "stopped",
+                percentage: 0,
+                artist: None,
+                title: None,
+                album: None,
+                position: None,
+                duration: None,
+                volume,
+            };
+        };
+        let position = now_playing.position();
+        let percentage = now_playing.duration
+            .filter(|duration| !duration.is_zero())
+            .map_or(0, |duration| (position.as_secs_f64() / duration.as_secs_f64() * 100.0).min(100.0) as u8);
+        status::Status {
+            text: format!("{} - {}", now_playing.artist, now_playing.title),
+            tooltip: self.status(),
+            class: if self.music_sink.is_paused() { "paused" } else { "playing" },
+            percentage,
+            artist: Some(now_playing.artist.clone()),
+            title: Some(now_playing.title.clone()),
+            album: now_playing.album.clone(),
+            position: Some(position.as_secs()),
+            duration: now_playing.duration.map(|duration| duration.as_secs()),
+            volume,
+        }
+    }
+
+    // Whole seconds in the line keep the writes to one a second while playing
+    pub fn publish_status(&mut self) {
+        if self.status_file.is_none() {
+            return;
+        }
+        let line = self.status_line().to_json();
+        if let Some(file) = &mut self.status_file {
+            file.write(line);
+        }
+    }
+
+    pub fn take_snapshot(&mut self) {
+        let Some(now_playing) = &self.now_playing else {
+            say!("Nothing is playing");
+            return;
+        };
+        let snapshot = Snapshot {
+            track_id: now_playing.id,
+            position: now_playing.position(),
+            volume: self.volume(),
+            speed: self.speed(),
+        };
+        say!("Snapshot taken at {}:{:02} of {}", snapshot.position.as_secs() / 60, snapshot.position.as_secs() % 60, now_playing.title);
+        self.snapshot = Some(snapshot);
+    }
+
+    pub fn restore_snapshot(&mut self) {
+        let Some(snapshot) = self.snapshot else {
+            say!("No snapshot taken");
+            return;
+        };
+        self.set_volume(snapshot.volume);
+        self.music_sink.set_speed(snapshot.speed);
+
+        if self.now_playing.as_ref().is_some_and(|now_playing| now_playing.id == snapshot.track_id) {
+            self.seek(snapshot.position);
+            return;
+        }
+        let Some(position) = self.queue
+            .iter()
+            .position(|&i| self.tracks[i].id == snapshot.track_id)
+        else {
+            say!("The snapshot track is no longer queued");
+            return;
+        };
+
+        self.pending_offset = Some(ResumePoint { track_id: snapshot.track_id, position: snapshot.position });
+        self.jump_to(position);
+    }
+
+    pub fn toggle_playback(&mut self) {
+        if self.music_sink.is_paused() {
+            if let Some(lock) = self.limits.lock() {
+                say!("{}, playback is locked", lock);
+                return;
+            }
+            self.music_sink.play();
+            if let Some(now_playing) = &mut self.now_playing {
+                now_playing.resume();
+            }
+        } else {
+            self.music_sink.pause();
+            if let Some(now_playing) = &mut self.now_playing {
+                now_playing.pause();
+            }
+        }
+    }
+
+    pub fn toggle_lyrics_follow(&mut self) -> bool {
+        self.follow_lyrics = !self.follow_lyrics;
+        self.lyrics_line = None;
+        self.follow_lyrics
+    }
+
+    fn print_next_lyrics_line(&mut self) {
+        let (Some((id, Some(lyrics))), Some(now_playing)) = (&self.lyrics, &self.now_playing) else {
+            return;
+        };
+        if *id != now_playing.id || !lyrics.is_synced() {
+            return;
+        }
+
+        let line = lyrics.line_at(now_playing.position());
+        if line != self.lyrics_line {
+            if let Some(n) = line {
+                say!("♪ {}", lyrics.lines[n].text);
+            }
+            self.lyrics_line = line;
+        }
+    }
+
+    pub fn shuffle_tracks(&mut self, rng: &mut impl Rng) {
+        self.queue.shuffle(rng); 
+        self.shuffled = true;
+        self.reset();
+    }
+
+    pub fn toggle_announce(&mut self) -> bool {
+        self.announce = !self.announce;
+        self.announce
+    }
+
+    fn announce_track(&mut self) {
+        if !self.announce {
+            return;
+        }
+        let Some(track) = self.next_track() else {
+            return;
+        };
+        let artist = track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", ");
+        let text = format!("Next: {} \u{2014} {}", artist, track.full_title());
+        if announce::speak(&text, self.config.announce.command.as_deref()) {
+            self.duck(announce::estimate(&text), self.config.announce.duck_level);
+        } else {
+            tracing::warn!("No speech synthesizer found, set announce.command in the config");
+        }
+    }
+
+    pub fn toggle_skip_played(&mut self) -> bool {
+        self.skip_played = !self.skip_played;
+        self.skip_played
+    }
+
+    fn gain(&self, track: &Track) -> f32 {
+        match track.r128 {
+            Some(loudness) if self.normalize => loudness.gain(NORMALIZATION_TARGET),
+            _ => 1.0,
+        }
+    }
+
+    fn load_eq_preset(&mut self, name: &str) -> bool {
+        let Some(bands) = eq::find_preset(name, &self.config.equalizer.presets) else {
+            return false;
+        };
+        self.eq.set(bands);
+        self.eq_preset = name.to_owned();
+        true
+    }
+
+    pub fn set_eq_preset(&mut self, name: &str) {
+        if self.load_eq_preset(name) {
+            say!("Equalizer preset: {}", name);
+        } else {
+            say!("No equalizer preset named {}", name);
+        }
+    }
+
+    // Runs as the next track starts, leaving a preset brings back what it replaced
+    fn apply_auto_preset(&mut self) {
+        let Some(track) = self.next_track() else {
+            return;
+        };
+        let matched = presets::find(&self.config.auto_presets, track);
+        if self.overridden.as_ref().map(|overridden| overridden.preset) == matched {
+            return;
+        }
+        if let Some(overridden) = self.overridden.take() {
+            self.set_volume(overridden.volume);
+            self.music_sink.set_speed(overridden.speed);
+            self.load_eq_preset(&overridden.eq);
+        }
+        let Some(n) = matched else {
+            return;
+        };
+
+        self.overridden = Some(Overridden {
+            preset: n,
+            volume: self.volume(),
+            speed: self.speed(),
+            eq: self.eq_preset.clone(),
+        });
+        let preset = self.config.auto_presets[n].clone();
+        if let Some(name) = &preset.eq {
+            if !self.load_eq_preset(name) {
+                tracing::warn!("Auto preset {} names an unknown equalizer preset {}", n, name);
+            }
+        }
+        // A muted player stays muted
+        if let Some(percent) = preset.volume.filter(|_| self.muted.is_none()) {
+            self.set_volume_percent(percent);
+        }
+        if let Some(speed) = preset.speed {
+            self.music_sink.set_speed(speed);
+        }
+    }
+
+    pub fn print_eq(&self) {
+        match &self.overridden {
+            Some(overridden) => say!("Equalizer preset: {} (auto preset {}, {} otherwise)", self.eq_preset, overridden.preset, overridden.eq),
+            None => say!("Equalizer preset: {}", self.eq_preset),
+        }
+        say!("Available: {}", eq::preset_names(&self.config.equalizer.presets).join(", "));
+    }
+
+    // The playing track is decoded anew so the change is heard right away
+    pub fn toggle_normalize(&mut self) -> bool {
+        self.normalize = !self.normalize;
+        let gain = self.current_index().map(|n| self.gain(&self.tracks[self.queue[n]]));
+        if let (Some(gain), Some(now_playing)) = (gain, &mut self.now_playing) {
+            now_playing.gain = gain;
+            let position = now_playing.position();
+            self.seek(position);
+        }
+        self.normalize
+    }
+
+    pub fn toggle_no_repeats(&mut self) -> bool {
+        self.no_repeats = !self.no_repeats;
+        self.no_repeats
+    }
+
+    // In shuffle mode (or always, with no repeats) already heard tracks are passed over,
+    // unless nothing else is left or the user explicitly went back to one
+    fn skip_played_tracks(&mut self) {
+        if !(self.no_repeats || self.shuffled && self.skip_played) || self.jumped {
+            return;
+        }
+
+        let unplayed = self.queue[self.queue_position..]
+            .iter()
+            .position(|&i| !self.played.contains(&self.tracks[i].id));
+        if let Some(skip) = unplayed.filter(|&skip| skip > 0) {
+            self.queue_position += skip;
+            self.next_track_task_handle = None;
+        }
+    }
+
+    // Drops every queue entry of the track, keeping the position on the same next track
+    fn remove_from_queue(&mut self, id: TrackId) {
+        let before = self.queue[..self.queue_position]
+            .iter()
+            .filter(|&&i| self.tracks[i].id == id)
+            .count();
+        self.queue.retain(|&i| self.tracks[i].id != id);
+        self.queue_position -= before;
+        self.next_track_task_handle = None;
+        self.clear_search_selection();
+    }
+
+    // Forgets up to count of the tracks played before the current one, later indices shift down
+    fn drop_played(&mut self, count: usize) {
+        let count = count.min(self.current_index().unwrap_or(self.queue_position));
+        if count == 0 {
+            return;
+        }
+        let dropped: HashSet<usize> = self.queue.drain(..count).collect();
+        self.queue_position -= count;
+
+        let mut remap = vec![0; self.tracks.len()];
+        let mut kept = Vec::with_capacity(self.tracks.len() - dropped.len());
+        let mut origins = Vec::with_capacity(kept.capacity());
+        let tracks = std::mem::take(&mut self.tracks);
+        for (n, (track, origin)) in tracks.into_iter().zip(std::mem::take(&mut self.origins)).enumerate() {
+            if !dropped.contains(&n) {
+                remap[n] = kept.len();
+                kept.push(track);
+                origins.push(origin);
+            }
+        }
+        self.tracks = kept;
+        self.origins = origins;
+        for n in &mut self.queue {
+            *n = remap[*n];
+        }
+        self.clear_search_selection();
+    }
+
+    // Queue indices shift when the queue is reordered or shrinks
+    fn clear_search_selection(&mut self) {
+        if let Some(search) = &mut self.queue_search {
+            search.selected = None;
+        }
+    }
+
+    pub fn print_queue(&self) {
+        let current = self.now_playing.as_ref().map(|now_playing| now_playing.id);
+        let start = self.queue_position.saturating_sub(5);
+        let end = (self.queue_position + 10).min(self.queue.len());
+        let selected = self.queue_search.as_ref().and_then(|search| search.selected);
+        for n in start..end {
+            let track = &self.tracks[self.queue[n]];
+            let marker = if Some(track.id) == current {
+                "▶"
+            } else if Some(n) == selected {
+                ">"
+            } else if self.played.contains(&track.id) {
+                "✓"
+            } else {
+                " "
+            };
+            say!("{} {}. {} [{}]", marker, n, track, self.origins[self.queue[n]]);
+        }
+    }
+
+    pub fn print_history(&self) {
+        if self.history.is_empty() {
+            say!("Nothing played yet");
+        }
+        for entry in &self.history {
+            say!("{} {} [{}]", entry.started.with_timezone(&Local).format("%H:%M"), entry.track, entry.origin);
+        }
+    }
+
+    fn source_origin(&self) -> Origin {
+        let title = self.source_title.clone();
+        match &self.source {
+            Source::Favorites => Origin::Liked,
+            Source::Playlist { .. } => Origin::Playlist(title),
+            Source::Album(_) => Origin::Album(title),
+            Source::Artist(_) => Origin::Artist(title),
+            Source::Radio(station) if station.starts_with("track:") => Origin::SimilarTo(title),
+            Source::Radio(_) => Origin::Radio(title),
+            Source::Queue(_) => Origin::Queue(title),
+        }
+    }
+
+    // New tracks of the current source go to the end of the queue
+    fn append_tracks(&mut self, tracks: Vec<Track>) {
+        let start = self.tracks.len();
+        self.tracks.extend(tracks);
+        self.origins.resize(self.tracks.len(), self.source_origin());
+        self.queue.extend(start..self.tracks.len());
+    }
+    
+    pub fn landing_titles(&self) -> Vec<String> {
+        self.landing.iter().filter_map(landing_title).collect()
+    }
+
+    pub fn print_library(&self) {
+        let artists = library::ArtistIndex::new(&self.tracks);
+        for group in library::group_by_album(&self.tracks) {
+            say!("{} - {} [{} tracks]", group.album, group.artist(&artists), group.tracks.len());
+        }
+    }
+
+    pub fn search_queue(&mut self, query: String) {
+        self.queue_search = Some(QueueSearch { query, selected: None });
+        self.search_next(true);
+    }
+
+    // Cycles through matches starting after the selection, or the playing track before the first one
+    pub fn search_next(&mut self, forward: bool) {
+        let Some(search) = &self.queue_search else {
+            say!("No search in progress");
+            return;
+        };
+        let len = self.queue.len();
+        let from = search.selected.or(self.current_index()).unwrap_or(len.saturating_sub(1));
+        let found = (1..=len)
+            .map(|step| if forward { (from + step) % len } else { (from + len - step % len) % len })
+            .find(|&n| self.tracks[self.queue[n]].matches(&search.query));
+        match found {
+            Some(n) => {
+                say!("{}. {}", n, self.tracks[self.queue[n]]);
+                self.queue_search.as_mut().unwrap().selected = Some(n);
+            },
+            None => say!("No matches for {} in the queue", search.query),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.queue_position = 0;
+        self.next_track_task_handle = None;
+        self.clear_search_selection();
+    }
+
+    fn replace_tracks(&mut self, mut tracks: Vec<Track>, source: Source, title: String) {
+        self.remember_position();
+        tracks.retain(|track| !self.blacklist.contains(track.id));
+        if tracks.len() > self.config.max_queue_length {
+            say!(
+                "Queued the first {} of {} tracks, raise max_queue_length to queue them all",
+                self.config.max_queue_length,
+                tracks.len(),
+            );
+            tracks.truncate(self.config.max_queue_length);
+        }
+        let mode = self.switch_once.take().unwrap_or(self.config.switch_mode);
+        // Carried over into the new queue, so the queue always holds what is heard
+        let current = self.current_index()
+            .filter(|_| mode == SwitchMode::AfterCurrent)
+            .map(|position| self.queue[position]);
+        let mut old_tracks = std::mem::replace(&mut self.tracks, tracks);
+        let mut old_origins = std::mem::take(&mut self.origins);
+        self.pending_pages = None;
+        self.remote_queue = None;
+        self.source = source;
+        self.source_title = title;
+        self.origins = vec![self.source_origin(); self.tracks.len()];
+        self.shuffled = false;
+        if self.sort == SortKey::Liked && self.source != Source::Favorites {
+            self.sort = SortKey::Original;
+        }
+        self.reset();
+        self.queue = Vec::from_iter(0..self.tracks.len());
+        self.restore_position();
+
+        match current {
+            Some(n) => {
+                self.tracks.push(old_tracks.swap_remove(n));
+                self.origins.push(old_origins.swap_remove(n));
+                self.queue.insert(self.queue_position, self.tracks.len() - 1);
+                self.queue_position += 1;
+            },
+            None if mode == SwitchMode::Now => self.recreate_sink(),
+            None => {},
+        }
+    }
+
+    fn skip_jingles(&self, tracks: &mut Vec<Track>) {
+        if !self.config.skip_jingles {
+            return;
+        }
+        tracks.retain(|track| {
+            let jingle = track.is_jingle();
+            if jingle {
+                tracing::info!("Skipping jingle {}", track.full_title());
+            }
+            !jingle
+        });
+    }
+
+    pub fn switch_once(&mut self, mode: SwitchMode) {
+        self.switch_once = Some(mode);
+    }
+
+    // Appends pages of the current source that finished loading in the background
+    fn receive_pages(&mut self) {
+        loop {
+            let Some(pages) = &mut self.pending_pages else {
+                return;
+            };
+            match pages.try_recv() {
+                Ok(mut tracks) => {
+                    tracks.retain(|track| !self.blacklist.contains(track.id));
+                    let room = self.config.max_queue_length.saturating_sub(self.tracks.len());
+                    let full = tracks.len() > room;
+                    tracks.truncate(room);
+                    self.append_tracks(tracks);
+                    // Nothing of the source has played yet, so the saved track can still be picked up
+                    let started = self.now_playing.as_ref().is_some_and(|now_playing| now_playing.source == self.source);
+                    if self.resume_unmatched && self.pending_offset.is_none() && !started {
+                        self.restore_position();
+                    }
+                    if full {
+                        say!("Stopped loading at {} tracks, raise max_queue_length to queue them all", self.tracks.len());
+                        self.pending_pages = None;
+                        break;
+                    }
+                },
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.pending_pages = None;
+                    break;
+                },
+            }
+        }
+    }
+
+    // Episodes listened to the (almost) very end start over next time
+    fn remember_episode(&mut self, episode: &NowPlaying) {
+        if !episode.podcast {
+            return;
+        }
+
+        let position = episode.position();
+        match episode.duration {
+            Some(duration) if position + Duration::from_secs(10) >= duration => self.resume.finish_episode(episode.id),
+            _ => self.resume.set_episode(episode.id, position),
+        }
+        if let Err(err) = self.resume.save() {
+            tracing::warn!("Failed to save resume positions: {}", err);
+        }
+    }
+
+    pub fn remember_position(&mut self) {
+        if let Some(now_playing) = self.now_playing.take() {
+            self.remember_episode(&now_playing);
+            self.now_playing = Some(now_playing);
+        }
+
+        let Some(now_playing) = &self.now_playing else {
+            return;
+        };
+        if now_playing.source != self.source || local::is_local(now_playing.id) {
+            return;
+        }
+
+        let point = ResumePoint {
+            track_id: now_playing.id,
+            position: now_playing.position(),
+        };
+        self.resume.set(self.source.key(), point);
+        if let Some(index) = self.current_index() {
+            self.resume.set_last(LastSession {
+                source: self.source.key(),
+                title: self.source_title.clone(),
+                index,
+                total: self.queue.len(),
+                point,
+            });
+        }
+        if let Err(err) = self.resume.save() {
+            tracing::warn!("Failed to save resume positions: {}", err);
+        }
+    }
+
+    // Muted sessions come back at the volume they were muted from
+    pub fn remember_levels(&mut self) {
+        // What an auto preset set is its own, the levels underneath are the ones to keep
+        let levels = match &self.overridden {
+            Some(overridden) => Levels { volume: overridden.volume, speed: overridden.speed },
+            None => Levels { volume: self.muted.unwrap_or(self.volume()), speed: self.speed() },
+        };
+        self.resume.set_levels(levels);
+        if let Err(err) = self.resume.save() {
+            tracing::warn!("Failed to save volume: {}", err);
+        }
+    }
+
+    fn restore_levels(&mut self) {
+        if let Some(levels) = self.resume.levels() {
+            self.set_volume(levels.volume);
+            self.music_sink.set_speed(levels.speed);
+        }
+        if let Some(percent) = self.config.startup_volume {
+            self.set_volume_percent(percent);
+        }
+    }
+
+    // Nothing to offer when the player already started from where the last session ended
+    pub fn last_session_banner(&self) -> Option<String> {
+        let last = self.resume.last()?;
+        if last.source == self.source.key() {
+            return None;
+        }
+        let kind = Source::from_key(&last.source)?.kind();
+        Some(format!(
+            "Last session: {} '{}', track {}/{} at {} — press r to resume",
+            kind,
+            last.title,
+            last.index + 1,
+            last.total,
+            format_time(last.point.position),
+        ))
+    }
+
+    fn restore_position(&mut self) {
+        self.resume_unmatched = false;
+        let Some(point) = self.resume.get(&self.source.key()) else {
+            return;
+        };
+        let Some(position) = self.queue
+            .iter()
+            .position(|&i| self.tracks[i].id == point.track_id)
+        else {
+            // Retried as the rest of the source loads, if it comes in pages
+            self.resume_unmatched = true;
+            return;
+        };
+
+        self.queue_position = position;
+        self.pending_offset = Some(point);
+    }
+
+    pub fn downloads(&self) -> &DownloadManager {
+        &self.downloads
+    }
+
+    pub fn shared_client(&self) -> SharedClient {
+        self.shared_client.clone()
+    }
+
+    pub fn uid(&self) -> Uid {
+        self.account.uid
+    }
+
+    // Tracks that disappear can't be downloaded anymore, so the rest gets saved while it still can be
+    pub fn report_availability(&mut self, report: Report, download: bool) {
+        for title in &report.restored {
+            say!("{} is available again", title);
+        }
+        if !report.lost.is_empty() {
+            say!("{} liked tracks are no longer available:", report.lost.len());
+            for track in &report.lost {
+                let kept = if self.is_cached(track.id) { ", kept in the cache" } else { "" };
+                say!("  {}{}", track, kept);
+            }
+        }
+
+        let lost = !report.lost.is_empty();
+        if download || (lost && self.config.availability.auto_download) {
+            self.download_tracks(&report.available);
+        } else if lost && self.cache.is_some() {
+            say!("Save the {} still available with availability download", report.available.len());
+        }
+    }
+
+    pub fn download_track(&self, n: usize) {
+        if self.cache.is_none() {
+            say!("Downloads are kept in the audio cache, enable it in the config first");
+            return;
+        }
+        let Some(track) = self.tracks.get(n) else {
+            say!("No track with index {}", n);
+            return;
+        };
+        if local::is_local(track.id) {
+            say!("{} is a local file", track);
+            return;
+        }
+        if let Some(cache) = self.cache.as_ref().filter(|_| self.is_cached(track.id)) {
+            cache.pin(track.id, self.quality);
+            say!("{} is already downloaded", track);
+            return;
+        }
+
+        let duration = track.duration.map(Duration::from_millis);
+        drop(self.downloads.enqueue(track.id, self.quality, track.to_string(), duration, DownloadKind::Explicit));
+    }
+
+    // The listed indices into the loaded tracks, or all of them, uncached ones are left out
+    pub fn export_bundle(&self, dir: &std::path::Path, indices: &[usize]) {
+        let Some(cache) = &self.cache else {
+            say!("Only cached tracks can be exported, enable the cache in the config first");
+            return;
+        };
+        let mut tracks = if indices.is_empty() {
+            self.remote_tracks().collect::<Vec<_>>()
+        } else {
+            match indices.iter().map(|&n| self.tracks.get(n).ok_or(n)).collect::<Result<Vec<_>, _>>() {
+                Ok(tracks) => tracks,
+                Err(n) => {
+                    say!("No track with index {}", n);
+                    return;
+                },
+            }
+        };
+        tracks.retain(|track| !local::is_local(track.id));
+
+        match bundle::export(dir, &tracks, cache, self.quality) {
+            Ok(exported) => {
+                say!("Exported {} tracks to {}", exported.copied, dir.display());
+                if !exported.missing.is_empty() {
+                    say!("{} not downloaded, skipped: {}", exported.missing.len(), exported.missing.join(", "));
+                }
+            },
+            Err(err) => say!("Failed to export: {}", err),
+        }
+    }
+
+    pub fn export_queue(&self, format: export::Format, path: &std::path::Path) {
+        let tracks = self.remote_tracks().collect::<Vec<_>>();
+        match export::write(path, &self.source_title, &tracks, format, self.cache.as_ref(), self.quality) {
+            Ok(()) => say!("Exported {} tracks to {}", tracks.len(), path.display()),
+            Err(err) => say!("Failed to export: {}", err),
+        }
+    }
+
+    // Tagged files outside the cache, for other players and devices
+    pub async fn save_tracks(&self, n: Option<usize>, dir: &std::path::Path) {
+        let tracks = match n {
+            Some(n) => match self.tracks.get(n) {
+                Some(track) if local::is_local(track.id) => {
+                    say!("{} is a local file", track);
+                    return;
+                },
+                Some(track) => vec![track],
+                None => {
+                    say!("No track with index {}", n);
+                    return;
+                },
+            },
+            None => tagged::distinct(self.remote_tracks()),
+        };
+        let progress = tagged::print_progress(tracks.len());
+        match tagged::save_tracks(&tracks, dir, self.quality, self.cache.as_ref(), &self.client, SAVE_JOBS, progress).await {
+            Ok(saved) => say!("Saved {} of {} tracks to {}", saved, tracks.len(), dir.display()),
+            Err(err) => say!("Failed to save to {}: {}", dir.display(), err),
+        }
+    }
+
+    // Sizes are estimated from the duration and the preferred bitrate, the real ones are unknown until downloaded
+    pub fn download_tracks(&mut self, tracks: &[Track]) {
+        let Some(cache) = &self.cache else {
+            say!("Downloads are kept in the audio cache, enable it in the config first");
+            return;
+        };
+
+        let mut budget = cache.remaining();
+        let mut jobs = Vec::new();
+        let mut size = 0;
+        for track in tracks {
+            if self.blacklist.contains(track.id) {
+                continue;
+            }
+            if self.is_cached(track.id) {
+                cache.pin(track.id, self.quality);
+                continue;
+            }
+            let estimate = track.duration.unwrap_or(0) * self.quality.bitrate as u64 / 8;
+            if budget.is_some_and(|budget| budget < estimate) {
+                say!("Cache size limit reached, skipping the rest");
+                break;
+            }
+            budget = budget.map(|budget| budget - estimate);
+
+            jobs.push((track.id, track.to_string(), track.duration.map(Duration::from_millis)));
+            size += estimate;
+        }
+
+        let eta = match self.downloads.last_speed() {
+            Some(speed) => format!(", about {} min to download", (size as f64 / speed / 60.0).ceil()),
+            None => String::new(),
+        };
+        say!("{} tracks, ~{} MiB{}", jobs.len(), size / 1024 / 1024, eta);
+        if self.config.cache.confirm_above.is_some_and(|mib| size > mib * 1024 * 1024) {
+            say!("Type confirm-download to start");
+            self.pending_downloads = jobs;
+            return;
+        }
+        self.enqueue_downloads(jobs);
+    }
+
+    pub fn confirm_downloads(&mut self) {
+        if self.pending_downloads.is_empty() {
+            say!("No downloads waiting for confirmation");
+            return;
+        }
+        let jobs = std::mem::take(&mut self.pending_downloads);
+        self.enqueue_downloads(jobs);
+    }
+
+    pub fn confirm_removal(&mut self) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        if self.pending_removal.is_empty() {
+            say!("No stale tracks waiting for confirmation, run audit fix stale first");
+            return;
+        }
+        let removed = std::mem::take(&mut self.pending_removal)
+            .iter()
+            .filter(|path| match cache.remove(path) {
+                Ok(()) => true,
+                Err(err) => {
+                    tracing::warn!("Failed to remove {}: {}", path.display(), err);
+                    false
+                },
+            })
+            .count();
+        say!("Removed {} stale tracks from the cache", removed);
+    }
+
+    fn enqueue_downloads(&self, jobs: Vec<(TrackId, String, Option<Duration>)>) {
+        say!("Queued {} tracks for download", jobs.len());
+        for (id, title, duration) in jobs {
+            drop(self.downloads.enqueue(id, self.quality, title, duration, DownloadKind::Explicit));
+        }
+    }
+
+    // Holding the next track besides the playing one mustn't go past max_audio_memory,
+    // otherwise it's only loaded once it has to play
+    fn next_fits_in_memory(&mut self) -> bool {
+        let Some(track) = self.next_track() else {
+            return false;
+        };
+        let playing = self.current_audio.as_ref().map_or(0, |audio| audio.get_ref().len() as u64);
+        let estimate = track.duration.unwrap_or(0) * self.quality.bitrate as u64 / 8;
+        if playing + estimate <= self.config.max_audio_memory * 1024 * 1024 {
+            return true;
+        }
+        if self.oversized != Some(track.id) {
+            say!("Not prefetching {}, it would take the audio in memory past max_audio_memory", track);
+            self.oversized = Some(track.id);
+        }
+        false
+    }
+
+    fn is_cached(&self, id: TrackId) -> bool {
+        local::is_local(id) || self.cache
+            .as_ref()
+            .is_some_and(|cache| cache.contains(id, self.quality))
+    }
+
+    fn report_play(&mut self, finished: NowPlaying) {
+        self.remember_episode(&finished);
+        if let Source::Radio(station) = &finished.source {
+            let kind = match finished.duration {
+                Some(duration) if finished.position() + Duration::from_secs(10) >= duration => "trackFinished",
+                _ => "skip",
+            };
+            self.radio_feedback(station, kind, Some((finished.id, finished.played())));
+        }
+        self.scrobblers.scrobble(finished.play_event());
+    }
+
+    fn report_now_playing(&self) {
+        if let Some(now_playing) = &self.now_playing {
+            if let Source::Radio(station) = &now_playing.source {
+                self.radio_feedback(station, "trackStarted", Some((now_playing.id, Duration::ZERO)));
+            }
+            self.scrobblers.now_playing(now_playing.play_event());
+        }
+    }
+
+    pub fn attach_ynison(&mut self, link: ynison::Link) {
+        self.ynison = Some(link);
+        self.ynison_state = None;
+        self.ynison_sent = None;
+    }
+
+    // Local files can't be played elsewhere, so other devices never see them
+    fn ynison_playables(&self) -> Vec<ynison::Playable> {
+        self.queue
+            .iter()
+            .filter(|&&n| !local::is_local(self.tracks[n].id))
+            .map(|&n| ynison::Playable {
+                track_id: self.tracks[n].id,
+                album_id: self.tracks[n].albums.first().map(|album| album.id),
+            })
+            .collect()
+    }
+
+    fn ynison_status(&self) -> ynison::Status {
+        ynison::Status {
+            paused: self.is_paused() || self.now_playing.is_none(),
+            progress: self.position().unwrap_or_default(),
+            duration: self.duration().unwrap_or_default(),
+        }
+    }
+
+    // Playing here makes this the active device, other devices then follow what happens here
+    fn publish_ynison(&mut self) {
+        let (Some(link), Some(state)) = (&self.ynison, &self.ynison_state) else {
+            return;
+        };
+        let own = link.device_id.clone();
+        let active = state.active_device.as_deref() == Some(own.as_str());
+        let status = self.ynison_status();
+        if !active && status.paused {
+            return;
+        }
+
+        let snapshot = YnisonSnapshot {
+            track: self.now_playing.as_ref().map(|now_playing| now_playing.id),
+            paused: status.paused,
+            source: self.source.key(),
+            queue_length: self.queue.len(),
+            offset: self.now_playing.as_ref().map_or(Duration::ZERO, |now_playing| now_playing.offset),
+        };
+        if active && self.ynison_sent.as_ref() == Some(&snapshot) {
+            return;
+        }
+        let status_only = active && self.ynison_sent.as_ref().is_some_and(|sent| {
+            sent.track == snapshot.track && sent.source == snapshot.source && sent.queue_length == snapshot.queue_length
+        });
+        let update = if status_only {
+            ynison::Update::Status(status)
+        } else {
+            let index = self.queue[..self.current_index().unwrap_or(0)]
+                .iter()
+                .filter(|&&n| !local::is_local(self.tracks[n].id))
+                .count();
+            ynison::Update::Queue { queue: self.ynison_playables(), index, status }
+        };
+        link.send(update);
+        if !active {
+            link.send(ynison::Update::Activate(own.clone()));
+            if let Some(state) = &mut self.ynison_state {
+                state.active_device = Some(own);
+            }
+        }
+        self.ynison_sent = Some(snapshot);
+    }
+
+    pub fn print_devices(&self) {
+        let (Some(link), Some(state)) = (&self.ynison, &self.ynison_state) else {
+            say!("Not connected to Ynison");
+            return;
+        };
+        for (n, device) in state.devices.iter().enumerate() {
+            let marker = if state.active_device.as_ref() == Some(&device.id) { "▶" } else { " " };
+            let own = if device.id == link.device_id { " (this player)" } else { "" };
+            say!("{} {}. {}{}", marker, n, device.title, own);
+        }
+    }
+
+    // The other device continues from here, this one stops
+    pub fn give_playback(&mut self, n: usize) {
+        let (Some(link), Some(state)) = (&self.ynison, &mut self.ynison_state) else {
+            say!("Not connected to Ynison");
+            return;
+        };
+        let Some(device) = state.devices.get(n) else {
+            say!("No device with index {}", n);
+            return;
+        };
+        if device.id == link.device_id {
+            say!("That's this player");
+            return;
+        }
+        if self.now_playing.is_none() {
+            say!("Nothing is playing");
+            return;
+        }
+
+        let (id, title) = (device.id.clone(), device.title.clone());
+        state.active_device = Some(id.clone());
+        let status = ynison::Status { paused: false, ..self.ynison_status() };
+        let queue = self.ynison_playables();
+        let index = self.current_index().unwrap_or(0);
+        let link = self.ynison.as_ref().unwrap();
+        link.send(ynison::Update::Queue { queue, index, status });
+        link.send(ynison::Update::Activate(id));
+        self.set_paused(true);
+        self.ynison_sent = None;
+        say!("Handed playback to {}", title);
+    }
+
+    fn sync_remote_queue(&self) {
+        let (Some(remote), Some(now_playing)) = (&self.remote_queue, &self.now_playing) else {
+            return;
+        };
+        // A track only this queue has leaves the shared one where it was
+        let Some(index) = remote.tracks.iter().position(|&id| id == now_playing.id) else {
+            return;
+        };
+        let (id, client) = (remote.id.clone(), self.client.clone());
+        tokio::spawn(async move {
+            if let Err(err) = update_queue_position(&id, index, &client).await {
+                tracing::warn!("Failed to update the shared queue: {}", err);
+            }
+        });
+    }
+
+    // The station tunes the following batches by what was listened to and what got skipped
+    fn radio_feedback(&self, station: &str, kind: &'static str, track: Option<(TrackId, Duration)>) {
+        let Some(batch_id) = self.radio_batch.clone() else {
+            return;
+        };
+        if track.is_some_and(|(id, _)| local::is_local(id)) {
+            return;
+        }
+        let feedback = StationFeedback {
+            kind,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            from: "yandex_music_tui",
+            track_id: track.map(|(id, _)| id),
+            total_played_seconds: track.map(|(_, played)| played.as_secs_f64()),
+        };
+        let station = station.to_owned();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            if let Err(err) = send_station_feedback(&station, &batch_id, &feedback, &client).await {
+                tracing::warn!("Failed to send radio feedback: {}", err);
+            }
+        });
+    }
+}
+
+pub async fn check_availability(player: &mut Player, download: bool) -> Result<(), ApiError> {
+    let report = availability::check(player.account.uid, &player.client).await?;
+    if report.lost.is_empty() && report.restored.is_empty() {
+        say!("Nothing changed since the last check, {} liked tracks are available", report.available.len());
+    }
+    player.report_availability(report, download);
+
+    Ok(())
+}
+
+// Likes what Yandex has of a Spotify export or a plain artist,title list
+pub async fn import_likes(player: &Player, path: &std::path::Path) -> Result<(), ApiError> {
+   let rows = match import::read_rows(path) {
+       Ok(rows) if rows.is_empty() => {
+           say!("No tracks in {}", path.display());
+           return Ok(());
+       },
+       Ok(rows) => rows,
+       Err(err) => {
+           say!("Failed to read {}: {}", path.display(), err);
+           return Ok(());
+       },
+   };
+   say!("Searching for {} tracks", rows.len());
+   let progress = |done: usize, total| {
+       if done.is_multiple_of(50) && done != total {
+           say!("Searched {}/{}", done, total);
+       }
+   };
+   let report = import::import_likes(rows, player.account.uid, &player.client, player.config.fetch_concurrency, progress).await?;
+   say!("Liked {} tracks, {} were liked already", report.liked, report.already_liked);
+   if !report.unmatched.is_empty() {
+       say!("No match for {}:", report.unmatched.len());
+       for row in &report.unmatched {
+           say!("  {}", row);
+       }
+   }
+   if !report.failed.is_empty() {
+       say!("Failed to search for {}, import the file again to retry them:", report.failed.len());
+       for (row, err) in &report.failed {
+           say!("  {}: {}", row, err);
+       }
+   }
+   Ok(())
+}
+
+// Cross-checks the cache and the saved positions against the account, reconciling the given categories
+pub async fn audit(player: &mut Player, fix: &[audit::Category]) -> Result<(), ApiError> {
+   let liked = liked_tracks_infos(player.account.uid, &player.client).await?
+       .into_iter()
+       .map(|info| info.id)
+       .collect::<HashSet<_>>();
+   let mut keep = liked.clone();
+   keep.extend(player.tracks.iter().map(|track| track.id));
+   let mut contents = HashMap::new();
+   for playlist in playlists(player).await? {
+       let ids = playlist_track_ids(&playlist, &player.client).await?;
+       keep.extend(ids.iter().copied());
+       contents.insert(Source::Playlist { uid: playlist.uid, kind: playlist.kind }.key(), (playlist.title, ids));
+   }
+
+   let mut report = audit::Report::default();
+   if let Some(cache) = &player.cache {
+       audit::stale_audio(cache, &keep, &mut report);
+   }
+   let points = player.resume.points().map(|(key, point)| (key.to_owned(), point)).collect::<Vec<_>>();
+   for (key, point) in points {
+       let present = match Source::from_key(&key) {
+           Some(Source::Favorites) => liked.contains(&point.track_id),
+           Some(Source::Playlist { uid, kind }) => {
+               if !contents.contains_key(&key) {
+                   // Someone else's playlist opened by link, only followed ones are listed
+                   match playlist_by_owner(&uid.to_string(), kind, &player.client).await {
+                       Ok(playlist) => {
+                           let ids = playlist_track_ids(&playlist, &player.client).await?;
+                           contents.insert(key.clone(), (playlist.title, ids));
+                       },
+                       Err(ApiError::NotFound(_)) => {
+                           report.orphaned.push(key);
+                           continue;
+                       },
+                       Err(err) => return Err(err),
+                   }
+               }
+               contents[&key].1.contains(&point.track_id)
+           },
+           _ => true,
+       };
+       if !present {
+           let title = contents.get(&key).map_or(FAVORITES_TITLE.to_owned(), |(title, _)| title.clone());
+           report.changed.push((key, title));
+       }
+   }
+
+   if report.is_clean() {
+       say!("Everything is in order");
+       return Ok(());
+   }
+   if !report.stale.is_empty() {
+       say!("Stale: {} prefetched tracks, ~{} MiB, no longer liked, queued or in any playlist", report.stale.len(), report.stale_bytes / 1024 / 1024);
+   }
+   if !report.changed.is_empty() {
+       let titles = report.changed.iter().map(|(_, title)| title.as_str()).collect::<Vec<_>>();
+       say!("Changed: the saved position is gone from {}", titles.join(", "));
+   }
+   if !report.orphaned.is_empty() {
+       say!("Orphaned: {} saved positions for deleted playlists", report.orphaned.len());
+   }
+   if fix.is_empty() {
+       say!("Reconcile with audit fix <stale|changed|orphaned|all>");
+       return Ok(());
+   }
+
+   // Deleting audio can't be undone, so it waits for audit confirm
+   if fix.contains(&audit::Category::Stale) && !report.stale.is_empty() {
+       say!("Type audit confirm to remove the {} stale tracks", report.stale.len());
+       player.pending_removal = report.stale;
+   }
+   let mut forgotten = Vec::new();
+   if fix.contains(&audit::Category::Changed) {
+       forgotten.extend(report.changed.iter().map(|(key, _)| key.as_str()));
+   }
+   if fix.contains(&audit::Category::Orphaned) {
+       forgotten.extend(report.orphaned.iter().map(String::as_str));
+   }
+   if !forgotten.is_empty() {
+       for key in &forgotten {
+           player.resume.remove(key);
+       }
+       if let Err(err) = player.resume.save() {
+           say!("Failed to save positions: {}", err);
+           return Ok(());
+       }
+       say!("Forgot {} saved positions", forgotten.len());
+   }
+   Ok(())
+}
+
+// One file per playlist named after it, own and followed ones alike
+pub async fn export_playlists(player: &Player, format: export::Format, dir: &std::path::Path) -> Result<(), ApiError> {
+   let playlists = playlists(player).await?;
+   let mut names = HashSet::new();
+   for playlist in &playlists {
+       let ids = playlist_track_ids(playlist, &player.client).await?;
+       let tracks = fetch_all_tracks(&ids, &player.client).await?;
+       let mut name = bundle::file_safe(&playlist.title);
+       if !names.insert(name.clone()) {
+           name = format!("{} ({})", name, playlist.kind);
+       }
+       let path = dir.join(format!("{}.{}", name, format.extension()));
+       let tracks = tracks.iter().collect::<Vec<_>>();
+       if let Err(err) = export::write(&path, &playlist.title, &tracks, format, player.cache.as_ref(), player.quality) {
+           say!("Failed to export {}: {}", playlist.title, err);
+           return Ok(());
+       }
+       say!("Exported {} to {}", playlist.title, path.display());
+   }
+   say!("Exported {} playlists to {}", playlists.len(), dir.display());
+   Ok(())
+}
+
+pub async fn playlists(player: &Player) -> Result<Vec<PlaylistInfo>, ApiError> {
+    let mut playlists = crate::api::playlists(player.account.uid, &player.client).await?;
+    let followed = liked_playlists(player.account.uid, &player.client).await?;
+    playlists.extend(followed.into_iter().filter(|playlist| playlist.uid != player.account.uid));
+    Ok(playlists)
+}
+
+// A number picks by index, anything else by title, exact matches first
+pub fn find_playlist<'a>(playlists: &'a [PlaylistInfo], selector: &str) -> Option<&'a PlaylistInfo> {
+    if let Ok(n) = selector.parse::<usize>() {
+        return playlists.get(n);
+    }
+    let selector = selector.to_lowercase();
+    playlists
+        .iter()
+        .find(|playlist| playlist.title.to_lowercase() == selector)
+        .or_else(|| playlists.iter().find(|playlist| playlist.title.to_lowercase().contains(&selector)))
+}
+
+fn playlist_owner(player: &Player, playlist: &PlaylistInfo) -> String {
+    match &playlist.owner {
+        _ if playlist.uid == player.account.uid => "mine".to_owned(),
+        Some(owner) => format!("followed, by {}", owner.login),
+        None => "followed".to_owned(),
+    }
+}
+
+// Returns the titles for completion
+pub async fn list_playlists(player: &Player) -> Result<Vec<String>, ApiError> {
+    let playlists = playlists(player).await?;
+    for (n, playlist) in playlists.iter().enumerate() {
+        let duration = playlist.duration.map_or(String::new(), |ms| format!(", {}", format_time(Duration::from_millis(ms))));
+        say!("{}. {} [{} tracks{}, {}]", n, playlist.title, playlist.track_count, duration, playlist_owner(player, playlist));
+        if let Some(line) = playlist.description.as_deref().and_then(|description| description.lines().next()) {
+            let line: String = line.chars().take(80).collect();
+            say!("   {}", line);
+        }
+    }
+    Ok(playlists.into_iter().map(|playlist| playlist.title).collect())
+}
+
+// Playlists of other users are named owner/kind, the way user-playlists lists them
+async fn resolve_playlist(player: &Player, selector: &str) -> Result<Option<PlaylistInfo>, ApiError> {
+    // A title like "Mix 24/7" reads as owner/kind too, so a miss falls back to the titles
+    if let Some((owner, kind)) = selector.split_once('/') {
+        if let Ok(kind) = kind.parse() {
+            match playlist_by_owner(owner, kind, &player.client).await {
+                Ok(playlist) => return Ok(Some(playlist)),
+                Err(ApiError::NotFound(_)) => {},
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    let playlists = playlists(player).await?;
+    Ok(find_playlist(&playlists, selector).cloned())
+}
+
+pub async fn list_user_playlists(player: &Player, owner: &str) -> Result<(), ApiError> {
+    let playlists = user_playlists(owner, &player.client).await?;
+    if playlists.is_empty() {
+        say!("{} has no public playlists", owner);
+    }
+    for playlist in &playlists {
+        say!("{}/{} {} [{} tracks]", owner, playlist.kind, playlist.title, playlist.track_count);
+    }
+    Ok(())
+}
+
+// Shows everything about the playlist and remembers it, so load-playlist alone opens it
+pub async fn show_playlist(player: &mut Player, selector: &str) -> Result<(), ApiError> {
+    let Some(playlist) = resolve_playlist(player, selector).await? else {
+        say!("No playlist matches {}", selector);
+        return Ok(());
+    };
+
+    if let Some(uri) = playlist.cover_uri() {
+        let mode = match player.config.cover_art {
+            ArtMode::Off => ArtMode::Auto,
+            mode => mode,
+        };
+        let bytes = download_cover(uri, 400, &player.client).await?;
+        match art::render(&bytes, mode) {
+            Ok(art) => events::emit(Event::Art(art)),
+            Err(err) => say!("Failed to render cover: {}", err),
+        }
+    }
+    say!("{}", playlist.title);
+    say!("Owner: {}", playlist_owner(player, &playlist));
+    say!("Tracks: {}", playlist.track_count);
+    if let Some(ms) = playlist.duration {
+        say!("Duration: {}", format_time(Duration::from_millis(ms)));
+    }
+    if let Some(description) = playlist.description.as_deref().filter(|description| !description.is_empty()) {
+        say!("{}", description);
+    }
+    say!("Open it with load-playlist");
+    player.selected_playlist = Some(playlist);
+
+    Ok(())
+}
+
+// The selected playlist when there is no selector
+pub async fn select_playlist(player: &Player, selector: Option<&str>) -> Result<Option<PlaylistInfo>, ApiError> {
+    let Some(selector) = selector else {
+        if player.selected_playlist.is_none() {
+            say!("Pick a playlist with playlist <n> or give its index or title");
+        }
+        return Ok(player.selected_playlist.clone());
+    };
+    let playlist = resolve_playlist(player, selector).await?;
+    if playlist.is_none() {
+        say!("No playlist matches {}", selector);
+    }
+    Ok(playlist)
+}
+
+fn check_own(player: &Player, playlist: &PlaylistInfo) -> bool {
+    let own = playlist.uid == player.account.uid;
+    if !own {
+        say!("{} isn't yours, only your own playlists can be changed", playlist.title);
+    }
+    own
+}
+
+// Playback starts with the first page, the rest keeps loading in the background
+pub async fn load_playlist_into_player(player:&mut Player, playlist: &PlaylistInfo) -> Result<(), ApiError> {
+   let ids = playlist_track_ids(playlist, &player.client).await?;
+   let mut pages = ids.chunks(TRACKS_PAGE).map(<[TrackId]>::to_vec);
+   let first = match pages.next() {
+       Some(page) => fetch_tracks(&page, &player.client).await?,
+       None => Vec::new(),
+   };
+   player.replace_tracks(first, Source::Playlist { uid: playlist.uid, kind: playlist.kind }, playlist.title.clone());
+
+   let rest = pages.collect::<Vec<_>>();
+   if rest.is_empty() {
+       return Ok(());
+   }
+   let (sender, receiver) = mpsc::unbounded_channel();
+   let (client, title, total) = (player.client.clone(), playlist.title.clone(), ids.len());
+   tokio::spawn(async move {
+       let mut loaded = TRACKS_PAGE;
+       for page in rest {
+           match fetch_tracks(&page, &client).await {
+               Ok(tracks) => {
+                   if sender.send(tracks).is_err() {
+                       return;
+                   }
+                   loaded += page.len();
+                   tracing::info!("Loaded {}/{} tracks of {}", loaded, total, title);
+               },
+               Err(err) => {
+                   tracing::warn!("Failed to load the rest of {}: {}", title, err);
+                   return;
+               },
+           }
+       }
+   });
+   player.pending_pages = Some(receiver);
+
+   Ok(())
+}
+
+pub async fn prefetch_playlist(player: &mut Player, playlist: &PlaylistInfo) -> Result<(), ApiError> {
+   let tracks = tracks_from_playlist(playlist, &player.client, |loaded, total| {
+       say!("Loaded {}/{} tracks", loaded, total);
+   }).await?;
+   player.download_tracks(&tracks);
+
+   Ok(())
+}
+
+pub async fn add_current_to_playlist(player: &Player, n: Option<usize>) -> Result<(), ApiError> {
+   let Some(now_playing) = player.remote_now_playing() else {
+       return Ok(());
+   };
+
+   let playlists = playlists(player).await?;
+   let Some(n) = n else {
+       for (n, playlist) in playlists.iter().enumerate() {
+           say!("{}. {}", n, playlist.title);
+       }
+       say!("Pick one with add-to-playlist <n>");
+       return Ok(());
+   };
+   let Some(playlist) = playlists.get(n) else {
+       say!("No playlist with index {}", n);
+       return Ok(());
+   };
+   if !check_own(player, playlist) {
+       return Ok(());
+   }
+   let Some(album_id) = now_playing.album_id else {
+       say!("{} has no album and can't be added to playlists", now_playing.title);
+       return Ok(());
+   };
+
+   playlist_insert_track(playlist, playlist.track_count, now_playing.id, album_id, &player.client).await?;
+   say!("Added {} to {}", now_playing.title, playlist.title);
+
+   Ok(())
+}
+
+pub async fn create_playlist(player: &Player, title: &str) -> Result<(), ApiError> {
+   let playlist = crate::api::create_playlist(player.account.uid, title, &player.client).await?;
+   say!("Created {}", playlist.title);
+
+   Ok(())
+}
+
+pub async fn rename_playlist(player: &Player, n: usize, title: &str) -> Result<(), ApiError> {
+   let playlists = playlists(player).await?;
+   let Some(playlist) = playlists.get(n) else {
+       say!("No playlist with index {}", n);
+       return Ok(());
+   };
+   if !check_own(player, playlist) {
+       return Ok(());
+   }
+
+   crate::api::rename_playlist(playlist, title, &player.client).await?;
+   say!("Renamed {} to {}", playlist.title, title);
+
+   Ok(())
+}
+
+pub async fn delete_playlist(player: &Player, n: usize) -> Result<(), ApiError> {
+   let playlists = playlists(player).await?;
+   let Some(playlist) = playlists.get(n) else {
+       say!("No playlist with index {}", n);
+       return Ok(());
+   };
+   if !check_own(player, playlist) {
+       return Ok(());
+   }
+
+   crate::api::delete_playlist(playlist, &player.client).await?;
+   say!("Deleted {}", playlist.title);
+
+   Ok(())
+}
+
+// Removes the track at queue index n, or the current one, from the playlist being played
+pub async fn remove_from_playlist(player: &mut Player, n: Option<usize>) -> Result<(), ApiError> {
+   let Source::Playlist { uid, kind } = player.source else {
+       say!("Not playing a playlist");
+       return Ok(());
+   };
+   if uid != player.account.uid {
+       say!("Only tracks of your own playlists can be removed");
+       return Ok(());
+   }
+   let id = match n {
+       Some(n) => match player.queue.get(n) {
+           Some(&i) => player.tracks[i].id,
+           None => {
+               say!("No track with index {}", n);
+               return Ok(());
+           },
+       },
+       None => match &player.now_playing {
+           Some(now_playing) if now_playing.source == player.source => now_playing.id,
+           _ => {
+               say!("Nothing from this playlist is playing");
+               return Ok(());
+           },
+       },
+   };
+
+   // Positions are taken from a fresh copy, the loaded one may be filtered or outdated
+   let playlist = playlist_with_tracks(uid, kind, &player.client).await?;
+   let Some((at, track)) = playlist.tracks().enumerate().find(|(_, track)| track.id == id) else {
+       say!("The track is no longer in {}", playlist.info.title);
+       return Ok(());
+   };
+   let Some(album_id) = track.albums.first().map(|album| album.id) else {
+       say!("{} has no album and can't be removed", track.title);
+       return Ok(());
+   };
+
+   playlist_delete_track(&playlist.info, at, id, album_id, &player.client).await?;
+   say!("Removed {} from {}", track.title, playlist.info.title);
+   player.remove_from_queue(id);
+
+   Ok(())
+}
+
+pub async fn load_favorites_into_player(player:&mut Player) -> Result<(), ApiError> {
+   let tracks = library::liked_music_tracks(
+       player.account.uid,
+       &player.client,
+       player.config.fetch_concurrency,
+       print_progress,
+   ).await?;
+   player.replace_tracks(tracks, Source::Favorites, FAVORITES_TITLE.to_owned());
+
+   Ok(())
+}
+
+pub async fn load_album_into_player(player:&mut Player, album_id: AlbumId) -> Result<(), ApiError> {
+   let album = album_with_tracks(album_id, &player.client).await?;
+   match album.info.released() {
+       Some(released) => say!("Loading {}, released {}", album.info.title, released),
+       None => say!("Loading {}", album.info.title),
+   }
+   let tracks = album.volumes.into_iter().flatten().collect();
+   player.replace_tracks(tracks, Source::Album(album_id), album.info.title);
+
+   Ok(())
+}
+
+pub async fn list_podcasts(player: &mut Player) -> Result<(), ApiError> {
+   player.podcasts = liked_podcasts(player.account.uid, &player.client).await?;
+   if player.podcasts.is_empty() {
+       say!("No subscribed podcasts");
+   }
+   for (n, podcast) in player.podcasts.iter().enumerate() {
+       say!("{}. {} ({} episodes)", n, podcast.title, podcast.track_count);
+   }
+
+   Ok(())
+}
+
+pub async fn list_liked_albums(player: &mut Player, by_release: bool) -> Result<(), ApiError> {
+   player.albums = liked_music_albums(player.account.uid, &player.client).await?;
+   if by_release {
+       // Newest first, undated ones last
+       player.albums.sort_by_key(|album| std::cmp::Reverse(album.released()));
+   }
+   if player.albums.is_empty() {
+       say!("No liked albums");
+   }
+   for (n, album) in player.albums.iter().enumerate() {
+       say!("{}. {}", n, album);
+   }
+
+   Ok(())
+}
+
+pub async fn load_liked_album_into_player(player:&mut Player, n: usize) -> Result<(), ApiError> {
+   let Some(album_id) = player.albums.get(n).map(|album| album.id) else {
+       say!("No album with index {}, list them with albums first", n);
+       return Ok(());
+   };
+
+   load_album_into_player(player, album_id).await
+}
+
+pub async fn load_podcast_into_player(player:&mut Player, n: usize) -> Result<(), ApiError> {
+   let Some(album_id) = player.podcasts.get(n).map(|podcast| podcast.id) else {
+       say!("No podcast with index {}, list them with podcasts first", n);
+       return Ok(());
+   };
+
+   load_album_into_player(player, album_id).await
+}
+
+fn landing_title(entity: &LandingEntity) -> Option<String> {
+   match entity {
+       LandingEntity::PersonalPlaylist(personal) => Some(personal.data.title.clone()),
+       LandingEntity::Playlist(playlist) => Some(playlist.title.clone()),
+       LandingEntity::Album(album) => Some(album.title.clone()),
+       LandingEntity::ChartItem(item) => Some(format!("#{} {}", item.chart.position, item.track)),
+       LandingEntity::Other => None,
+   }
+}
+
+pub async fn browse_landing(player: &mut Player) -> Result<(), ApiError> {
+   let blocks = landing(&["personal-playlists", "new-releases", "new-playlists", "chart"], &player.client).await?;
+
+   player.landing.clear();
+   for block in blocks {
+       say!("{}:", block.title);
+       for entity in block.entities {
+           let Some(title) = landing_title(&entity) else {
+               continue;
+           };
+           say!("  {}. {}", player.landing.len(), title);
+           player.landing.push(entity);
+       }
+   }
+
+   Ok(())
+}
+
+pub async fn load_landing_into_player(player:&mut Player, n: usize) -> Result<(), ApiError> {
+   let playlist = match player.landing.get(n) {
+       Some(LandingEntity::PersonalPlaylist(personal)) => personal.data.clone(),
+       Some(LandingEntity::Playlist(playlist)) => playlist.clone(),
+       Some(LandingEntity::Album(album)) => {
+           let album_id = album.id;
+           return load_album_into_player(player, album_id).await;
+       },
+       // A chart entry is a single track, it goes next in the current queue
+       Some(LandingEntity::ChartItem(item)) => {
+           let id = item.track.id;
+           if let Some(index) = player.tracks.iter().position(|track| track.id == id) {
+               player.enqueue(index);
+               return Ok(());
+           }
+           let tracks = fetch_tracks(&[id], &player.client).await?;
+           let start = player.tracks.len();
+           player.tracks.extend(tracks);
+           player.origins.resize(player.tracks.len(), Origin::Manual);
+           if player.tracks.len() > start {
+               player.enqueue(start);
+           }
+           return Ok(());
+       },
+       _ => {
+           say!("No entry with index {}, list them with browse first", n);
+           return Ok(());
+       },
+   };
+
+   say!("Loading {}", playlist.title);
+   load_playlist_into_player(player, &playlist).await
+}
+
+pub async fn start_track_radio(player: &mut Player) -> Result<(), ApiError> {
+   let Some(now_playing) = &player.now_playing else {
+       say!("Nothing is playing");
+       return Ok(());
+   };
+   say!("Starting radio based on {} - {}", now_playing.artist, now_playing.title);
+
+   let station = format!("track:{}", now_playing.id);
+   let title = format!("{} - {}", now_playing.artist, now_playing.title);
+   start_radio(player, station, title).await
+}
+
+async fn start_radio(player: &mut Player, station: String, title: String) -> Result<(), ApiError> {
+   let batch = station_tracks(&station, None, &player.client).await?;
+   player.radio_batch = Some(batch.batch_id.clone());
+   let mut tracks = batch.into_tracks();
+   player.skip_jingles(&mut tracks);
+   player.replace_tracks(tracks, Source::Radio(station.clone()), title);
+   player.radio_feedback(&station, "radioStarted", None);
+
+   Ok(())
+}
+
+// Radio has no end: a new batch gets appended once the queue runs dry
+async fn top_up_radio(player: &mut Player) -> Result<(), ApiError> {
+   let Source::Radio(station) = &player.source else {
+       return Ok(());
+   };
+   if player.queue_position + 1 < player.queue.len()
+       || player.radio_retry_at.is_some_and(|retry_at| retry_at > Instant::now())
+   {
+       return Ok(());
+   }
+
+   let last = player.tracks.last().map(|track| track.id);
+   let batch = match station_tracks(station, last, &player.client).await {
+       Ok(batch) => batch,
+       Err(err) => {
+           player.radio_retry_at = Some(Instant::now() + Duration::from_secs(10));
+           return Err(err);
+       },
+   };
+   player.radio_retry_at = None;
+   player.radio_batch = Some(batch.batch_id.clone());
+
+   let mut tracks = batch.into_tracks();
+   tracks.retain(|track| !player.blacklist.contains(track.id));
+   player.skip_jingles(&mut tracks);
+   // A batch of nothing but jingles would be asked for again right away
+   if tracks.is_empty() {
+       player.radio_retry_at = Some(Instant::now() + Duration::from_secs(10));
+   }
+   if player.no_repeats && tracks.iter().any(|track| !player.played.contains(&track.id)) {
+       tracks.retain(|track| !player.played.contains(&track.id));
+   }
+
+   player.drop_played((player.tracks.len() + tracks.len()).saturating_sub(player.config.max_queue_length));
+   player.append_tracks(tracks);
+
+   Ok(())
+}
+
+pub async fn dislike_current_track(player: &mut Player) -> Result<(), ApiError> {
+   let Some(now_playing) = player.remote_now_playing() else {
+       return Ok(());
+   };
+   let id = now_playing.id;
+   say!("Disliked {} - {}", now_playing.artist, now_playing.title);
+
+   player.blacklist.insert(id);
+   if let Err(err) = player.blacklist.save() {
+       tracing::warn!("Failed to save blacklist: {}", err);
+   }
+   player.remove_from_queue(id);
+   player.move_next();
+
+   dislike_track(player.account.uid, id, &player.client).await
+}
+
+pub async fn open_current_artist(player: &mut Player) -> Result<(), ApiError> {
+   let Some(now_playing) = player.remote_now_playing() else {
+       return Ok(());
+   };
+   let Some(&artist_id) = now_playing.artist_ids.first() else {
+       say!("{} has no artist", now_playing.title);
+       return Ok(());
+   };
+
+   let mut brief = artist_info(artist_id, &player.client).await?;
+   // brief-info only lists a handful of albums
+   brief.albums = artist_albums(artist_id, &player.client).await?;
+
+   if brief.artist.genres.is_empty() {
+       say!("{}", brief.artist);
+   } else {
+       say!("{} ({})", brief.artist, brief.artist.genres.join(", "));
+   }
+   say!("Popular tracks:");
+   for (n, track) in brief.popular_tracks.iter().enumerate() {
+       say!("  {}. {}", n, track);
+   }
+   say!("Albums:");
+   for (n, album) in brief.albums.iter().enumerate() {
+       say!("  {}. {}", n, album);
+   }
+   player.artist = Some(brief);
+
+   Ok(())
+}
+
+pub async fn load_artist_tracks_into_player(player:&mut Player) -> Result<(), ApiError> {
+   let Some((artist_id, name)) = player.artist.as_ref().map(|brief| (brief.artist.id, brief.artist.name.clone())) else {
+       say!("Open an artist first");
+       return Ok(());
+   };
+
+   let tracks = artist_tracks(artist_id, &player.client).await?;
+   player.replace_tracks(tracks, Source::Artist(artist_id), name);
+
+   Ok(())
+}
+
+pub async fn open_link(player: &mut Player, link: Link) -> Result<(), ApiError> {
+   match link {
+       Link::Playlist { owner, kind } => {
+           let playlist = playlist_by_owner(&owner, kind, &player.client).await?;
+           match &playlist.owner {
+               Some(owner) => say!("Loading {} by {}", playlist.title, owner.login),
+               None => say!("Loading {}", playlist.title),
+           }
+           load_playlist_into_player(player, &playlist).await
+       },
+       Link::Album(album_id) => load_album_into_player(player, album_id).await,
+       Link::Track { album, track } => {
+           let album_id = match album {
+               Some(album_id) => album_id,
+               None => match fetch_track(track, &player.client, None).await?.albums.first() {
+                   Some(album) => album.id,
+                   None => {
+                       say!("The track belongs to no album");
+                       return Ok(());
+                   },
+               },
+           };
+           load_album_into_player(player, album_id).await?;
+           // The album view starts from its first track, the link points at a single one
+           if let Some(n) = player.tracks.iter().position(|loaded| loaded.id == track) {
+               player.play_index(n);
+           }
+           Ok(())
+       },
+       Link::Artist(artist_id) => {
+           let brief = artist_info(artist_id, &player.client).await?;
+           let tracks = artist_tracks(artist_id, &player.client).await?;
+           say!("Loading tracks by {}", brief.artist.name);
+           player.replace_tracks(tracks, Source::Artist(artist_id), brief.artist.name);
+           Ok(())
+       },
+   }
+}
+
+// Loading the source brings back its own resume point, which the last session saved
+pub async fn resume_last_session(player: &mut Player) -> Result<(), ApiError> {
+   let Some(last) = player.resume.last().cloned() else {
+       say!("No previous session");
+       return Ok(());
+   };
+   let Some(source) = Source::from_key(&last.source) else {
+       return Ok(());
+   };
+   if source == player.source {
+       say!("Already playing {}", last.title);
+       return Ok(());
+   }
+
+   say!("Resuming {}", last.title);
+   match source {
+       Source::Favorites => load_favorites_into_player(player).await,
+       Source::Album(album_id) => load_album_into_player(player, album_id).await,
+       Source::Artist(artist_id) => {
+           let tracks = artist_tracks(artist_id, &player.client).await?;
+           player.replace_tracks(tracks, source, last.title);
+           Ok(())
+       },
+       Source::Radio(station) => start_radio(player, station, last.title).await,
+       Source::Queue(id) if id == YNISON_QUEUE => take_playback(player).await,
+       Source::Queue(id) => pick_up_queue(player, Some(id)).await,
+       Source::Playlist { uid, kind } => {
+           // Followed and other users' playlists resume too, not only the ones listed as yours
+           let playlists = playlists_by_kinds(uid, &[kind], &player.client).await?;
+           match playlists.first() {
+               Some(playlist) => load_playlist_into_player(player, playlist).await,
+               None => {
+                   say!("{} no longer exists", last.title);
+                   Ok(())
+               },
+           }
+       },
+   }
+}
+
+fn queue_source(context: &QueueContext, queue_id: &str) -> Source {
+    let key = match (context.kind.as_str(), context.id.as_deref()) {
+        ("playlist", Some(id)) => id.to_owned(),
+        ("album" | "artist" | "radio", Some(id)) => format!("{}:{}", context.kind, id),
+        ("my_music", _) => "favorites".to_owned(),
+        _ => String::new(),
+    };
+    Source::from_key(&key).unwrap_or_else(|| Source::Queue(queue_id.to_owned()))
+}
+
+fn queue_context(player: &Player) -> QueueContext {
+    let (kind, id) = match &player.source {
+        Source::Favorites => ("my_music", None),
+        Source::Playlist { uid, kind } => ("playlist", Some(format!("{}:{}", uid, kind))),
+        Source::Artist(id) => ("artist", Some(id.to_string())),
+        Source::Album(id) => ("album", Some(id.to_string())),
+        Source::Radio(station) => ("radio", Some(station.clone())),
+        Source::Queue(_) => ("various", None),
+    };
+    QueueContext { kind: kind.to_owned(), id, description: Some(player.source_title.clone()) }
+}
+
+async fn fetch_all_tracks(ids: &[TrackId], client: &Client) -> Result<Vec<Track>, ApiError> {
+    let mut tracks = Vec::with_capacity(ids.len());
+    for page in ids.chunks(TRACKS_PAGE) {
+        tracks.extend(fetch_tracks(page, client).await?);
+    }
+    Ok(tracks)
+}
+
+// Starts the queue of another device where it is at
+async fn load_ynison_queue(player: &mut Player, state: &ynison::State) -> Result<(), ApiError> {
+    let ids = state.queue.iter().map(|playable| playable.track_id).collect::<Vec<_>>();
+    let tracks = fetch_all_tracks(&ids, &player.client).await?;
+    player.switch_once(SwitchMode::Now);
+    player.replace_tracks(tracks, Source::Queue(YNISON_QUEUE.to_owned()), "Ynison queue".to_owned());
+    let Some(current) = state.current() else {
+        return Ok(());
+    };
+    if let Some(n) = player.tracks.iter().position(|track| track.id == current.track_id) {
+        player.pending_offset = Some(ResumePoint { track_id: current.track_id, position: state.progress });
+        player.play_index(n);
+    }
+    Ok(())
+}
+
+// Whatever another device plays continues here
+pub async fn take_playback(player: &mut Player) -> Result<(), ApiError> {
+    let (Some(link), Some(state)) = (&player.ynison, player.ynison_state.clone()) else {
+        say!("Not connected to Ynison");
+        return Ok(());
+    };
+    if state.active_device.as_deref() == Some(link.device_id.as_str()) {
+        say!("Already playing here");
+        return Ok(());
+    }
+    if state.current().is_none() {
+        say!("Nothing plays on other devices");
+        return Ok(());
+    }
+
+    if let Some(active) = &state.active_device {
+        say!("Taking over from {}", state.device_title(active));
+    }
+    load_ynison_queue(player, &state).await?;
+    player.set_paused(false);
+
+    Ok(())
+}
+
+// Other devices control this one while it's active, and take playback away from it
+pub async fn follow_ynison(player: &mut Player, state: ynison::State) -> Result<(), ApiError> {
+    let Some(own) = player.ynison.as_ref().map(|link| link.device_id.clone()) else {
+        return Ok(());
+    };
+    player.ynison_state = Some(state.clone());
+    if state.changed_by.as_deref() == Some(own.as_str()) {
+        return Ok(());
+    }
+
+    match state.active_device.as_deref() {
+        Some(active) if active == own => {
+            let playing = player.now_playing.as_ref().map(|now_playing| now_playing.id);
+            if let Some(current) = state.current().filter(|current| Some(current.track_id) != playing) {
+                match player.tracks.iter().position(|track| track.id == current.track_id) {
+                    Some(n) => player.play_index(n),
+                    None => load_ynison_queue(player, &state).await?,
+                }
+            }
+            player.set_paused(state.paused);
+        },
+        Some(active) => {
+            if player.now_playing.is_some() && !player.is_paused() {
+                player.set_paused(true);
+                say!("Playback moved to {}", state.device_title(active));
+            }
+            player.ynison_sent = None;
+        },
+        None => {},
+    }
+
+    Ok(())
+}
+
+// The most recent queue of any device without an id, the one the phone left off at
+pub async fn pick_up_queue(player: &mut Player, id: Option<String>) -> Result<(), ApiError> {
+    let id = match id {
+        Some(id) => id,
+        None => match queues(&player.client).await?.into_iter().next() {
+            Some(brief) => brief.id,
+            None => {
+                say!("No queues on other devices");
+                return Ok(());
+            },
+        },
+    };
+    let remote = queue(&id, &player.client).await?;
+    let ids = remote.tracks.iter().map(|track| track.track_id).collect::<Vec<_>>();
+    let tracks = fetch_all_tracks(&ids, &player.client).await?;
+    let title = remote.context.description.clone().unwrap_or_else(|| "Queue from another device".to_owned());
+    say!("Picking up {} at track {} of {}", title, remote.current_index.unwrap_or(0) + 1, tracks.len());
+
+    // Continuing where the other device is means dropping what plays here
+    player.switch_once(SwitchMode::Now);
+    player.replace_tracks(tracks, queue_source(&remote.context, &id), title);
+    if let Some(current) = remote.current_index.and_then(|n| ids.get(n)) {
+        if let Some(n) = player.tracks.iter().position(|track| track.id == *current) {
+            player.play_index(n);
+        }
+    }
+    player.remote_queue = Some(SharedQueue { id, tracks: ids });
+
+    Ok(())
+}
+
+// Shares the queue so other devices can pick it up, the position follows playback from then on
+pub async fn push_queue(player: &mut Player) -> Result<(), ApiError> {
+    if player.queue.is_empty() {
+        say!("The queue is empty");
+        return Ok(());
+    }
+    // Local files stay behind, the position counts only what is shared
+    let current = player.current_index().unwrap_or(0);
+    let shared = |&&n: &&usize| !local::is_local(player.tracks[n].id);
+    let remote = RemoteQueue {
+        id: None,
+        context: queue_context(player),
+        tracks: player.queue.iter().filter(shared).map(|&n| {
+            let track = &player.tracks[n];
+            QueueTrack {
+                track_id: track.id,
+                album_id: track.albums.first().map(|album| album.id),
+                from: "yandex_music_tui".to_owned(),
+            }
+        }).collect(),
+        current_index: Some(player.queue[..current.min(player.queue.len())].iter().filter(shared).count()),
+        from: "yandex_music_tui".to_owned(),
+        is_interactive: true,
+    };
+    if remote.tracks.is_empty() {
+        say!("Only local files are queued, nothing to share");
+        return Ok(());
+    }
+    let id = create_queue(&remote, &player.client).await?;
+    say!("Shared {} tracks with other devices", remote.tracks.len());
+    let tracks = remote.tracks.iter().map(|track| track.track_id).collect();
+    player.remote_queue = Some(SharedQueue { id, tracks });
+
+    Ok(())
+}
+
+pub async fn load_artist_album_into_player(player:&mut Player, n: usize) -> Result<(), ApiError> {
+   let Some(album_id) = player.artist.as_ref().and_then(|brief| brief.albums.get(n)).map(|album| album.id) else {
+       say!("No album with index {}", n);
+       return Ok(());
+   };
+
+   load_album_into_player(player, album_id).await
+}
+
+// Resumes at the position reached while the new stream was loading
+pub async fn refetch_current_track(player: &mut Player) -> Result<(), ApiError> {
+    let Some(now_playing) = player.remote_now_playing() else {
+        return Ok(());
+    };
+    let (id, duration) = (now_playing.id, now_playing.duration);
+
+    let data = fetch_track_data(id, player.quality, duration, player.cache.clone(), &player.client).await?;
+    say!("Reloaded at {} {} kbps", data.codec, data.bitrate);
+    let Some(position) = player.position() else {
+        return Ok(());
+    };
+    if let Some(now_playing) = player.now_playing.as_mut().filter(|now_playing| now_playing.id == id) {
+        now_playing.stream = Some((data.codec, data.bitrate));
+        player.current_audio = Some(data.data);
+        player.seek(position);
+    }
+
+    Ok(())
+}
+
+async fn fetch_track_data(
+    id: TrackId,
+    quality: Quality,
+    duration: Option<Duration>,
+    cache: Option<AudioCache>,
+    client: &Client,
+) -> Result<TrackData, ApiError> {
+    if let Some(data) = cache.as_ref().and_then(|cache| cache.load(id, quality)) {
+        return Ok(data);
+    }
+
+    let data = download_data(id, quality, client).await?;
+    if let Some(cache) = &cache {
+        if let Err(err) = cache.store(&data, quality, duration, DownloadKind::Prefetch).await {
+            tracing::warn!("Failed to cache track {}: {}", id, err);
+        }
+    }
+
+    Ok(data)
+}
+
+pub async fn show_track_info(player: &Player) -> Result<(), ApiError> {
+    let Some(now_playing) = &player.now_playing else {
+        say!("Nothing is playing");
+        return Ok(());
+    };
+    // The track may belong to a source that has since been replaced
+    let Some(n) = player.tracks.iter().position(|track| track.id == now_playing.id) else {
+        say!("{} - {}", now_playing.artist, now_playing.title);
+        return Ok(());
+    };
+    let track = &player.tracks[n];
+
+    say!("{}", track.full_title());
+    for album in &track.albums {
+        say!("Album: {}", album);
+    }
+    say!("Artists: {}", track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", "));
+    say!("Duration: {}", now_playing.duration.map_or("?".to_owned(), format_time));
+    if let Some((codec, bitrate)) = now_playing.stream {
+        say!("Stream: {} {} kbps", codec, bitrate);
+    }
+    say!("Queued from: {}", player.origins[n]);
+    if let Some(path) = player.local_files.get(&track.id) {
+        say!("File: {}", path.display());
+        return Ok(());
+    }
+
+    let liked = match player.source {
+        Source::Favorites => true,
+        _ => liked_tracks_infos(player.account.uid, &player.client)
+            .await?
+            .iter()
+            .any(|info| info.id == track.id),
+    };
+    say!("Liked: {}", if liked { "yes" } else { "no" });
+
+    Ok(())
+}
+
+pub async fn show_cover(player: &Player, mode: ArtMode) -> Result<(), ApiError> {
+    let Some(now_playing) = &player.now_playing else {
+        say!("Nothing is playing");
+        return Ok(());
+    };
+    let Some(uri) = &now_playing.cover_uri else {
+        say!("No cover for this track");
+        return Ok(());
+    };
+
+    let bytes = download_cover(uri, 400, &player.client).await?;
+    match art::render(&bytes, mode) {
+        Ok(art) => events::emit(Event::Art(art)),
+        Err(err) => say!("Failed to render cover: {}", err),
+    }
+
+    Ok(())
+}
+
+pub async fn share_card(player: &Player, copy: bool) -> Result<(), ApiError> {
+    let Some(now_playing) = &player.now_playing else {
+        say!("Nothing is playing");
+        return Ok(());
+    };
+    let cover = match &now_playing.cover_uri {
+        Some(uri) => Some(download_cover(uri, 400, &player.client).await?),
+        None => None,
+    };
+
+    let font = card::load_font(player.config.card_font.as_deref());
+    if font.is_none() {
+        say!("No font found, set card_font in the config for text on the card");
+    }
+    let info = CardInfo {
+        title: &now_playing.title,
+        artist: &now_playing.artist,
+        position: now_playing.position(),
+        duration: now_playing.duration,
+    };
+    let path = card::card_path();
+    let saved = card::render(cover.as_deref(), &info, font.as_ref()).and_then(|card| card.save(&path));
+    if let Err(err) = saved {
+        say!("Failed to render the card: {}", err);
+        return Ok(());
+    }
+
+    say!("Card saved to {}", path.display());
+    if copy && !card::copy_to_clipboard(&path) {
+        say!("Failed to copy the card, install wl-copy or xclip");
+    }
+
+    Ok(())
+}
+
+async fn fetch_current_lyrics(player: &mut Player) -> Result<(), ApiError> {
+    let Some(id) = player.now_playing.as_ref().map(|now_playing| now_playing.id).filter(|&id| !local::is_local(id)) else {
+        return Ok(());
+    };
+    if matches!(player.lyrics, Some((cached, _)) if cached == id) {
+        return Ok(());
+    }
+
+    let lyrics = track_lyrics(id, &player.client).await?.map(Lyrics::from);
+    if !player.lyrics_store.contains(id) {
+        player.lyrics_store.insert(id, lyrics.as_ref());
+        if let Err(err) = player.lyrics_store.save() {
+            tracing::warn!("Failed to save lyrics: {}", err);
+        }
+    }
+    player.lyrics = Some((id, lyrics));
+    player.lyrics_line = None;
+
+    Ok(())
+}
+
+// Lyrics of liked tracks not seen before are fetched once and kept for later searches
+pub async fn search_lyrics(player: &mut Player, phrase: &str) -> Result<(), ApiError> {
+    let liked = library::liked_tracks(player.account.uid, &player.client, player.config.fetch_concurrency, |_, _| {}).await?;
+    let missing: Vec<TrackId> = liked
+        .iter()
+        .map(|track| track.id)
+        .filter(|&id| !player.lyrics_store.contains(id))
+        .collect();
+    if !missing.is_empty() {
+        say!("Fetching lyrics of {} tracks", missing.len());
+    }
+    let client = &player.client;
+    let mut fetches = futures::stream::iter(missing.into_iter().map(|id| async move {
+        (id, track_lyrics(id, client).await)
+    }))
+    .buffer_unordered(player.config.fetch_concurrency.max(1));
+    while let Some((id, lyrics)) = fetches.next().await {
+        match lyrics {
+            Ok(lyrics) => player.lyrics_store.insert(id, lyrics.map(Lyrics::from).as_ref()),
+            Err(err) => tracing::warn!("Failed to fetch lyrics of {}: {}", id, err),
+        }
+    }
+    if let Err(err) = player.lyrics_store.save() {
+        tracing::warn!("Failed to save lyrics: {}", err);
+    }
+
+    let mut found = false;
+    for track in &liked {
+        if let Some(line) = player.lyrics_store.find(track.id, phrase) {
+            say!("{}\n    {}", track, line);
+            found = true;
+        }
+    }
+    if !found {
+        say!("No liked track has {} in its lyrics", phrase);
+    }
+
+    Ok(())
+}
+
+pub async fn show_lyrics(player: &mut Player) -> Result<(), ApiError> {
+    if player.remote_now_playing().is_none() {
+        return Ok(());
+    }
+    fetch_current_lyrics(player).await?;
+
+    match &player.lyrics {
+        Some((_, Some(lyrics))) => {
+            let current = player.position().and_then(|position| lyrics.line_at(position));
+            lyrics.print(current);
+        },
+        _ => say!("No lyrics for this track"),
+    }
+
+    Ok(())
+}
+
+pub async fn update_player(player: &mut Player) {
+    player.receive_pages();
+    player.publish_ynison();
+    player.check_limits();
+    player.apply_volume();
+    player.check_output();
+    // Before anything that returns early, so what the last commands changed shows up right away
+    player.publish_status();
+
+    if let Err(err) = top_up_radio(player).await {
+        tracing::warn!("Failed to load more radio tracks: {}", err);
+    }
+    if matches!(player.source, Source::Radio(_)) && player.queue_position >= player.queue.len() {
+        return;
+    }
+
+    if player.next_track_task_handle.is_none() {
+        player.skip_played_tracks();
+    }
+
+    if player.music_sink.empty() {
+        if let Some(finished) = player.now_playing.take() {
+            player.report_play(finished);
+        }
+        player.current_audio = None;
+        if player.unauthorized {
+            return;
+        }
+        // The last track finished or the loaded list was empty, playback stops until something is queued
+        let Some(&index) = player.queue.get(player.queue_position) else {
+            player.next_track_task_handle = None;
+            return;
+        };
+
+        let prefetched = match player.next_track_task_handle.as_mut().map(|handle| handle.try_recv()) {
+            // The prefetch is still running (or paused in the download manager)
+            Some(Err(TryRecvError::Empty)) => return,
+            Some(Ok(Ok(data))) => Some(data),
+            Some(Ok(Err(err))) => {
+                tracing::warn!("Prefetch failed: {}", err);
+                None
+            },
+            Some(Err(TryRecvError::Closed)) | None => None,
+        };
+        player.next_track_task_handle = None;
+
+        let (id, audio, stream) = if let Some(data) = prefetched {
+            (data.id, data.data, Some((data.codec, data.bitrate)))
+        } else {
+            let track = &player.tracks[index];
+            match player.item(track) {
+                QueueItem::Local(path) => {
+                    let audio = std::fs::read(&path).map(|bytes| std::io::Cursor::new(bytes::Bytes::from(bytes)));
+                    match audio {
+                        Ok(audio) if Decoder::new(audio.clone()).is_ok() => (track.id, audio, None),
+                        result => {
+                            let reason = result.err().map_or("unsupported format".to_owned(), |err| err.to_string());
+                            say!("Failed to play {}: {}", path.display(), reason);
+                            player.queue_position += 1;
+                            return;
+                        },
+                    }
+                },
+                QueueItem::Remote(id) => {
+                    let duration = track.duration.map(Duration::from_millis);
+                    tracing::debug!("Loading track {} directly", id);
+                    match fetch_track_data(id, player.quality, duration, player.cache.clone(), &player.client).await {
+                        Ok(data) => (data.id, data.data, Some((data.codec, data.bitrate))),
+                        // Every other track would fail the same way, so the queue waits for a new token
+                        Err(ApiError::Unauthorized(_)) => {
+                            say!("The token is no longer valid, type token to re-authorize");
+                            player.unauthorized = true;
+                            return;
+                        },
+                        Err(err) => {
+                            say!("Failed to load {}: {}", track, err);
+                            player.queue_position += 1;
+                            return;
+                        },
+                    }
+                },
+            }
+        };
+        say!("Playing: {}", player.tracks[index]);
+        player.announce_track();
+        
+        let offset = match player.pending_offset.take() {
+            Some(point) if point.track_id == id => point.position,
+            _ => player.resume.episode(id).unwrap_or(Duration::ZERO),
+        };
+        let offset = offset.max(player.trims.get(id).start.unwrap_or_default());
+        player.played.insert(id);
+        player.jumped = false;
+        player.current_audio = Some(audio.clone());
+        let gain = player.gain(&player.tracks[index]);
+        player.apply_auto_preset();
+        player.append_audio(audio, offset, gain);
+        let mut now_playing = NowPlaying::new(&player.tracks[index], player.source.clone(), offset, gain);
+        now_playing.stream = stream;
+        if player.history.len() == HISTORY_LENGTH {
+            player.history.pop_front();
+        }
+        player.history.push_back(HistoryEntry {
+            started: now_playing.started,
+            track: player.tracks[index].to_string(),
+            origin: player.origins[player.queue[player.queue_position]].clone(),
+        });
+        player.now_playing = Some(now_playing);
+        player.report_now_playing();
+        player.sync_remote_queue();
+        player.check_sleep();
+
+        let local = local::is_local(id);
+        if player.config.cover_art != ArtMode::Off && !local {
+            if let Err(err) = show_cover(player, player.config.cover_art).await {
+                tracing::warn!("Failed to fetch cover: {}", err);
+            }
+        }
+
+        if player.follow_lyrics && !local {
+            if let Err(err) = fetch_current_lyrics(player).await {
+                tracing::warn!("Failed to fetch lyrics: {}", err);
+            }
+        }
+
+        player.queue_position += 1; 
+    } else if player.next_track_task_handle.is_none()
+        && player.queue_position < player.queue.len()
+        && player.next_track().is_some_and(|track| !player.is_cached(track.id))
+        && player.now_playing.as_ref().is_some_and(|now_playing| now_playing.played() >= PREFETCH_DELAY)
+        && player.next_fits_in_memory()
+    {
+        let track = &player.tracks[player.queue[player.queue_position]];
+        tracing::debug!("Scheduling download of track {}", track.id);
+        player.next_track_task_handle = Some(
+            player.downloads.enqueue(
+                track.id,
+                player.quality,
+                track.to_string(),
+                track.duration.map(Duration::from_millis),
+                DownloadKind::Prefetch,
+            )
+        );
+    }
+
+    player.check_sleep();
+
+    if let Some(now_playing) = &player.now_playing {
+        if player.trims.get(now_playing.id).end.is_some_and(|end| now_playing.position() >= end) {
+            player.move_next();
+        }
+    }
+
+    if player.follow_lyrics {
+        player.print_next_lyrics_line();
+    }
+}
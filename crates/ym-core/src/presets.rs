@@ -0,0 +1,47 @@
+use crate::api::{
+    AlbumType,
+    Track,
+};
+
+use serde::Deserialize;
+
+// Sound settings used while a matching track plays, the ones from before come back after it
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AutoPreset {
+    // Artist names, compared case-insensitively
+    pub artists: Vec<String>,
+    // Yandex genre ids like "classicalmusic" or "jazz"
+    pub genres: Vec<String>,
+    pub podcasts: bool,
+    // An equalizer preset name
+    pub eq: Option<String>,
+    // In percent like vp
+    pub volume: Option<f32>,
+    pub speed: Option<f32>,
+}
+
+impl AutoPreset {
+    pub fn matches(&self, track: &Track) -> bool {
+        let album = track.albums.first();
+        if self.podcasts && album.is_some_and(|album| album.meta_type == AlbumType::Podcast) {
+            return true;
+        }
+        let artist = track
+            .artists
+            .iter()
+            .any(|artist| self.artists.iter().any(|name| name.to_lowercase() == artist.name.to_lowercase()));
+        let genre = track
+            .artists
+            .iter()
+            .flat_map(|artist| &artist.genres)
+            .chain(album.and_then(|album| album.genre.as_ref()))
+            .any(|genre| self.genres.contains(genre));
+        artist || genre
+    }
+}
+
+// The first matching preset wins, so more specific ones go earlier in the config
+pub fn find(presets: &[AutoPreset], track: &Track) -> Option<usize> {
+    presets.iter().position(|preset| preset.matches(track))
+}
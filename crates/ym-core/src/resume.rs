@@ -0,0 +1,116 @@
+use crate::api::{
+    TrackId,
+    Uid,
+};
+use crate::config;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ResumePoint {
+    pub track_id: TrackId,
+    pub position: Duration,
+}
+
+// Where the previous run stopped, offered back on the next launch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastSession {
+    pub source: String,
+    pub title: String,
+    pub index: usize,
+    pub total: usize,
+    pub point: ResumePoint,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Levels {
+    pub volume: f32,
+    pub speed: f32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResumeStore {
+    points: HashMap<String, ResumePoint>,
+    #[serde(default)]
+    episodes: HashMap<TrackId, Duration>,
+    #[serde(default)]
+    last: Option<LastSession>,
+    #[serde(default)]
+    levels: Option<Levels>,
+    // Where the account's store was loaded from and goes back to
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl ResumeStore {
+    pub fn load(uid: Uid) -> Self {
+        let path = config::account_path(config::data_dir(), uid, "resume.json");
+        let store = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        ResumeStore { path, ..store }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec(self)?)
+    }
+
+    pub fn get(&self, key: &str) -> Option<ResumePoint> {
+        self.points.get(key).copied()
+    }
+
+    pub fn set(&mut self, key: String, point: ResumePoint) {
+        self.points.insert(key, point);
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = (&str, ResumePoint)> {
+        self.points.iter().map(|(key, point)| (key.as_str(), *point))
+    }
+
+    // The last session goes too when it was in the source
+    pub fn remove(&mut self, key: &str) {
+        self.points.remove(key);
+        if self.last.as_ref().is_some_and(|last| last.source == key) {
+            self.last = None;
+        }
+    }
+
+    pub fn last(&self) -> Option<&LastSession> {
+        self.last.as_ref()
+    }
+
+    pub fn set_last(&mut self, last: LastSession) {
+        self.last = Some(last);
+    }
+
+    pub fn levels(&self) -> Option<Levels> {
+        self.levels
+    }
+
+    pub fn set_levels(&mut self, levels: Levels) {
+        self.levels = Some(levels);
+    }
+
+    pub fn episode(&self, id: TrackId) -> Option<Duration> {
+        self.episodes.get(&id).copied()
+    }
+
+    pub fn set_episode(&mut self, id: TrackId, position: Duration) {
+        self.episodes.insert(id, position);
+    }
+
+    pub fn finish_episode(&mut self, id: TrackId) {
+        self.episodes.remove(&id);
+    }
+}
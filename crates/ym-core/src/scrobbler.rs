@@ -0,0 +1,402 @@
+use crate::api::{
+    send_play_audio,
+    AlbumId,
+    ApiError,
+    PlayAudio,
+    SharedClient,
+    TrackId,
+    Uid,
+};
+use crate::config::{
+    self,
+    Config,
+    LastFmConfig,
+    ListenBrainzConfig,
+};
+use crate::local;
+
+use chrono::{
+    DateTime,
+    SecondsFormat,
+    Utc,
+};
+
+use futures::future::BoxFuture;
+
+use reqwest::Client;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    Arc,
+    Mutex,
+};
+use std::time::Duration;
+
+use tokio::{
+    runtime::Handle,
+    time::interval,
+};
+
+const LASTFM_API: &str = "https://ws.audioscrobbler.com/2.0/";
+const LISTENBRAINZ_API: &str = "https://api.listenbrainz.org/1/submit-listens";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayEvent {
+    pub track_id: TrackId,
+    pub album_id: Option<AlbumId>,
+    pub playlist_id: Option<String>,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub duration: Option<Duration>,
+    pub started: DateTime<Utc>,
+    pub played: Duration,
+    pub position: Duration,
+}
+
+impl PlayEvent {
+    fn listened_half(&self) -> bool {
+        self.duration.is_some_and(|duration| self.played * 2 >= duration)
+    }
+}
+
+pub trait Scrobbler: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Whether a finished play counts as a scrobble for this service
+    fn accepts(&self, play: &PlayEvent) -> bool;
+
+    fn now_playing<'a>(&'a self, play: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>>;
+
+    fn scrobble<'a>(&'a self, play: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>>;
+}
+
+pub struct YandexFeedback {
+    client: SharedClient,
+    uid: Uid,
+}
+
+impl YandexFeedback {
+    pub fn new(client: SharedClient, uid: Uid) -> Self {
+        YandexFeedback { client, uid }
+    }
+}
+
+impl Scrobbler for YandexFeedback {
+    fn name(&self) -> &'static str {
+        "Yandex"
+    }
+
+    // Local files have nothing to report to Yandex
+    fn accepts(&self, play: &PlayEvent) -> bool {
+        !local::is_local(play.track_id)
+    }
+
+    fn now_playing<'a>(&'a self, _: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn scrobble<'a>(&'a self, play: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>> {
+        let played = play.played.as_secs_f64();
+        let feedback = PlayAudio {
+            track_id: play.track_id,
+            album_id: play.album_id,
+            playlist_id: play.playlist_id.clone(),
+            from: "yandex_music_tui",
+            from_cache: false,
+            uid: self.uid,
+            timestamp: play.started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            client_now: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            track_length_seconds: play.duration.map_or(played, |duration| duration.as_secs_f64()),
+            total_played_seconds: played,
+            end_position_seconds: play.position.as_secs_f64(),
+        };
+        Box::pin(async move { send_play_audio(&feedback, &self.client.get()).await })
+    }
+}
+
+pub struct LastFm {
+    client: Client,
+    config: &'static LastFmConfig,
+}
+
+impl LastFm {
+    pub fn new(config: &'static LastFmConfig) -> Self {
+        // A separate client on purpose: the Yandex one carries the OAuth header
+        LastFm {
+            client: Client::new(),
+            config,
+        }
+    }
+
+    fn track_params(&self, method: &str, play: &PlayEvent) -> BTreeMap<&'static str, String> {
+        let mut params = BTreeMap::new();
+        params.insert("method", method.to_owned());
+        params.insert("artist", play.artist.clone());
+        params.insert("track", play.title.clone());
+        if let Some(album) = &play.album {
+            params.insert("album", album.clone());
+        }
+        if let Some(duration) = play.duration {
+            params.insert("duration", duration.as_secs().to_string());
+        }
+        params.insert("api_key", self.config.api_key.clone());
+        params.insert("sk", self.config.session_key.clone());
+        params
+    }
+
+    async fn call(&self, mut params: BTreeMap<&'static str, String>) -> Result<(), ApiError> {
+        params.insert("api_sig", self.signature(&params));
+        params.insert("format", "json".to_owned());
+
+        self.client
+            .post(LASTFM_API)
+            .form(&params)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn signature(&self, params: &BTreeMap<&'static str, String>) -> String {
+        let mut payload = String::new();
+        for (key, value) in params {
+            payload.push_str(key);
+            payload.push_str(value);
+        }
+        payload.push_str(&self.config.api_secret);
+
+        hex::encode::<[u8; 16]>(md5::compute(payload.as_bytes()).into())
+    }
+}
+
+impl Scrobbler for LastFm {
+    fn name(&self) -> &'static str {
+        "Last.fm"
+    }
+
+    // Last.fm rules: longer than 30 seconds and listened to at least half way
+    fn accepts(&self, play: &PlayEvent) -> bool {
+        play.duration.is_some_and(|duration| duration > Duration::from_secs(30)) && play.listened_half()
+    }
+
+    fn now_playing<'a>(&'a self, play: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>> {
+        let params = self.track_params("track.updateNowPlaying", play);
+        Box::pin(self.call(params))
+    }
+
+    fn scrobble<'a>(&'a self, play: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>> {
+        let mut params = self.track_params("track.scrobble", play);
+        params.insert("timestamp", play.started.timestamp().to_string());
+        Box::pin(self.call(params))
+    }
+}
+
+pub struct ListenBrainz {
+    client: Client,
+    config: &'static ListenBrainzConfig,
+}
+
+impl ListenBrainz {
+    pub fn new(config: &'static ListenBrainzConfig) -> Self {
+        ListenBrainz {
+            client: Client::new(),
+            config,
+        }
+    }
+
+    async fn submit(&self, listen_type: &str, play: &PlayEvent, listened_at: Option<i64>) -> Result<(), ApiError> {
+        let mut listen = serde_json::json!({
+            "track_metadata": {
+                "artist_name": play.artist,
+                "track_name": play.title,
+                "release_name": play.album,
+                "additional_info": {
+                    "duration_ms": play.duration.map(|duration| duration.as_millis() as u64),
+                    "submission_client": "yandex_music_tui",
+                },
+            },
+        });
+        if let Some(listened_at) = listened_at {
+            listen["listened_at"] = listened_at.into();
+        }
+
+        self.client
+            .post(LISTENBRAINZ_API)
+            .header("Authorization", format!("Token {}", self.config.token))
+            .json(&serde_json::json!({
+                "listen_type": listen_type,
+                "payload": [listen],
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+impl Scrobbler for ListenBrainz {
+    fn name(&self) -> &'static str {
+        "ListenBrainz"
+    }
+
+    // ListenBrainz rules: four minutes or half the track, whichever is lower
+    fn accepts(&self, play: &PlayEvent) -> bool {
+        play.played >= Duration::from_secs(240) || play.listened_half()
+    }
+
+    fn now_playing<'a>(&'a self, play: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>> {
+        Box::pin(self.submit("playing_now", play, None))
+    }
+
+    fn scrobble<'a>(&'a self, play: &'a PlayEvent) -> BoxFuture<'a, Result<(), ApiError>> {
+        Box::pin(self.submit("single", play, Some(play.started.timestamp())))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingScrobble {
+    service: String,
+    play: PlayEvent,
+}
+
+fn pending_path() -> PathBuf {
+    config::data_dir().join("scrobbles.json")
+}
+
+fn load_pending() -> Vec<PendingScrobble> {
+    std::fs::read(pending_path())
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+const RETRY_PERIOD: Duration = Duration::from_secs(60);
+
+// Every enabled service plus the scrobbles they failed to accept. Those are kept on disk
+// and retried on the next transition or periodically, whichever comes first
+#[derive(Clone)]
+pub struct Scrobblers {
+    services: Arc<Vec<Box<dyn Scrobbler>>>,
+    pending: Arc<Mutex<Vec<PendingScrobble>>>,
+    flushing: Arc<AtomicBool>,
+}
+
+impl Scrobblers {
+    pub fn from_config(config: &'static Config, client: SharedClient, uid: Uid) -> Self {
+        let mut services: Vec<Box<dyn Scrobbler>> = Vec::new();
+        if config.yandex_feedback {
+            services.push(Box::new(YandexFeedback::new(client, uid)));
+        }
+        if let Some(lastfm) = config.lastfm.as_ref().filter(|lastfm| lastfm.enabled) {
+            services.push(Box::new(LastFm::new(lastfm)));
+        }
+        if let Some(listenbrainz) = config.listenbrainz.as_ref().filter(|listenbrainz| listenbrainz.enabled) {
+            services.push(Box::new(ListenBrainz::new(listenbrainz)));
+        }
+
+        let scrobblers = Scrobblers {
+            services: Arc::new(services),
+            pending: Arc::new(Mutex::new(load_pending())),
+            flushing: Arc::new(AtomicBool::new(false)),
+        };
+
+        let this = scrobblers.clone();
+        Handle::current().spawn(async move {
+            let mut timer = interval(RETRY_PERIOD);
+            loop {
+                timer.tick().await;
+                this.retry();
+            }
+        });
+
+        scrobblers
+    }
+
+    fn save_pending(&self) {
+        let pending = self.pending.lock().unwrap();
+        let result = serde_json::to_vec(&*pending)
+            .map_err(std::io::Error::from)
+            .and_then(|bytes| {
+                std::fs::create_dir_all(config::data_dir())?;
+                std::fs::write(pending_path(), bytes)
+            });
+        if let Err(err) = result {
+            tracing::warn!("Failed to save pending scrobbles: {}", err);
+        }
+    }
+
+    pub fn now_playing(&self, play: PlayEvent) {
+        let services = self.services.clone();
+        Handle::current().spawn(async move {
+            for service in services.iter() {
+                if let Err(err) = service.now_playing(&play).await {
+                    tracing::warn!("Failed to update now playing on {}: {}", service.name(), err);
+                }
+            }
+        });
+    }
+
+    pub fn scrobble(&self, play: PlayEvent) {
+        let this = self.clone();
+        Handle::current().spawn(async move {
+            for service in this.services.iter() {
+                if !service.accepts(&play) {
+                    continue;
+                }
+                if let Err(err) = service.scrobble(&play).await {
+                    tracing::warn!("Failed to scrobble to {}, will retry later: {}", service.name(), err);
+                    this.pending.lock().unwrap().push(PendingScrobble {
+                        service: service.name().to_owned(),
+                        play: play.clone(),
+                    });
+                    this.save_pending();
+                }
+            }
+        });
+        self.retry();
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    pub fn retry(&self) {
+        if self.pending() == 0 || self.flushing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let this = self.clone();
+        Handle::current().spawn(async move {
+            // Entries stay in place (and on disk) until sent; new failures only get appended
+            let pending = this.pending.lock().unwrap().clone();
+            let attempted = pending.len();
+            let mut failed = Vec::new();
+            for scrobble in pending {
+                // Entries of services disabled since are kept until they get enabled again
+                let Some(service) = this.services.iter().find(|service| service.name() == scrobble.service) else {
+                    failed.push(scrobble);
+                    continue;
+                };
+                if service.scrobble(&scrobble.play).await.is_err() {
+                    failed.push(scrobble);
+                }
+            }
+            this.pending.lock().unwrap().splice(0..attempted, failed);
+            this.save_pending();
+            this.flushing.store(false, Ordering::Release);
+        });
+    }
+}
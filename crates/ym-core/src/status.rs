@@ -0,0 +1,97 @@
+use serde::Serialize;
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+// Shaped for waybar's custom modules, text is all polybar needs, the raw fields are for scripts
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub text: String,
+    pub tooltip: String,
+    // playing, paused or stopped
+    pub class: &'static str,
+    pub percentage: u8,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub album: Option<String>,
+    // Both in seconds
+    pub position: Option<u64>,
+    pub duration: Option<u64>,
+    pub volume: u16,
+}
+
+impl Status {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_: &std::path::Path) -> bool {
+    false
+}
+
+// Opening without a reader fails instead of blocking the player until one shows up
+#[cfg(unix)]
+fn open_fifo(path: &std::path::Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open(path)
+}
+
+#[cfg(not(unix))]
+fn open_fifo(path: &std::path::Path) -> std::io::Result<File> {
+    std::fs::OpenOptions::new().write(true).open(path)
+}
+
+// A FIFO gets a line per change for as long as its reader stays, a plain file always holds the latest one
+pub struct StatusFile {
+    path: PathBuf,
+    fifo: Option<File>,
+    last: Option<String>,
+}
+
+impl StatusFile {
+    pub fn new(path: PathBuf) -> Self {
+        StatusFile { path, fifo: None, last: None }
+    }
+
+    pub fn write(&mut self, line: String) {
+        if self.last.as_ref() == Some(&line) {
+            return;
+        }
+        let result = if is_fifo(&self.path) { self.write_fifo(&line) } else { self.replace(&line) };
+        match result {
+            Ok(()) => self.last = Some(line),
+            Err(err) => tracing::debug!("Failed to write the status to {}: {}", self.path.display(), err),
+        }
+    }
+
+    fn write_fifo(&mut self, line: &str) -> std::io::Result<()> {
+        let fifo = match &mut self.fifo {
+            Some(fifo) => fifo,
+            None => self.fifo.insert(open_fifo(&self.path)?),
+        };
+        // A single write of the whole line, pipes take that in one piece or not at all,
+        // so a reader that fell behind misses the line instead of getting half of it
+        let result = fifo.write_all(format!("{}\n", line).as_bytes());
+        // The reader went away, the next one gets a fresh handle
+        if result.as_ref().is_err_and(|err| err.kind() != std::io::ErrorKind::WouldBlock) {
+            self.fifo = None;
+        }
+        result
+    }
+
+    // Through a rename, so readers never see a half written line
+    fn replace(&self, line: &str) -> std::io::Result<()> {
+        let partial = self.path.with_extension("partial");
+        std::fs::write(&partial, format!("{}\n", line))?;
+        std::fs::rename(partial, &self.path)
+    }
+}
@@ -0,0 +1,181 @@
+use crate::say;
+use crate::api::{
+    download_cover,
+    download_data,
+    ApiError,
+    Quality,
+    Track,
+};
+use crate::bundle::file_safe;
+use crate::cache::AudioCache;
+
+use futures::StreamExt;
+use id3::{
+    frame::{
+        Picture,
+        PictureType,
+    },
+    TagLike,
+};
+use reqwest::Client;
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+#[derive(Debug)]
+pub enum SaveError {
+    Api(ApiError),
+    Io(std::io::Error),
+    Tag(id3::Error),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Api(err) => write!(f, "{}", err),
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::Tag(err) => write!(f, "failed to tag: {}", err),
+        }
+    }
+}
+
+impl From<ApiError> for SaveError {
+    fn from(err: ApiError) -> Self {
+        SaveError::Api(err)
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<id3::Error> for SaveError {
+    fn from(err: id3::Error) -> Self {
+        SaveError::Tag(err)
+    }
+}
+
+fn file_name(track: &Track) -> String {
+    let artists = track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", ");
+    file_safe(&format!("{} - {}", artists, track.full_title()))
+}
+
+// Tracks sharing a name get their id appended, so no two of them write the same file at once.
+// Compared case-insensitively for the filesystems that do the same
+fn unique_names(tracks: &[&Track]) -> Vec<String> {
+    let names = tracks.iter().map(|track| file_name(track)).collect::<Vec<_>>();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for name in &names {
+        *counts.entry(name.to_lowercase()).or_default() += 1;
+    }
+    names
+        .into_iter()
+        .zip(tracks)
+        .map(|(name, track)| match counts[&name.to_lowercase()] {
+            1 => name,
+            _ => format!("{} [{}]", name, track.id),
+        })
+        .collect()
+}
+
+// Cached audio is reused, the cover is left out when it can't be fetched
+async fn save_track(
+    track: &Track,
+    name: &str,
+    dir: &Path,
+    quality: Quality,
+    cache: Option<&AudioCache>,
+    client: &Client,
+) -> Result<PathBuf, SaveError> {
+    let data = match cache.and_then(|cache| cache.load(track.id, quality)) {
+        Some(data) => data,
+        None => download_data(track.id, quality, client).await?,
+    };
+    let path = dir.join(format!("{}.{}", name, data.codec));
+    std::fs::write(&path, data.data.get_ref())?;
+
+    let mut tag = id3::Tag::new();
+    tag.set_title(track.full_title());
+    tag.set_artist(track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", "));
+    if let Some(album) = track.albums.first() {
+        tag.set_album(&album.title);
+        if let Some(year) = album.year {
+            tag.set_year(year);
+        }
+        if let Some(artist) = album.artists.first() {
+            tag.set_album_artist(&artist.name);
+        }
+        if let Some(genre) = &album.genre {
+            tag.set_genre(genre);
+        }
+    }
+    if let Some(uri) = track.cover_uri() {
+        match download_cover(uri, 400, client).await {
+            Ok(cover) => {
+                tag.add_frame(Picture {
+                    mime_type: "image/jpeg".to_owned(),
+                    picture_type: PictureType::CoverFront,
+                    description: String::new(),
+                    data: cover.to_vec(),
+                });
+            },
+            Err(err) => tracing::warn!("Failed to fetch the cover of {}: {}", track.id, err),
+        }
+    }
+    tag.write_to_path(&path, id3::Version::Id3v24)?;
+
+    Ok(path)
+}
+
+// The tracks without repeats, in their order, so a track listed twice is saved once
+pub fn distinct<'a>(tracks: impl IntoIterator<Item = &'a Track>) -> Vec<&'a Track> {
+    let mut seen = HashSet::new();
+    tracks.into_iter().filter(|track| seen.insert(track.id)).collect()
+}
+
+// Saves tracks as "Artist - Title" files tagged from their metadata, several at once.
+// Returns how many were saved, each result goes to the callback as it comes in
+pub async fn save_tracks(
+    tracks: &[&Track],
+    dir: &Path,
+    quality: Quality,
+    cache: Option<&AudioCache>,
+    client: &Client,
+    jobs: usize,
+    mut progress: impl FnMut(&Track, Result<PathBuf, SaveError>),
+) -> std::io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let tracks = distinct(tracks.iter().copied());
+    let names = unique_names(&tracks);
+    let mut saved = 0;
+    let mut downloads = futures::stream::iter(tracks.into_iter().zip(names).map(|(track, name)| async move {
+        let result = save_track(track, &name, dir, quality, cache, client).await;
+        (track, result)
+    }))
+    .buffer_unordered(jobs.max(1));
+    while let Some((track, result)) = downloads.next().await {
+        saved += usize::from(result.is_ok());
+        progress(track, result);
+    }
+    Ok(saved)
+}
+
+// One line per finished track, for the prompt and the download subcommand alike
+pub fn print_progress(total: usize) -> impl FnMut(&Track, Result<PathBuf, SaveError>) {
+    let mut done = 0;
+    move |track, result| {
+        done += 1;
+        match result {
+            Ok(path) => say!("[{}/{}] {}", done, total, path.display()),
+            Err(err) => say!("[{}/{}] Failed to save {}: {}", done, total, track, err),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Reduces text to a lowercase Latin skeleton so "molchat doma" and "Молчат Дома"
+// compare equal, along with the usual spelling variants of romanized names
+pub fn fold(text: &str) -> String {
+    let mut latin = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match cyrillic(c) {
+            Some(romanized) => latin.push_str(romanized),
+            None => latin.push(c),
+        }
+    }
+
+    [("kh", "h"), ("ia", "ya"), ("iu", "yu"), ("j", "y"), ("w", "v"), ("tz", "ts"), ("x", "ks")]
+        .iter()
+        .fold(latin, |text, (from, to)| text.replace(from, to))
+}
+
+fn cyrillic(c: char) -> Option<&'static str> {
+    Some(match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        'є' => "ye",
+        'ї' => "yi",
+        _ => return None,
+    })
+}
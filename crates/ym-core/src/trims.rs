@@ -0,0 +1,55 @@
+use crate::api::TrackId;
+use crate::config;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Trim {
+    pub start: Option<Duration>,
+    pub end: Option<Duration>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrimStore {
+    tracks: HashMap<TrackId, Trim>,
+}
+
+fn store_path() -> PathBuf {
+    config::data_dir().join("trims.json")
+}
+
+impl TrimStore {
+    pub fn load() -> Self {
+        std::fs::read(store_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = store_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    pub fn get(&self, id: TrackId) -> Trim {
+        self.tracks.get(&id).copied().unwrap_or_default()
+    }
+
+    pub fn update(&mut self, id: TrackId, f: impl FnOnce(&mut Trim)) {
+        let trim = self.tracks.entry(id).or_default();
+        f(trim);
+        if trim.start.is_none() && trim.end.is_none() {
+            self.tracks.remove(&id);
+        }
+    }
+}
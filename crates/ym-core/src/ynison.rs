@@ -0,0 +1,411 @@
+// Ynison keeps the player state of every device on the account in sync. Connected, this player
+// shows up among the devices of the official apps, which can control it or take playback over
+use crate::api::{
+    AlbumId,
+    TrackId,
+};
+
+use tokio::sync::mpsc;
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playable {
+    pub track_id: TrackId,
+    pub album_id: Option<AlbumId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct State {
+    pub queue: Vec<Playable>,
+    pub index: Option<usize>,
+    pub paused: bool,
+    pub progress: Duration,
+    pub devices: Vec<Device>,
+    pub active_device: Option<String>,
+    // The device that made the change, echoes of our own updates are told apart by it
+    pub changed_by: Option<String>,
+}
+
+impl State {
+    pub fn current(&self) -> Option<Playable> {
+        self.queue.get(self.index?).copied()
+    }
+
+    pub fn device_title(&self, id: &str) -> &str {
+        self.devices
+            .iter()
+            .find(|device| device.id == id)
+            .map_or("another device", |device| device.title.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Status {
+    pub paused: bool,
+    pub progress: Duration,
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+pub enum Update {
+    Queue { queue: Vec<Playable>, index: usize, status: Status },
+    Status(Status),
+    // Makes the device with this id the one playing
+    Activate(String),
+}
+
+// Dropping the link closes the connection
+#[derive(Debug)]
+pub struct Link {
+    pub device_id: String,
+    updates: mpsc::UnboundedSender<Update>,
+}
+
+impl Link {
+    pub fn send(&self, update: Update) {
+        if self.updates.send(update).is_err() {
+            tracing::warn!("Ynison connection is gone");
+        }
+    }
+}
+
+#[cfg(feature = "ynison")]
+pub use connection::connect;
+
+#[cfg(feature = "ynison")]
+mod connection {
+    use super::*;
+    use crate::config;
+
+    use futures::{
+        SinkExt,
+        StreamExt,
+    };
+    use serde::{
+        Deserialize,
+        Deserializer,
+    };
+    use serde_json::{
+        json,
+        Value,
+    };
+    use tokio_tungstenite::tungstenite::{
+        client::IntoClientRequest,
+        handshake::client::Request,
+        Message,
+    };
+
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    // Kept across runs, so the apps don't list a new device after every start
+    fn device_id() -> String {
+        let path = config::data_dir().join("ynison-device");
+        if let Some(id) = std::fs::read_to_string(&path).ok().filter(|id| !id.trim().is_empty()) {
+            return id.trim().to_owned();
+        }
+        let id = format!("{:032x}", rand::random::<u128>());
+        if let Err(err) = std::fs::create_dir_all(config::data_dir()).and_then(|_| std::fs::write(&path, &id)) {
+            tracing::warn!("Failed to save the Ynison device id: {}", err);
+        }
+        id
+    }
+
+    const REDIRECTOR: &str = "wss://ynison.music.yandex.ru/redirector.YnisonRedirectService/GetRedirectToYnison";
+    const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+    #[derive(Debug, Deserialize)]
+    struct Redirect {
+        host: String,
+        redirect_ticket: String,
+        session_id: Value,
+    }
+
+    // 64-bit numbers come as strings, like in any protobuf turned into JSON
+    fn int<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Number(number) => Ok(number.as_i64().unwrap_or_default()),
+            Value::String(string) => string.parse().map_err(serde::de::Error::custom),
+            _ => Ok(0),
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Version {
+        device_id: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct StatusMessage {
+        #[serde(default)]
+        paused: bool,
+        #[serde(default, deserialize_with = "int")]
+        progress_ms: i64,
+        version: Option<Version>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct PlayableMessage {
+        playable_id: String,
+        album_id_optional: Option<String>,
+        playable_type: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct QueueMessage {
+        #[serde(default = "no_index", deserialize_with = "int")]
+        current_playable_index: i64,
+        #[serde(default)]
+        playable_list: Vec<PlayableMessage>,
+        version: Option<Version>,
+    }
+
+    fn no_index() -> i64 {
+        -1
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct PlayerStateMessage {
+        status: StatusMessage,
+        player_queue: QueueMessage,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct DeviceInfo {
+        device_id: String,
+        #[serde(default)]
+        title: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct DeviceMessage {
+        info: DeviceInfo,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct StateMessage {
+        player_state: PlayerStateMessage,
+        #[serde(default)]
+        devices: Vec<DeviceMessage>,
+        active_device_id_optional: Option<String>,
+    }
+
+    impl From<StateMessage> for State {
+        fn from(message: StateMessage) -> Self {
+            let PlayerStateMessage { status, player_queue } = message.player_state;
+            // User uploaded tracks and anything that isn't a track are left out, the index follows
+            let mut index = None;
+            let mut queue = Vec::new();
+            for (n, playable) in player_queue.playable_list.into_iter().enumerate() {
+                let Ok(track_id) = playable.playable_id.parse() else {
+                    continue;
+                };
+                if playable.playable_type != "TRACK" {
+                    continue;
+                }
+                if n as i64 == player_queue.current_playable_index {
+                    index = Some(queue.len());
+                }
+                let album_id = playable.album_id_optional.and_then(|id| id.parse().ok());
+                queue.push(Playable { track_id, album_id });
+            }
+            let changed_by = status.version.or(player_queue.version).map(|version| version.device_id);
+            State {
+                queue,
+                index,
+                paused: status.paused,
+                progress: Duration::from_millis(status.progress_ms.max(0) as u64),
+                devices: message
+                    .devices
+                    .into_iter()
+                    .map(|device| Device { id: device.info.device_id, title: device.info.title })
+                    .collect(),
+                active_device: message.active_device_id_optional.filter(|id| !id.is_empty()),
+                changed_by,
+            }
+        }
+    }
+
+    fn now_ms() -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+
+    fn request_id() -> String {
+        let id = format!("{:032x}", rand::random::<u128>());
+        format!("{}-{}-{}-{}-{}", &id[..8], &id[8..12], &id[12..16], &id[16..20], &id[20..])
+    }
+
+    fn request(url: &str, token: &str, protocol: Value) -> Result<Request, Error> {
+        let mut request = url.into_client_request()?;
+        let headers = request.headers_mut();
+        headers.insert("Sec-WebSocket-Protocol", format!("Bearer, v2, {}", protocol).parse()?);
+        headers.insert("Origin", "http://music.yandex.ru".parse()?);
+        headers.insert("Authorization", format!("OAuth {}", token).parse()?);
+        Ok(request)
+    }
+
+    fn protocol(device_id: &str, redirect: Option<&Redirect>) -> Value {
+        let mut protocol = json!({
+            "Ynison-Device-Id": device_id,
+            "Ynison-Device-Info": json!({ "app_name": "Chrome", "type": 1 }).to_string(),
+        });
+        if let Some(redirect) = redirect {
+            let session_id = match &redirect.session_id {
+                Value::String(id) => id.clone(),
+                id => id.to_string(),
+            };
+            protocol["Ynison-Redirect-Ticket"] = json!(redirect.redirect_ticket);
+            protocol["Ynison-Session-Id"] = json!(session_id);
+        }
+        protocol
+    }
+
+    fn version(device_id: &str, version: u64) -> Value {
+        json!({ "device_id": device_id, "version": version, "timestamp_ms": now_ms() })
+    }
+
+    fn queue(device_id: &str, n: u64, queue: &[Playable], index: Option<usize>) -> Value {
+        let playables = queue
+            .iter()
+            .map(|playable| json!({
+                "playable_id": playable.track_id.to_string(),
+                "album_id_optional": playable.album_id.map(|id| id.to_string()),
+                "playable_type": "TRACK",
+                "from": "yandex_music_tui",
+                "title": "",
+            }))
+            .collect::<Vec<_>>();
+        json!({
+            "current_playable_index": index.map_or(-1, |index| index as i64),
+            "entity_id": "",
+            "entity_type": "VARIOUS",
+            "entity_context": "BASED_ON_ENTITY_BY_DEFAULT",
+            "from_optional": "",
+            "options": { "repeat_mode": "NONE" },
+            "playable_list": playables,
+            "version": version(device_id, n),
+        })
+    }
+
+    fn status(device_id: &str, n: u64, status: Status) -> Value {
+        json!({
+            "duration_ms": status.duration.as_millis() as u64,
+            "paused": status.paused,
+            "playback_speed": 1,
+            "progress_ms": status.progress.as_millis() as u64,
+            "version": version(device_id, n),
+        })
+    }
+
+    fn envelope(kind: &str, body: Value) -> Message {
+        let mut message = json!({
+            "rid": request_id(),
+            "player_action_timestamp_ms": now_ms(),
+            "activity_interception_type": "DO_NOT_INTERCEPT_BY_DEFAULT",
+        });
+        message[kind] = body;
+        Message::Text(message.to_string())
+    }
+
+    // Joins as a passive device, nothing plays here until playback is handed over or started
+    fn hello(device_id: &str, title: &str) -> Message {
+        envelope("update_full_state", json!({
+            "player_state": {
+                "player_queue": queue(device_id, 0, &[], None),
+                "status": status(device_id, 0, Status { paused: true, progress: Duration::ZERO, duration: Duration::ZERO }),
+            },
+            "device": {
+                "capabilities": { "can_be_player": true, "can_be_remote_controller": false, "volume_granularity": 16 },
+                "info": { "device_id": device_id, "type": "WEB", "title": title, "app_name": "Chrome" },
+                "volume_info": { "volume": 0 },
+                "is_shadow": false,
+            },
+            "is_currently_active": false,
+        }))
+    }
+
+    fn update_message(device_id: &str, n: u64, update: Update) -> Message {
+        match update {
+            Update::Queue { queue: playables, index, status: current } => envelope("update_player_state", json!({
+                "player_state": {
+                    "player_queue": queue(device_id, n, &playables, Some(index)),
+                    "status": status(device_id, n, current),
+                },
+            })),
+            Update::Status(current) => envelope("update_playing_status", json!({
+                "playing_status": status(device_id, n, current),
+            })),
+            Update::Activate(id) => envelope("update_active_device", json!({ "device_id_optional": id })),
+        }
+    }
+
+    // Ok when the player let go of the link, errors reconnect
+    async fn session(
+        token: &str,
+        device_id: &str,
+        title: &str,
+        updates: &mut mpsc::UnboundedReceiver<Update>,
+        states: &mut (impl FnMut(State) -> bool + Send),
+    ) -> Result<(), Error> {
+        let (mut redirector, _) = tokio_tungstenite::connect_async(request(REDIRECTOR, token, protocol(device_id, None))?).await?;
+        let redirect = loop {
+            match redirector.next().await.ok_or("redirector closed the connection")?? {
+                Message::Text(text) => break serde_json::from_str::<Redirect>(&text)?,
+                _ => continue,
+            }
+        };
+        drop(redirector);
+
+        let url = format!("wss://{}/ynison_state.YnisonStateService/PutYnisonState", redirect.host);
+        let (socket, _) = tokio_tungstenite::connect_async(request(&url, token, protocol(device_id, Some(&redirect)))?).await?;
+        let (mut sink, mut stream) = socket.split();
+        sink.send(hello(device_id, title)).await?;
+        tracing::info!("Connected to Ynison as {}", device_id);
+
+        let mut version = 0;
+        loop {
+            tokio::select! {
+                update = updates.recv() => {
+                    let Some(update) = update else {
+                        return Ok(());
+                    };
+                    version += 1;
+                    sink.send(update_message(device_id, version, update)).await?;
+                },
+                message = stream.next() => match message.ok_or("Ynison closed the connection")?? {
+                    Message::Text(text) => match serde_json::from_str::<StateMessage>(&text) {
+                        Ok(message) => if !states(message.into()) {
+                            return Ok(());
+                        },
+                        Err(err) => tracing::debug!("Unexpected Ynison message: {}", err),
+                    },
+                    Message::Ping(payload) => sink.send(Message::Pong(payload)).await?,
+                    _ => {},
+                },
+            }
+        }
+    }
+
+    // States go to the callback until it returns false or the link is dropped
+    pub fn connect(token: String, title: String, mut states: impl FnMut(State) -> bool + Send + 'static) -> Link {
+        let device_id = device_id();
+        let (sender, mut updates) = mpsc::unbounded_channel();
+        let id = device_id.clone();
+        tokio::spawn(async move {
+            loop {
+                match session(&token, &id, &title, &mut updates, &mut states).await {
+                    Ok(()) => return,
+                    Err(err) => tracing::warn!("Ynison connection failed: {}", err),
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        Link { device_id, updates: sender }
+    }
+}
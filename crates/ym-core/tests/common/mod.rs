@@ -0,0 +1,45 @@
+use serde_json::Value;
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+// Bumped to a new directory when the API changes in a way the old captures can't describe
+pub const FIXTURES_VERSION: &str = "v1";
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(FIXTURES_VERSION)
+        .join(format!("{}.json", name))
+}
+
+pub fn fixture(name: &str) -> Value {
+    let text = std::fs::read_to_string(fixture_path(name)).expect("Missing fixture");
+    serde_json::from_str(&text).expect("Fixture isn't valid JSON")
+}
+
+// Every field path in the value like "result.albums[].title", array items merged together
+#[allow(dead_code)]
+pub fn shape(value: &Value) -> BTreeSet<String> {
+    fn walk(value: &Value, path: &str, paths: &mut BTreeSet<String>) {
+        match value {
+            Value::Object(fields) => {
+                for (key, field) in fields {
+                    let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    paths.insert(path.clone());
+                    walk(field, &path, paths);
+                }
+            },
+            Value::Array(items) => {
+                for item in items {
+                    walk(item, &format!("{}[]", path), paths);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let mut paths = BTreeSet::new();
+    walk(value, "", &mut paths);
+    paths
+}
@@ -0,0 +1,122 @@
+// Compares the shape of live responses with the captured fixtures and reports fields Yandex
+// added or dropped. Run with YANDEX_MUSIC_TOKEN=... cargo test -p ym-core --features live-tests drift,
+// adding UPDATE_FIXTURES=1 rewrites the fixtures with the live responses once they're reviewed
+#![cfg(feature = "live-tests")]
+
+mod common;
+
+use common::{
+    fixture,
+    fixture_path,
+    shape,
+};
+
+use serde_json::Value;
+
+use ym_core::api::{
+    self,
+    YandexClient,
+};
+
+const API: &str = "https://api.music.yandex.net";
+
+// Fields the API leaves out of items that don't have them, like a track without a loudness
+// measurement or an album without a version, so their absence isn't drift
+const OPTIONAL: &[&str] = &["r128", "version", "lyricsInfo"];
+
+// Personal fields get the placeholders the fixtures were captured with
+fn placeholder(field: &str) -> Option<Value> {
+    let value = match field {
+        "uid" => Value::from(356544709),
+        "login" | "displayName" => Value::from("listener"),
+        "fullName" => Value::from("Some Listener"),
+        "firstName" => Value::from("Some"),
+        "secondName" => Value::from("Listener"),
+        "email" | "defaultEmail" => Value::from("listener@yandex.ru"),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn client() -> YandexClient {
+    let token = std::env::var("YANDEX_MUSIC_TOKEN").expect("YANDEX_MUSIC_TOKEN is not set");
+    YandexClient::new(&token).expect("Failed to create a client")
+}
+
+async fn get(client: &YandexClient, url: String) -> Value {
+    client.get(url).send().await.unwrap().json().await.unwrap()
+}
+
+fn scrub(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                match placeholder(key) {
+                    Some(value) => *field = value,
+                    None => scrub(field),
+                }
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(scrub),
+        _ => {},
+    }
+}
+
+fn optional(path: &str) -> bool {
+    path.split('.').any(|field| OPTIONAL.contains(&field.trim_end_matches("[]")))
+}
+
+// Fields only some items carry may come and go with the data, so only dropped ones fail
+fn compare(name: &str, live: &Value) -> bool {
+    if std::env::var_os("UPDATE_FIXTURES").is_some() {
+        let mut live = live.clone();
+        scrub(&mut live);
+        std::fs::write(fixture_path(name), serde_json::to_string_pretty(&live).unwrap() + "\n").unwrap();
+        return true;
+    }
+
+    let (captured, live) = (shape(&fixture(name)), shape(live));
+    for added in live.difference(&captured) {
+        println!("{}: new field {}", name, added);
+    }
+    let (optional, removed): (Vec<_>, Vec<_>) = captured.difference(&live).partition(|field| optional(field));
+    for field in &optional {
+        println!("{}: optional field {} absent this time", name, field);
+    }
+    for field in &removed {
+        println!("{}: missing field {}", name, field);
+    }
+    removed.is_empty()
+}
+
+#[tokio::test]
+async fn drift() {
+    let client = client();
+    let uid = api::account_uid(&client).await.unwrap();
+
+    let mut responses = vec![
+        ("account_status", get(&client, format!("{}/account/status", API)).await),
+        ("likes_tracks", get(&client, format!("{}/users/{}/likes/tracks", API, uid)).await),
+        ("playlists_list", get(&client, format!("{}/users/{}/playlists/list", API, uid)).await),
+    ];
+    let liked = responses[1].1.pointer("/result/library/tracks/0/id").and_then(Value::as_str).map(str::to_owned);
+    if let Some(id) = liked {
+        let tracks = client
+            .post(format!("{}/tracks", API))
+            .query(&[("trackIds", &id)])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        responses.push(("tracks", tracks));
+        responses.push(("download_info", get(&client, format!("{}/tracks/{}/download-info", API, id)).await));
+    }
+
+    let mut unchanged = true;
+    for (name, live) in &responses {
+        unchanged &= compare(name, live);
+    }
+    assert!(unchanged, "Fields went missing from the API responses, see the output above");
+}
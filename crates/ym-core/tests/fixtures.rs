@@ -0,0 +1,55 @@
+// Captured responses still parse into the API types, so a change to them can't silently
+// stop reading something Yandex sends
+mod common;
+
+use common::fixture;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use ym_core::api::{
+    AccountStatus,
+    DownloadInfo,
+    PlaylistInfo,
+    Track,
+    TracksInfoLibrary,
+};
+
+fn parse<T: DeserializeOwned>(name: &str, pointer: &str) -> T {
+    let value = fixture(name).pointer(pointer).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).unwrap_or_else(|err| panic!("Failed to parse {}: {}", name, err))
+}
+
+#[test]
+fn account_status() {
+    let account: AccountStatus = parse("account_status", "/result/account");
+    assert_eq!(account.uid.0, 356544709);
+}
+
+#[test]
+fn likes_tracks() {
+    let library: TracksInfoLibrary = parse("likes_tracks", "/result/library");
+    assert_eq!(library.revision, 1042);
+    assert_eq!(library.tracks.len(), 2);
+}
+
+#[test]
+fn playlists_list() {
+    let playlists: Vec<PlaylistInfo> = parse("playlists_list", "/result");
+    assert_eq!(playlists[0].title, "Evening");
+}
+
+#[test]
+fn tracks() {
+    let tracks: Vec<Track> = parse("tracks", "/result");
+    let track = &tracks[0];
+    assert_eq!(track.id.0, 33311009);
+    assert_eq!(track.albums[0].year, Some(2017));
+    assert!(track.r128.is_some());
+}
+
+#[test]
+fn download_info() {
+    let infos: Vec<DownloadInfo> = parse("download_info", "/result");
+    assert_eq!(infos.len(), 2);
+}
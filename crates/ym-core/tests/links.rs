@@ -0,0 +1,66 @@
+// Every form of a shared link that opens something, and the ones that must not
+use ym_core::api::{
+    AlbumId,
+    ArtistId,
+    PlaylistKind,
+    TrackId,
+};
+use ym_core::link::{
+    Link,
+    LinkError,
+};
+
+#[test]
+fn parses_supported_forms() {
+    let playlist = Link::Playlist { owner: "music-blog".to_owned(), kind: PlaylistKind(1021) };
+    let cases = [
+        ("https://music.yandex.ru/users/music-blog/playlists/1021", playlist.clone()),
+        ("http://music.yandex.ru/users/music-blog/playlists/1021/", playlist.clone()),
+        ("music.yandex.ru/users/music-blog/playlists/1021?utm_source=share", playlist.clone()),
+        ("https://www.music.yandex.com/users/music-blog/playlists/1021#top", playlist.clone()),
+        ("  https://MUSIC.YANDEX.BY/users/music-blog/playlists/1021  ", playlist),
+        ("https://music.yandex.ru/album/3542216", Link::Album(AlbumId(3542216))),
+        ("https://music.yandex.kz/album/3542216/", Link::Album(AlbumId(3542216))),
+        (
+            "https://music.yandex.ru/album/3542216/track/29245712",
+            Link::Track { album: Some(AlbumId(3542216)), track: TrackId(29245712) },
+        ),
+        ("https://music.yandex.ru/track/29245712", Link::Track { album: None, track: TrackId(29245712) }),
+        ("https://music.yandex.ru/artist/36800", Link::Artist(ArtistId(36800))),
+        ("https://music.yandex.ru/artist/36800/tracks", Link::Artist(ArtistId(36800))),
+    ];
+    for (text, link) in cases {
+        assert_eq!(text.parse::<Link>(), Ok(link), "{}", text);
+    }
+}
+
+#[test]
+fn rejects_other_links() {
+    let cases = [
+        ("https://music.yandex.evil.com/album/3542216", LinkError::NotYandexMusic),
+        ("https://music.yandex.ru.evil.com/album/3542216", LinkError::NotYandexMusic),
+        ("https://evilmusic.yandex.ru/album/3542216", LinkError::NotYandexMusic),
+        ("https://open.spotify.com/album/3542216", LinkError::NotYandexMusic),
+        ("https://music.yandex.ru/home", LinkError::Unsupported),
+        ("https://music.yandex.ru/", LinkError::Unsupported),
+        ("https://music.yandex.ru/album/abc", LinkError::BadId),
+        ("https://music.yandex.ru/users/music-blog/playlists/new", LinkError::BadId),
+    ];
+    for (text, err) in cases {
+        assert_eq!(text.parse::<Link>(), Err(err), "{}", text);
+    }
+}
+
+#[test]
+fn display_parses_back() {
+    let links = [
+        Link::Playlist { owner: "music-blog".to_owned(), kind: PlaylistKind(1021) },
+        Link::Album(AlbumId(3542216)),
+        Link::Track { album: Some(AlbumId(3542216)), track: TrackId(29245712) },
+        Link::Track { album: None, track: TrackId(29245712) },
+        Link::Artist(ArtistId(36800)),
+    ];
+    for link in links {
+        assert_eq!(link.to_string().parse::<Link>(), Ok(link.clone()));
+    }
+}
@@ -0,0 +1,56 @@
+// Smoke tests against the real API, to notice when Yandex changes something under us.
+// Run with YANDEX_MUSIC_TOKEN=... cargo test -p ym-core --features live-tests
+#![cfg(feature = "live-tests")]
+
+use ym_core::api::{
+    self,
+    Quality,
+    YandexClient,
+};
+
+fn client() -> YandexClient {
+    let token = std::env::var("YANDEX_MUSIC_TOKEN").expect("YANDEX_MUSIC_TOKEN is not set");
+    YandexClient::new(&token).expect("Failed to create a client")
+}
+
+#[tokio::test]
+async fn account_status() {
+    let client = client();
+    let account = api::account_status(&client).await.unwrap();
+    assert!(!account.login.is_empty());
+}
+
+#[tokio::test]
+async fn likes() {
+    let client = client();
+    let uid = api::account_uid(&client).await.unwrap();
+    let library = api::liked_library(uid, &client).await.unwrap();
+    let Some(info) = library.tracks.first() else {
+        return;
+    };
+    let track = api::fetch_track(info.id, &client, Some(2)).await.unwrap();
+    assert_eq!(track.id, info.id);
+}
+
+#[tokio::test]
+async fn playlists() {
+    let client = client();
+    let uid = api::account_uid(&client).await.unwrap();
+    for playlist in api::playlists(uid, &client).await.unwrap() {
+        assert_eq!(playlist.uid, uid);
+    }
+}
+
+// Goes through download-info and the direct link, only the first chunk is read
+#[tokio::test]
+async fn download() {
+    let client = client();
+    let uid = api::account_uid(&client).await.unwrap();
+    let library = api::liked_library(uid, &client).await.unwrap();
+    let Some(info) = library.tracks.first() else {
+        return;
+    };
+    let mut stream = api::open_track_stream(info.id, Quality::default(), &client).await.unwrap();
+    let chunk = stream.response.chunk().await.unwrap();
+    assert!(chunk.is_some_and(|chunk| !chunk.is_empty()));
+}
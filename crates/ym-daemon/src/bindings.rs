@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+// Commands bound to another one when entered twice within the window. The single action
+// waits the window out, so a double press never runs it
+pub struct DoublePress {
+    bindings: &'static HashMap<String, String>,
+    window: Duration,
+    // The command waiting for a second press and the token of its timer
+    pending: Arc<Mutex<Option<(String, u64)>>>,
+    next_token: u64,
+}
+
+impl DoublePress {
+    pub fn new(bindings: &'static HashMap<String, String>, window: Duration) -> Self {
+        DoublePress {
+            bindings,
+            window,
+            pending: Arc::new(Mutex::new(None)),
+            next_token: 0,
+        }
+    }
+
+    // Returns the command to run right away, a single press of a bound one goes to `later`
+    pub fn press(&mut self, line: &str, later: impl FnOnce(String) + Send + 'static) -> Option<String> {
+        let command = line.trim();
+        let Some(double) = self.bindings.get(command) else {
+            return Some(line.to_owned());
+        };
+
+        let mut pending = self.pending.lock().unwrap();
+        if pending.as_ref().is_some_and(|(waiting, _)| waiting == command) {
+            *pending = None;
+            return Some(double.clone());
+        }
+
+        self.next_token += 1;
+        let token = self.next_token;
+        *pending = Some((command.to_owned(), token));
+        drop(pending);
+
+        let pending = self.pending.clone();
+        let window = self.window;
+        let line = line.to_owned();
+        std::thread::spawn(move || {
+            std::thread::sleep(window);
+            let mut pending = pending.lock().unwrap();
+            if pending.as_ref().is_some_and(|(_, waiting)| *waiting == token) {
+                *pending = None;
+                drop(pending);
+                later(line);
+            }
+        });
+        None
+    }
+}
+
+// Gaps between repeats longer than this start a new press
+const REPEAT_GAP: Duration = Duration::from_millis(500);
+const ACCELERATE_AFTER: Duration = Duration::from_secs(1);
+const MAX_FACTOR: f32 = 8.0;
+
+struct Repeat {
+    key: &'static str,
+    started: Instant,
+    last: Instant,
+}
+
+// Steps of a command repeated without pause grow after a second, like holding a remote button,
+// so large adjustments are quick while single presses stay precise
+#[derive(Default)]
+pub struct Accelerator {
+    repeat: Option<Repeat>,
+}
+
+impl Accelerator {
+    pub fn factor(&mut self, key: &'static str) -> f32 {
+        let now = Instant::now();
+        let repeat = match &mut self.repeat {
+            Some(repeat) if repeat.key == key && now - repeat.last < REPEAT_GAP => {
+                repeat.last = now;
+                repeat
+            },
+            repeat => repeat.insert(Repeat { key, started: now, last: now }),
+        };
+        match (now - repeat.started).checked_sub(ACCELERATE_AFTER) {
+            Some(accelerated) => (2.0 + accelerated.as_secs_f32() * 2.0).min(MAX_FACTOR),
+            None => 1.0,
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use ym_core::api::{
+    self,
+    AlbumId,
+    ApiError,
+    Track,
+    TrackId,
+    TRACKS_PAGE,
+};
+use ym_core::config::Config;
+use ym_core::library;
+use ym_core::say;
+use ym_core::link::Link;
+use ym_core::player::*;
+use ym_core::tagged;
+
+use clap::{
+    Parser,
+    Subcommand,
+};
+use rand::Rng;
+use reqwest::Client;
+
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Yandex Music in the terminal")]
+pub struct Args {
+    /// Start with one of your playlists, by title or by index
+    #[arg(long, conflicts_with_all = ["favorites", "album"])]
+    pub playlist: Option<String>,
+    /// Start with liked tracks (the default)
+    #[arg(long, conflicts_with = "album")]
+    pub favorites: bool,
+    /// Start with an album by its id
+    #[arg(long)]
+    pub album: Option<AlbumId>,
+    /// Shuffle the initial queue
+    #[arg(long)]
+    pub shuffle: bool,
+    /// Initial volume from 0.0 to 1.0, the original loudness
+    #[arg(long)]
+    pub volume: Option<f32>,
+    /// Initial playback speed
+    #[arg(long)]
+    pub speed: Option<f32>,
+    /// Don't open an audio device, only drive the queue and integrations
+    #[arg(long)]
+    pub headless: bool,
+    /// Use the account of a profile from the config instead of the default one
+    #[arg(long)]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run without the prompt, taking commands only from the control socket
+    Daemon,
+    /// Log in to Yandex and save the token to the config
+    Login,
+    /// Watch the running player from another terminal, read-only unless --control is given
+    Attach {
+        /// Send the commands typed here to the player
+        #[arg(long)]
+        control: bool,
+    },
+    /// Send a command to the running player, e.g. `ctl next` or `ctl status`
+    Ctl {
+        #[arg(required = true, trailing_var_arg = true)]
+        command: Vec<String>,
+    },
+    /// Play a music.yandex link, in the already running player if there is one
+    Open {
+        url: Link,
+    },
+    /// Save tracks as tagged files named "Artist - Title"
+    Download {
+        /// One of your playlists by title or index, or anyone's as owner/kind
+        #[arg(long)]
+        playlist: Option<String>,
+        /// An album by its id
+        #[arg(long)]
+        album: Option<AlbumId>,
+        /// A track by its id, can be repeated
+        #[arg(long = "track")]
+        tracks: Vec<TrackId>,
+        /// Liked tracks
+        #[arg(long)]
+        liked: bool,
+        /// Directory to save the files to
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+        /// Tracks downloaded at once
+        #[arg(short, long, default_value_t = 4)]
+        jobs: usize,
+    },
+}
+
+// Favorites are what the player starts with anyway, so only other sources need loading
+pub async fn apply(args: &Args, player: &mut Player, rng: &mut impl Rng) -> Result<(), ApiError> {
+    if let Some(name) = &args.playlist {
+        let playlists = playlists(player).await?;
+        match find_playlist(&playlists, name) {
+            Some(playlist) => {
+                say!("Loading {}", playlist.title);
+                load_playlist_into_player(player, playlist).await?;
+            },
+            None => say!("No playlist named {}", name),
+        }
+    } else if let Some(album) = args.album {
+        load_album_into_player(player, album).await?;
+    } else if let Some(Command::Open { url }) = &args.command {
+        open_link(player, url.clone()).await?;
+    }
+
+    if args.shuffle {
+        player.shuffle_tracks(rng);
+    }
+    if let Some(volume) = args.volume {
+        player.set_volume(volume);
+    }
+    if let Some(speed) = args.speed {
+        player.change_speed(speed - player.speed());
+    }
+
+    Ok(())
+}
+
+// The same start up options as prompt commands, for handing them to a player already running
+pub fn forwarded_commands(args: &Args) -> Vec<String> {
+    let mut commands = Vec::new();
+    if let Some(name) = &args.playlist {
+        commands.push(format!("load-playlist {}", name));
+    } else if let Some(album) = args.album {
+        commands.push(format!("load-album {}", album));
+    } else if args.favorites {
+        commands.push("load-favorites".to_owned());
+    }
+    if let Some(Command::Open { url }) = &args.command {
+        commands.push(format!("open {}", url));
+    }
+    if args.shuffle {
+        commands.push("sh".to_owned());
+    }
+    if let Some(volume) = args.volume {
+        commands.push(format!("vs {}", volume));
+    }
+    if let Some(speed) = args.speed {
+        commands.push(format!("ss {}", speed));
+    }
+    commands
+}
+
+async fn playlist_tracks(selector: &str, client: &Client) -> Result<Option<Vec<Track>>, ApiError> {
+    let playlist = match selector.split_once('/').and_then(|(owner, kind)| Some((owner, kind.parse().ok()?))) {
+        Some((owner, kind)) => api::playlist_by_owner(owner, kind, client).await?,
+        None => {
+            let uid = api::account_status(client).await?.uid;
+            let mut playlists = api::playlists(uid, client).await?;
+            playlists.extend(api::liked_playlists(uid, client).await?.into_iter().filter(|playlist| playlist.uid != uid));
+            match find_playlist(&playlists, selector) {
+                Some(playlist) => playlist.clone(),
+                None => return Ok(None),
+            }
+        },
+    };
+    let ids = api::playlist_track_ids(&playlist, client).await?;
+    let mut tracks = Vec::with_capacity(ids.len());
+    for page in ids.chunks(TRACKS_PAGE) {
+        tracks.extend(api::fetch_tracks(page, client).await?);
+    }
+    Ok(Some(tracks))
+}
+
+// The download subcommand, which needs no player and no audio device
+pub async fn download(command: &Command, client: &Client, config: &Config) -> Result<(), ApiError> {
+    let Command::Download { playlist, album, tracks: ids, liked, output, jobs } = command else {
+        return Ok(());
+    };
+
+    let mut tracks = Vec::new();
+    if !ids.is_empty() {
+        tracks.extend(api::fetch_tracks(ids, client).await?);
+    }
+    if let Some(album) = album {
+        tracks.extend(api::album_with_tracks(*album, client).await?.volumes.into_iter().flatten());
+    }
+    if let Some(selector) = playlist {
+        match playlist_tracks(selector, client).await? {
+            Some(found) => tracks.extend(found),
+            None => say!("No playlist matches {}", selector),
+        }
+    }
+    if *liked {
+        let uid = api::account_status(client).await?.uid;
+        tracks.extend(library::liked_tracks(uid, client, config.fetch_concurrency, |_, _| {}).await?);
+    }
+    if tracks.is_empty() {
+        say!("Nothing to download, pick tracks with --playlist, --album, --track or --liked");
+        return Ok(());
+    }
+
+    let tracks = tagged::distinct(&tracks);
+    let progress = tagged::print_progress(tracks.len());
+    match tagged::save_tracks(&tracks, output, config.quality, None, client, *jobs, progress).await {
+        Ok(saved) => say!("Saved {} of {} tracks to {}", saved, tracks.len(), output.display()),
+        Err(err) => say!("Failed to save to {}: {}", output.display(), err),
+    }
+    Ok(())
+}
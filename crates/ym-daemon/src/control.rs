@@ -0,0 +1,162 @@
+use ym_core::config;
+use ym_core::events::Event;
+use crate::AppEvent;
+
+use lazy_static::lazy_static;
+
+use std::cell::Cell;
+use std::path::PathBuf;
+use tokio::sync::{
+    broadcast,
+    mpsc,
+};
+
+use tokio::{
+    io::{
+        AsyncBufReadExt,
+        AsyncWriteExt,
+        BufReader,
+    },
+    net::{
+        unix::OwnedWriteHalf,
+        UnixListener,
+        UnixStream,
+    },
+    sync::oneshot,
+};
+
+// Lines a watching client hasn't read yet, past that it skips ahead
+const WATCH_BACKLOG: usize = 256;
+
+lazy_static! {
+    static ref WATCHERS: broadcast::Sender<String> = broadcast::channel(WATCH_BACKLOG).0;
+}
+
+// Passes what the player reports on to the clients that sent watch
+pub fn publish(event: &Event) {
+    if let Event::Message(line) = event {
+        let _ = WATCHERS.send(line.clone());
+    }
+}
+
+pub fn socket_path() -> PathBuf {
+    config::runtime_dir().join("yandex_music_tui.sock")
+}
+
+// Held while the player runs, None when another instance holds it
+pub fn lock() -> std::io::Result<Option<std::fs::File>> {
+    let path = config::runtime_dir().join("yandex_music_tui.lock");
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = std::fs::OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+        Err(std::fs::TryLockError::Error(err)) => Err(err),
+    }
+}
+
+// A socket activated service gets the listening socket from systemd as the first passed descriptor
+fn activated_listener() -> Option<std::io::Result<UnixListener>> {
+    let pid = std::env::var("LISTEN_PID").ok()?.parse::<u32>().ok()?;
+    let fds = std::env::var("LISTEN_FDS").ok()?.parse::<u32>().ok()?;
+    if pid != std::process::id() || fds == 0 {
+        return None;
+    }
+    use std::os::fd::FromRawFd;
+    // SAFETY: systemd passes descriptors from 3 on to this process alone, nothing else owns them
+    let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(3) };
+    Some(listener.set_nonblocking(true).and_then(|_| UnixListener::from_std(listener)))
+}
+
+// Accepts the same commands as the prompt, one per line, and answers each with a line.
+// watch turns the connection into a feed of everything the player reports instead
+pub fn serve(tx: mpsc::UnboundedSender<AppEvent>) -> std::io::Result<()> {
+    let listener = match activated_listener() {
+        Some(listener) => listener?,
+        None => bind()?,
+    };
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_client(stream, tx.clone()));
+                },
+                Err(err) => tracing::error!("Failed to accept a control connection: {}", err),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn bind() -> std::io::Result<UnixListener> {
+    let path = socket_path();
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(std::io::Error::new(std::io::ErrorKind::AddrInUse, "another player is already running"));
+        }
+        std::fs::remove_file(&path)?;
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    UnixListener::bind(&path)
+}
+
+async fn handle_client(stream: UnixStream, tx: mpsc::UnboundedSender<AppEvent>) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim() == "watch" {
+            return watch(write).await;
+        }
+        let status = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["status"] => Some(false),
+            ["status", "--json"] => Some(true),
+            _ => None,
+        };
+        let reply = if let Some(json) = status {
+            let (sender, receiver) = oneshot::channel();
+            if tx.send(AppEvent::Status { json, reply: Some(sender) }).is_err() {
+                break;
+            }
+            receiver.await.unwrap_or_else(|_| "Player has quit".to_owned())
+        } else {
+            let failed = Cell::new(None);
+            crate::parse_command(&line, &tx, |message| failed.set(Some(message)));
+            match failed.get() {
+                Some(message) => format!("Error parsing input: {}", message),
+                None => "ok".to_owned(),
+            }
+        };
+
+        if write.write_all(format!("{}\n", reply).as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn watch(mut write: OwnedWriteHalf) {
+    let mut receiver = WATCHERS.subscribe();
+    loop {
+        let line = match receiver.recv().await {
+            Ok(line) => line,
+            Err(broadcast::error::RecvError::Lagged(missed)) => format!("({} lines missed)", missed),
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if write.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+pub async fn send(command: &str) -> std::io::Result<String> {
+    let mut stream = UnixStream::connect(socket_path()).await?;
+    stream.write_all(format!("{}\n", command).as_bytes()).await?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).await?;
+    Ok(reply.trim_end().to_owned())
+}
@@ -0,0 +1,1247 @@
+pub mod bindings;
+pub mod cli;
+pub mod control;
+mod logging;
+mod login;
+mod mpd;
+mod timings;
+
+use ym_core::{
+    api,
+    art,
+    audit,
+    availability,
+    config,
+    eq,
+    events::{
+        self,
+        Event,
+    },
+    export,
+    link,
+    player::*,
+    say,
+    trims,
+    ynison,
+    YandexClient,
+};
+
+use tokio::sync::mpsc;
+
+use std::sync::{
+    atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    Arc,
+    Mutex,
+};
+
+use rand::thread_rng;
+
+
+#[derive(Debug)]
+pub enum AppEvent {
+    ChangeVolume(f32),
+    SetVolume(f32),
+    SetVolumePercent(f32),
+    PrintVolume,
+    ToggleMute,
+    ChangeSpeed(f32), 
+    PrintSpeed,
+    SetSpeed(f32), 
+    TogglePlayback,
+    Seek(std::time::Duration),
+    SeekPercent(f32),
+    SeekBy(i64),
+    NextTrack,
+    SkipTracks(usize),
+    PlayIndex(usize),
+    ShowTracks,
+    ShareCard { copy: bool },
+    Filter(Option<String>),
+    Sort(SortKey),
+    Enqueue(usize),
+    AddLocal(std::path::PathBuf),
+    PrevTrack,
+    Shuffle,
+    ShowQueue,
+    ShowLibrary,
+    SearchQueue(String),
+    SearchNext,
+    SearchPrev,
+    ToggleSkipPlayed,
+    ToggleAnnounce,
+    ToggleNoRepeats,
+    ToggleNormalize,
+    ShowEq,
+    SetEqPreset(String),
+    Dislike,
+    ListPlaylists,
+    LoadPlaylist(Option<String>),
+    RetryLoad,
+    ShowPlaylist(String),
+    ListUserPlaylists(String),
+    PrefetchPlaylist(usize),
+    AddToPlaylist(Option<usize>),
+    ConfirmDownload,
+    CreatePlaylist(String),
+    RenamePlaylist(usize, String),
+    DeletePlaylist(usize),
+    RemoveFromPlaylist(Option<usize>),
+    ShowTimings,
+    ShowLog(usize),
+    Duck(std::time::Duration, f32),
+    SleepTimer(std::time::Duration),
+    SleepAtTrackEnd,
+    ShowSleep,
+    CancelSleep,
+    Status { json: bool, reply: Option<tokio::sync::oneshot::Sender<String>> },
+    SetQuality(api::Quality),
+    SetPaused(bool),
+    MpdQuery(mpd::Query, tokio::sync::oneshot::Sender<String>),
+    TrimStart(std::time::Duration),
+    TrimEnd(std::time::Duration),
+    ClearTrim,
+    Refetch,
+    TakeSnapshot,
+    RestoreSnapshot,
+    LoadFavorites,
+    ResumeLastSession,
+    LoadAlbum(api::AlbumId),
+    OpenLink(link::Link),
+    PickUpQueue,
+    #[cfg_attr(not(feature = "ynison"), allow(dead_code))]
+    YnisonState(ynison::State),
+    ListDevices,
+    TakePlayback,
+    GivePlayback(usize),
+    PushQueue,
+    ListPodcasts,
+    ListAlbums { by_release: bool },
+    LoadLikedAlbum(usize),
+    Browse,
+    StartRadio,
+    LoadBrowsed(usize),
+    LoadPodcast(usize),
+    ShowLyrics,
+    FollowLyrics,
+    ShowCover,
+    SearchLyrics(String),
+    ShowTrackInfo,
+    ShowHistory,
+    ListDownloads,
+    DownloadTrack(usize),
+    SaveTracks { n: Option<usize>, dir: std::path::PathBuf },
+    ExportBundle { dir: std::path::PathBuf, indices: Vec<usize> },
+    Audit(Vec<audit::Category>),
+    ConfirmRemoval,
+    ImportLikes(std::path::PathBuf),
+    ExportQueue { format: export::Format, path: std::path::PathBuf },
+    ExportPlaylists { format: export::Format, dir: std::path::PathBuf },
+    PauseDownload(usize),
+    ResumeDownload(usize),
+    CancelDownload(usize),
+    PrioritizeDownload(usize),
+    CheckAvailability { download: bool },
+    AvailabilityReport(availability::Report),
+    OpenArtist,
+    LoadArtistTracks,
+    LoadArtistAlbum(usize),
+    SetToken(String),
+    ShowLimits,
+    Unlock(String),
+    SwitchOnce(SwitchMode),
+    ListProfiles,
+    SwitchProfile(Option<String>),
+    Quit,
+}
+
+lazy_static::lazy_static! {
+    pub static ref CONFIG: config::Config = config::load().unwrap_or_else(|err| {
+        say!("Using default config: {}", err);
+        config::Config::default()
+    });
+}
+
+fn save_token(token: &str, profile: Option<&str>) {
+    match config::save_token(token, profile) {
+        Ok(location) => say!("Token saved to {}", location),
+        Err(err) => say!("Failed to save the token: {}", err),
+    }
+}
+
+// A new account needs its own client and library, so a whole new player gets built
+async fn open_profile(profile: Option<&str>, headless: bool) -> Result<Player, String> {
+    let token = config::token(&CONFIG, profile)
+        .ok_or("not logged in, run the login command with --profile first")?;
+    let client = YandexClient::new(&token).map_err(|err| err.to_string())?;
+    init_player(&client, &CONFIG, 100, headless).await.map_err(|err| err.to_string())
+}
+
+// Like the availability watcher, states stop coming once the account changes
+#[cfg(feature = "ynison")]
+fn connect_ynison(player: &mut Player, token: &str, account: &Arc<AtomicU64>, tx: &mpsc::UnboundedSender<AppEvent>) {
+    if !CONFIG.ynison.enabled {
+        return;
+    }
+    let (uid, account, tx) = (player.uid(), account.clone(), tx.clone());
+    let link = ynison::connect(token.to_owned(), CONFIG.ynison.device_name.clone(), move |state| {
+        account.load(Ordering::Acquire) == uid.0 && tx.send(AppEvent::YnisonState(state)).is_ok()
+    });
+    player.attach_ynison(link);
+}
+
+#[cfg(not(feature = "ynison"))]
+fn connect_ynison(_: &mut Player, _: &str, _: &Arc<AtomicU64>, _: &mpsc::UnboundedSender<AppEvent>) {}
+
+// A watcher stops once its player's account is no longer the current one
+fn watch_availability(player: &Player, account: &Arc<AtomicU64>, tx: &mpsc::UnboundedSender<AppEvent>) {
+    let Some(hours) = CONFIG.availability.check_every else {
+        return;
+    };
+    let (uid, account, tx) = (player.uid(), account.clone(), tx.clone());
+    availability::watch(
+        player.shared_client(),
+        uid,
+        std::time::Duration::from_secs(hours.max(1) * 60 * 60),
+        move |report| account.load(Ordering::Acquire) == uid.0 && tx.send(AppEvent::AvailabilityReport(report)).is_ok(),
+    );
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &mut std::str::SplitWhitespace) -> Result<T, &'static str> {
+    args.next()
+        .ok_or("Not enough arguments supplied")?
+        .parse::<T>()
+        .map_err(|_| "Invalid argument format")
+}
+
+// Accepts "50%", "+10"/"-10" seconds relative to now, "1:30" or plain seconds
+fn parse_seek(arg: &str) -> Option<AppEvent> {
+    if let Some(percent) = arg.strip_suffix('%') {
+        return percent.parse::<f32>().ok().map(AppEvent::SeekPercent);
+    }
+    if arg.starts_with('+') || arg.starts_with('-') {
+        return arg.parse::<i64>().ok().map(AppEvent::SeekBy);
+    }
+    parse_time(arg).map(AppEvent::Seek)
+}
+
+// "1:30" or plain seconds
+fn parse_time(arg: &str) -> Option<std::time::Duration> {
+    let seconds = match arg.split_once(':') {
+        Some((minutes, seconds)) => minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?,
+        None => arg.parse::<u64>().ok()?,
+    };
+    Some(std::time::Duration::from_secs(seconds))
+}
+
+// What the player last listed, commands taking an index complete to it
+#[derive(Debug, Default)]
+pub struct CompletionData {
+    pub playlists: Vec<String>,
+    pub browsed: Vec<String>,
+    pub eq_presets: Vec<String>,
+}
+
+pub type Completions = Arc<Mutex<CompletionData>>;
+
+// Handed to a frontend once the player is up, commands it parses go to tx
+pub struct Attach {
+    pub tx: mpsc::UnboundedSender<AppEvent>,
+    pub completions: Completions,
+}
+
+pub type Frontend = Box<dyn FnOnce(Attach)>;
+
+// Every command parse_command knows, for completion in the prompt
+pub const COMMANDS: &[&str] = &[
+    "vu", "vd", "vg", "m", "mute", "vs", "vp", "su", "sd", "sg", "ss", "p", "pause", "status",
+    "quality", "trim", "refetch", "seek", "next", "play", "tracks", "share-card", "sort", "filter",
+    "enqueue", "add-local", "prev", "sh", "queue", "library", "n", "N", "skip-played", "announce",
+    "no-repeats", "normalize", "eq", "dislike", "playlists", "playlist", "load-playlist", "retry",
+    "user-playlists", "prefetch-playlist", "add-to-playlist", "confirm-download", "create-playlist",
+    "rename-playlist", "delete-playlist", "remove-from-playlist", "snapshot", "duck", "sleep",
+    "log", "timings", "load-favorites", "radio", "browse", "browse-load", "podcasts",
+    "load-podcast", "albums", "load-liked-album", "load-album", "open", "remote-queue",
+    "push-queue", "devices", "take", "give", "lyrics", "lyrics-follow", "lyrics-search", "cover",
+    "info", "history", "artist", "artist-tracks", "artist-album", "downloads", "download", "export",
+    "export-bundle", "dl-pause", "dl-resume", "dl-cancel", "dl-top", "availability", "audit",
+    "import-likes", "r", "token", "profile", "limits", "unlock", "q",
+];
+
+// Commands taking a secret, kept out of the prompt history and read without echo when given no argument
+pub const SECRET: &[&str] = &["token", "unlock"];
+
+// Commands that replace the queue, they take a trailing now or after overriding switch_mode
+const SWITCHING: &[&str] = &[
+    "load-playlist", "retry", "r", "load-favorites", "radio", "browse-load", "load-podcast", "load-liked-album",
+    "load-album", "artist-tracks", "artist-album", "open",
+];
+
+// Prints what the player reports, for the daemon and the plain prompt
+pub fn print_event(event: Event) {
+    use std::io::Write;
+    match event {
+        Event::Message(line) => println!("{}", line),
+        Event::Art(art) => {
+            let mut out = std::io::stdout().lock();
+            let _ = out.write_all(&art).and_then(|()| out.flush());
+        },
+    }
+}
+
+// Sets what shows the player's output here, attached terminals get it all the same
+pub fn show_events(sink: impl Fn(Event) + Send + Sync + 'static) {
+    events::set_sink(move |event| {
+        control::publish(&event);
+        sink(event);
+    });
+}
+
+pub fn print_parse_error(message: &'static str) {
+    say!("Error parsing input: {}", message);
+}
+
+// Returns whether the command asks to quit
+pub fn parse_command(input: &str, tx: &mpsc::UnboundedSender<AppEvent>, error: impl Fn(&'static str)) -> bool {
+    if let Some(query) = input.trim_start().strip_prefix('/') {
+        match query.trim() {
+            "" => error("Not enough arguments supplied"),
+            query => tx.send(AppEvent::SearchQueue(query.to_owned())).unwrap(),
+        }
+        return false;
+    }
+    let switching = |rest: &str| rest.split_whitespace().next().is_some_and(|command| SWITCHING.contains(&command));
+    let input = match input.trim_end().rsplit_once(char::is_whitespace) {
+        Some((rest, mode @ ("now" | "after"))) if switching(rest) => {
+            let mode = if mode == "now" { SwitchMode::Now } else { SwitchMode::AfterCurrent };
+            tx.send(AppEvent::SwitchOnce(mode)).unwrap();
+            rest
+        },
+        _ => input,
+    };
+    let mut args = input.split_whitespace();
+    let command = args.next().unwrap_or("err");
+    match command {
+        "vu" => {tx.send(AppEvent::ChangeVolume(0.05)).unwrap()},
+        "vd" => {tx.send(AppEvent::ChangeVolume(-0.05)).unwrap()},
+        "vg" => {tx.send(AppEvent::PrintVolume).unwrap()},
+        "m" | "mute" => {tx.send(AppEvent::ToggleMute).unwrap()},
+        "vs" => 'vs : {
+            let Some(string) = args.next() else {
+                error("Not enough arguments supplied"); 
+                break 'vs;
+            };
+            let Ok(value) = string.parse::<f32>() else {
+                error("Invalid argument format");
+                break 'vs;
+            };
+            tx.send(AppEvent::SetVolume(value)).unwrap()
+        },
+        "vp" => match parse_arg(&mut args) {
+            Ok(percent) => tx.send(AppEvent::SetVolumePercent(percent)).unwrap(),
+            Err(message) => error(message),
+        },
+        "su" => {tx.send(AppEvent::ChangeSpeed(0.5)).unwrap()},
+        "sd" => {tx.send(AppEvent::ChangeSpeed(-0.5)).unwrap()},
+        "sg" => {tx.send(AppEvent::PrintSpeed).unwrap()},
+        "ss" => 'ss : {
+            let Some(string) = args.next() else {
+                error("Not enough arguments supplied"); 
+                break 'ss;
+            };
+            let Ok(value) = string.parse::<f32>() else {
+                error("Invalid argument format");
+                break 'ss;
+            };
+            tx.send(AppEvent::SetSpeed(value)).unwrap()
+        },
+        "p" | "pause" => {tx.send(AppEvent::TogglePlayback).unwrap()},
+        "status" => match args.next() {
+            None => tx.send(AppEvent::Status { json: false, reply: None }).unwrap(),
+            Some("--json") => tx.send(AppEvent::Status { json: true, reply: None }).unwrap(),
+            Some(_) => error("Invalid argument format"),
+        },
+        "quality" => {
+            let codec = match args.next() {
+                Some("mp3") => Ok(api::Codec::Mp3),
+                Some("aac") => Ok(api::Codec::Aac),
+                Some(_) => Err("Expected mp3 or aac"),
+                None => Err("Not enough arguments supplied"),
+            };
+            match codec.and_then(|codec| Ok((codec, parse_arg(&mut args)?))) {
+                Ok((codec, bitrate)) => tx.send(AppEvent::SetQuality(api::Quality { codec, bitrate })).unwrap(),
+                Err(message) => error(message),
+            }
+        },
+        "trim" => match (args.next(), args.next().map(parse_time)) {
+            (Some("start"), Some(Some(time))) => tx.send(AppEvent::TrimStart(time)).unwrap(),
+            (Some("end"), Some(Some(time))) => tx.send(AppEvent::TrimEnd(time)).unwrap(),
+            (Some("clear"), None) => tx.send(AppEvent::ClearTrim).unwrap(),
+            (Some("start" | "end"), None) | (None, _) => error("Not enough arguments supplied"),
+            _ => error("Invalid argument format"),
+        },
+        "refetch" => {tx.send(AppEvent::Refetch).unwrap()},
+        "seek" => 'seek : {
+            let Some(string) = args.next() else {
+                error("Not enough arguments supplied"); 
+                break 'seek;
+            };
+            let Some(event) = parse_seek(string) else {
+                error("Invalid argument format");
+                break 'seek;
+            };
+            tx.send(event).unwrap()
+        },
+        digit @ ("0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9") => {
+            let tenths = digit.parse::<f32>().unwrap();
+            tx.send(AppEvent::SeekPercent(tenths * 10.0)).unwrap()
+        },
+        "next" => match args.next().map(str::parse) {
+            None => tx.send(AppEvent::NextTrack).unwrap(),
+            Some(Ok(n)) => tx.send(AppEvent::SkipTracks(n)).unwrap(),
+            Some(Err(_)) => error("Invalid argument format"),
+        },
+        "play" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::PlayIndex(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "tracks" => {tx.send(AppEvent::ShowTracks).unwrap()},
+        "share-card" => match args.next() {
+            None => tx.send(AppEvent::ShareCard { copy: false }).unwrap(),
+            Some("copy") => tx.send(AppEvent::ShareCard { copy: true }).unwrap(),
+            Some(_) => error("Expected copy"),
+        },
+        "sort" => match args.next() {
+            Some("none") => tx.send(AppEvent::Sort(SortKey::Original)).unwrap(),
+            Some("artist") => tx.send(AppEvent::Sort(SortKey::Artist)).unwrap(),
+            Some("album") => tx.send(AppEvent::Sort(SortKey::Album)).unwrap(),
+            Some("title") => tx.send(AppEvent::Sort(SortKey::Title)).unwrap(),
+            Some("duration") => tx.send(AppEvent::Sort(SortKey::Duration)).unwrap(),
+            Some("liked") => tx.send(AppEvent::Sort(SortKey::Liked)).unwrap(),
+            _ => error("Expected none, artist, album, title, duration or liked"),
+        },
+        "filter" => match args.collect::<Vec<_>>().join(" ") {
+            query if query.is_empty() => tx.send(AppEvent::Filter(None)).unwrap(),
+            query => tx.send(AppEvent::Filter(Some(query))).unwrap(),
+        },
+        "add-local" => match args.collect::<Vec<_>>().join(" ") {
+            path if path.is_empty() => error("Not enough arguments supplied"),
+            path => tx.send(AppEvent::AddLocal(path.into())).unwrap(),
+        },
+        "enqueue" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::Enqueue(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "prev" => {tx.send(AppEvent::PrevTrack).unwrap()},
+        "sh" => {tx.send(AppEvent::Shuffle).unwrap()},
+        "queue" => {tx.send(AppEvent::ShowQueue).unwrap()},
+        "library" => {tx.send(AppEvent::ShowLibrary).unwrap()},
+        "n" => {tx.send(AppEvent::SearchNext).unwrap()},
+        "N" => {tx.send(AppEvent::SearchPrev).unwrap()},
+        "skip-played" => {tx.send(AppEvent::ToggleSkipPlayed).unwrap()},
+        "announce" => {tx.send(AppEvent::ToggleAnnounce).unwrap()},
+        "no-repeats" => {tx.send(AppEvent::ToggleNoRepeats).unwrap()},
+        "normalize" => {tx.send(AppEvent::ToggleNormalize).unwrap()},
+        "eq" => match args.next() {
+            None => tx.send(AppEvent::ShowEq).unwrap(),
+            Some(preset) => tx.send(AppEvent::SetEqPreset(preset.to_owned())).unwrap(),
+        },
+        "dislike" => {tx.send(AppEvent::Dislike).unwrap()},
+        "playlists" => {tx.send(AppEvent::ListPlaylists).unwrap()},
+        "playlist" => match args.collect::<Vec<_>>().join(" ") {
+            selector if selector.is_empty() => error("Not enough arguments supplied"),
+            selector => tx.send(AppEvent::ShowPlaylist(selector)).unwrap(),
+        },
+        "load-playlist" => match args.collect::<Vec<_>>().join(" ") {
+            selector if selector.is_empty() => tx.send(AppEvent::LoadPlaylist(None)).unwrap(),
+            selector => tx.send(AppEvent::LoadPlaylist(Some(selector))).unwrap(),
+        },
+        "retry" => {tx.send(AppEvent::RetryLoad).unwrap()},
+        "user-playlists" => match args.next() {
+            Some(owner) => tx.send(AppEvent::ListUserPlaylists(owner.to_owned())).unwrap(),
+            None => error("Not enough arguments supplied"),
+        },
+        "prefetch-playlist" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::PrefetchPlaylist(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "add-to-playlist" => match args.next().map(str::parse) {
+            None => tx.send(AppEvent::AddToPlaylist(None)).unwrap(),
+            Some(Ok(n)) => tx.send(AppEvent::AddToPlaylist(Some(n))).unwrap(),
+            Some(Err(_)) => error("Invalid argument format"),
+        },
+        "confirm-download" => {tx.send(AppEvent::ConfirmDownload).unwrap()},
+        "create-playlist" => match args.collect::<Vec<_>>().join(" ") {
+            title if title.is_empty() => error("Not enough arguments supplied"),
+            title => tx.send(AppEvent::CreatePlaylist(title)).unwrap(),
+        },
+        "rename-playlist" => match parse_arg(&mut args) {
+            Ok(n) => match args.collect::<Vec<_>>().join(" ") {
+                title if title.is_empty() => error("Not enough arguments supplied"),
+                title => tx.send(AppEvent::RenamePlaylist(n, title)).unwrap(),
+            },
+            Err(message) => error(message),
+        },
+        "delete-playlist" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::DeletePlaylist(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "remove-from-playlist" => match args.next().map(str::parse) {
+            None => tx.send(AppEvent::RemoveFromPlaylist(None)).unwrap(),
+            Some(Ok(n)) => tx.send(AppEvent::RemoveFromPlaylist(Some(n))).unwrap(),
+            Some(Err(_)) => error("Invalid argument format"),
+        },
+        "snapshot" => match args.next() {
+            Some("take") => tx.send(AppEvent::TakeSnapshot).unwrap(),
+            Some("restore") => tx.send(AppEvent::RestoreSnapshot).unwrap(),
+            Some(_) => error("Expected take or restore"),
+            None => error("Not enough arguments supplied"),
+        },
+        "duck" => match (parse_arg(&mut args), parse_arg(&mut args)) {
+            (Ok(ms), Ok(level)) => tx.send(AppEvent::Duck(std::time::Duration::from_millis(ms), level)).unwrap(),
+            (Err(message), _) | (_, Err(message)) => error(message),
+        },
+        "sleep" => match args.next() {
+            None => tx.send(AppEvent::ShowSleep).unwrap(),
+            Some("cancel") => tx.send(AppEvent::CancelSleep).unwrap(),
+            Some("end") => tx.send(AppEvent::SleepAtTrackEnd).unwrap(),
+            Some(arg) => match parse_time(arg) {
+                Some(time) => tx.send(AppEvent::SleepTimer(time)).unwrap(),
+                None => error("Invalid argument format"),
+            },
+        },
+        "log" => match args.next().map(str::parse) {
+            None => tx.send(AppEvent::ShowLog(20)).unwrap(),
+            Some(Ok(n)) => tx.send(AppEvent::ShowLog(n)).unwrap(),
+            Some(Err(_)) => error("Invalid argument format"),
+        },
+        "timings" => {tx.send(AppEvent::ShowTimings).unwrap()},
+        "r" => {tx.send(AppEvent::ResumeLastSession).unwrap()},
+        "load-favorites" => {tx.send(AppEvent::LoadFavorites).unwrap()},
+        "radio" => {tx.send(AppEvent::StartRadio).unwrap()},
+        "browse" => {tx.send(AppEvent::Browse).unwrap()},
+        "browse-load" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::LoadBrowsed(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "podcasts" => {tx.send(AppEvent::ListPodcasts).unwrap()},
+        "load-podcast" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::LoadPodcast(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "albums" => match args.next() {
+            None => tx.send(AppEvent::ListAlbums { by_release: false }).unwrap(),
+            Some("date") => tx.send(AppEvent::ListAlbums { by_release: true }).unwrap(),
+            Some(_) => error("Expected date"),
+        },
+        "load-liked-album" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::LoadLikedAlbum(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "remote-queue" => {tx.send(AppEvent::PickUpQueue).unwrap()},
+        "devices" => {tx.send(AppEvent::ListDevices).unwrap()},
+        "take" => {tx.send(AppEvent::TakePlayback).unwrap()},
+        "give" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::GivePlayback(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "push-queue" => {tx.send(AppEvent::PushQueue).unwrap()},
+        "open" => match args.next().map(str::parse::<link::Link>) {
+            Some(Ok(link)) => tx.send(AppEvent::OpenLink(link)).unwrap(),
+            Some(Err(err)) => error(err.as_str()),
+            None => error("Not enough arguments supplied"),
+        },
+        "load-album" => match parse_arg(&mut args) {
+            Ok(id) => tx.send(AppEvent::LoadAlbum(id)).unwrap(),
+            Err(message) => error(message),
+        },
+        "lyrics" => {tx.send(AppEvent::ShowLyrics).unwrap()},
+        "lyrics-search" => match args.collect::<Vec<_>>().join(" ") {
+            phrase if phrase.is_empty() => error("Not enough arguments supplied"),
+            phrase => tx.send(AppEvent::SearchLyrics(phrase)).unwrap(),
+        },
+        "lyrics-follow" => {tx.send(AppEvent::FollowLyrics).unwrap()},
+        "cover" => {tx.send(AppEvent::ShowCover).unwrap()},
+        "info" => {tx.send(AppEvent::ShowTrackInfo).unwrap()},
+        "history" => {tx.send(AppEvent::ShowHistory).unwrap()},
+        "artist" => {tx.send(AppEvent::OpenArtist).unwrap()},
+        "artist-tracks" => {tx.send(AppEvent::LoadArtistTracks).unwrap()},
+        "artist-album" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::LoadArtistAlbum(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "downloads" => {tx.send(AppEvent::ListDownloads).unwrap()},
+        "export-bundle" => match args.next() {
+            Some(dir) => match args.map(str::parse).collect::<Result<Vec<usize>, _>>() {
+                Ok(indices) => tx.send(AppEvent::ExportBundle { dir: dir.into(), indices }).unwrap(),
+                Err(_) => error("Invalid argument format"),
+            },
+            None => error("Not enough arguments supplied"),
+        },
+        "import-likes" => match args.collect::<Vec<_>>().join(" ") {
+            path if path.is_empty() => error("Not enough arguments supplied"),
+            path => tx.send(AppEvent::ImportLikes(path.into())).unwrap(),
+        },
+        "audit" => match (args.next(), args.next()) {
+            (None, _) => tx.send(AppEvent::Audit(Vec::new())).unwrap(),
+            (Some("confirm"), None) => tx.send(AppEvent::ConfirmRemoval).unwrap(),
+            (Some("fix"), Some("all")) => tx.send(AppEvent::Audit(audit::Category::ALL.to_vec())).unwrap(),
+            (Some("fix"), Some(category)) => match category.parse() {
+                Ok(category) => tx.send(AppEvent::Audit(vec![category])).unwrap(),
+                Err(message) => error(message),
+            },
+            _ => error("Usage: audit, audit fix <stale|changed|orphaned|all> or audit confirm"),
+        },
+        // The loaded tracks into a file, or every playlist into a directory
+        "export" => match (args.next(), args.next(), args.next()) {
+            (Some("all"), Some(format), Some(dir)) => match format.parse() {
+                Ok(format) => tx.send(AppEvent::ExportPlaylists { format, dir: dir.into() }).unwrap(),
+                Err(message) => error(message),
+            },
+            (Some(format), Some(path), None) if format != "all" => match format.parse() {
+                Ok(format) => tx.send(AppEvent::ExportQueue { format, path: path.into() }).unwrap(),
+                Err(message) => error(message),
+            },
+            _ => error("Usage: export <m3u|json> <file> or export all <m3u|json> <dir>"),
+        },
+        // A directory saves tagged files there instead of caching
+        "download" => match (args.next(), args.next()) {
+            (Some("all"), Some(dir)) => tx.send(AppEvent::SaveTracks { n: None, dir: dir.into() }).unwrap(),
+            (Some(n), dir) => match (n.parse(), dir) {
+                (Ok(n), Some(dir)) => tx.send(AppEvent::SaveTracks { n: Some(n), dir: dir.into() }).unwrap(),
+                (Ok(n), None) => tx.send(AppEvent::DownloadTrack(n)).unwrap(),
+                (Err(_), _) => error("Invalid argument format"),
+            },
+            (None, _) => error("Not enough arguments supplied"),
+        },
+        "dl-pause" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::PauseDownload(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "dl-resume" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::ResumeDownload(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "dl-cancel" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::CancelDownload(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "dl-top" => match parse_arg(&mut args) {
+            Ok(n) => tx.send(AppEvent::PrioritizeDownload(n)).unwrap(),
+            Err(message) => error(message),
+        },
+        "availability" => match args.next() {
+            None => tx.send(AppEvent::CheckAvailability { download: false }).unwrap(),
+            Some("download") => tx.send(AppEvent::CheckAvailability { download: true }).unwrap(),
+            Some(_) => error("Expected download"),
+        },
+        "limits" => {tx.send(AppEvent::ShowLimits).unwrap()},
+        "unlock" => match args.next() {
+            Some(code) => tx.send(AppEvent::Unlock(code.to_owned())).unwrap(),
+            None => error("Not enough arguments supplied"),
+        },
+        "profile" => match args.next() {
+            None => tx.send(AppEvent::ListProfiles).unwrap(),
+            Some("default") => tx.send(AppEvent::SwitchProfile(None)).unwrap(),
+            Some(name) => tx.send(AppEvent::SwitchProfile(Some(name.to_owned()))).unwrap(),
+        },
+        "token" => match args.next() {
+            Some(token) => tx.send(AppEvent::SetToken(token.to_owned())).unwrap(),
+            None => error("Not enough arguments supplied"),
+        },
+        "q" => {
+            tx.send(AppEvent::Quit).unwrap();
+            return true;
+        },
+        _ => {
+            error("Unknown command");
+        },
+    };
+
+    false
+}
+
+// Hands the command line over to the player that is already running instead of opening a second audio session
+async fn forward(args: &cli::Args) {
+    let commands = cli::forwarded_commands(args);
+    if commands.is_empty() {
+        say!("The player is already running, control it with the ctl command");
+    }
+    for command in commands {
+        match control::send(&command).await {
+            Ok(reply) if reply == "ok" => {}
+            Ok(reply) => say!("{}", reply),
+            Err(err) => {
+                say!("Failed to reach the player: {}", err);
+                return;
+            }
+        }
+    }
+}
+
+// Runs the player until it quits, headless without a frontend or with the daemon subcommand
+pub async fn run(args: cli::Args, frontend: Option<Frontend>) {
+    show_events(print_event);
+    if let Some(cli::Command::Ctl { command }) = &args.command {
+        match control::send(&command.join(" ")).await {
+            Ok(reply) => say!("{}", reply),
+            Err(err) => say!("Failed to reach the player: {}", err),
+        }
+        return;
+    }
+    if let Some(cli::Command::Attach { .. }) = &args.command {
+        say!("Attaching needs the terminal interface, run yandex_music_tui attach");
+        return;
+    }
+    if let Some(cli::Command::Login) = &args.command {
+        login::run(&CONFIG.login, args.profile.as_deref()).await;
+        return;
+    }
+
+    let timings = timings::Timings::default();
+    let log = logging::init(&CONFIG.log_filter, timings.clone());
+
+    // Downloads don't touch the audio output, so they may run next to the player
+    let _lock = match &args.command {
+        Some(cli::Command::Download { .. }) => None,
+        _ => match control::lock() {
+            Ok(Some(lock)) => Some(lock),
+            Ok(None) => {
+                forward(&args).await;
+                return;
+            }
+            Err(err) => {
+                tracing::warn!("Failed to take the instance lock: {}", err);
+                None
+            }
+        },
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    if let Err(err) = control::serve(tx.clone()) {
+        tracing::warn!("Control socket is unavailable: {}", err);
+    }
+    if let Some(address) = &CONFIG.mpd_address {
+        if let Err(err) = mpd::serve(address, tx.clone()).await {
+            tracing::error!("Failed to start the MPD server on {}: {}", address, err);
+        }
+    }
+
+    let completions = Completions::default();
+    completions.lock().unwrap().eq_presets = eq::preset_names(&CONFIG.equalizer.presets)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    let daemon = frontend.is_none() || matches!(args.command, Some(cli::Command::Daemon));
+    let mut profile = args.profile.clone();
+    let token = match config::token(&CONFIG, profile.as_deref()) {
+        Some(token) => token,
+        None if daemon => {
+            say!("Not logged in, run the login command first");
+            return;
+        },
+        None => match login::run(&CONFIG.login, profile.as_deref()).await {
+            Some(token) => token,
+            None => return,
+        },
+    };
+    let mut client = match YandexClient::new(&token) {
+        Ok(client) => client,
+        Err(err) => {
+            say!("Failed to create a client: {}", err);
+            return;
+        },
+    };
+    if let Some(command @ cli::Command::Download { .. }) = &args.command {
+        if let Err(err) = cli::download(command, &client, &CONFIG).await {
+            say!("Failed to download: {}", err);
+        }
+        return;
+    }
+    let mut player = loop {
+        match init_player(&client, &CONFIG, 100, args.headless).await {
+            Ok(player) => break player,
+            Err(api::ApiError::Unauthorized(message)) => {
+                say!("Yandex rejected the token: {}", message);
+                if daemon {
+                    say!("Run the login command to get a new token");
+                    return;
+                }
+                let Some(token) = login::run(&CONFIG.login, profile.as_deref()).await else {
+                    return;
+                };
+                match YandexClient::new(&token) {
+                    Ok(authorized) => client = authorized,
+                    Err(err) => say!("Failed to create a client: {}", err),
+                }
+            },
+            Err(err) => {
+                say!("Failed to start: {}", err);
+                return;
+            },
+        }
+    };
+
+    let account = Arc::new(AtomicU64::new(player.uid().0));
+    watch_availability(&player, &account, &tx);
+    connect_ynison(&mut player, &token, &account, &tx);
+
+    if let Some(frontend) = frontend.filter(|_| !daemon) {
+        frontend(Attach { tx: tx.clone(), completions: completions.clone() });
+    }
+
+    let mut rng = thread_rng();
+    let mut accelerator = bindings::Accelerator::default();
+    if let Err(err) = cli::apply(&args, &mut player, &mut rng).await {
+        say!("Failed to set up the initial queue: {}", err);
+    }
+    if let Some(banner) = player.last_session_banner() {
+        say!("{}", banner);
+    }
+    // The selector of the last playlist that failed to load, for retry
+    // The last load that failed, retry sends it again
+    let mut failed_load: Option<AppEvent> = None;
+    'app: loop {
+        update_player(&mut player).await;
+        let first = tokio::select! {
+            Some(event) = rx.recv() => Some(event),
+            _ = player.wait() => None,
+        };
+        for event in first.into_iter().chain(std::iter::from_fn(|| rx.try_recv().ok())) {
+            match event {
+                AppEvent::ChangeVolume(volume) => {
+                    let key = if volume < 0.0 { "volume-down" } else { "volume-up" };
+                    player.change_volume(volume * accelerator.factor(key))
+                },
+                AppEvent::SetVolume(volume) => { player.set_volume(volume) },
+                AppEvent::SetVolumePercent(percent) => { player.set_volume_percent(percent) },
+                AppEvent::ToggleMute => {
+                    if player.toggle_mute() {
+                        say!("Muted");
+                    } else {
+                        say!("Unmuted, volume {:.0}%", player.volume_percent());
+                    }
+                },
+                AppEvent::PrintVolume => { say!("Current volume: {:.0}%", player.volume_percent()) },
+                AppEvent::ChangeSpeed(speed) => { player.change_speed(speed) },
+                AppEvent::SetSpeed(speed) => { player.change_speed(speed - player.speed()) },
+                AppEvent::PrintSpeed => { say!("Current speed: {}", player.speed()) },
+                AppEvent::TogglePlayback => { player.toggle_playback() },
+                AppEvent::Seek(position) => { player.seek(position) },
+                AppEvent::SeekPercent(percent) => { player.seek_percent(percent) },
+                AppEvent::SeekBy(seconds) => {
+                    let key = if seconds < 0 { "seek-back" } else { "seek-forward" };
+                    player.seek_by((seconds as f32 * accelerator.factor(key)) as i64)
+                },
+                AppEvent::NextTrack => { player.move_next() },
+                AppEvent::SkipTracks(n) => { player.skip(n) },
+                AppEvent::PlayIndex(n) => { player.play_index(n) },
+                AppEvent::ShowTracks => { player.print_tracks() },
+                AppEvent::ShareCard { copy } => {
+                    if let Err(err) = share_card(&player, copy).await {
+                        say!("Failed to fetch cover: {}", err);
+                    }
+                },
+                AppEvent::Filter(query) => { player.set_filter(query) },
+                AppEvent::Sort(key) => { player.set_sort(key) },
+                AppEvent::Enqueue(n) => { player.enqueue(n) },
+                AppEvent::AddLocal(path) => { player.add_local(&path) },
+                AppEvent::PrevTrack => { player.move_prev() },
+                AppEvent::ListPlaylists => match list_playlists(&player).await {
+                    Ok(titles) => completions.lock().unwrap().playlists = titles,
+                    Err(err) => say!("Failed to load playlists: {}", err),
+                },
+                AppEvent::ShowPlaylist(selector) => {
+                    if let Err(err) = show_playlist(&mut player, &selector).await {
+                        say!("Failed to load the playlist: {}", err);
+                    }
+                },
+                AppEvent::LoadPlaylist(selector) => {
+                    let result = match select_playlist(&player, selector.as_deref()).await {
+                        Ok(Some(playlist)) => {
+                            say!("Loading {}", playlist.title);
+                            load_playlist_into_player(&mut player, &playlist).await
+                        },
+                        Ok(None) => Ok(()),
+                        Err(err) => Err(err),
+                    };
+                    match result {
+                        Ok(()) => failed_load = None,
+                        Err(err) if CONFIG.strict_loading => {
+                            say!("Failed to load the playlist: {}", err);
+                            player.remember_position();
+                            player.remember_levels();
+                            player.save_listened();
+                            break 'app;
+                        },
+                        Err(err) => {
+                            say!("Failed to load the playlist, the current queue keeps playing. Type retry to try again: {}", err);
+                            failed_load = Some(AppEvent::LoadPlaylist(selector));
+                        },
+                    }
+                },
+                AppEvent::RetryLoad => match failed_load.take() {
+                    Some(event) => tx.send(event).unwrap(),
+                    None => say!("No failed load to retry"),
+                },
+                AppEvent::ListUserPlaylists(owner) => {
+                    if let Err(err) = list_user_playlists(&player, &owner).await {
+                        say!("Failed to list playlists: {}", err);
+                    }
+                },
+                AppEvent::PrefetchPlaylist(n) => {
+                    let result = match playlists(&player).await {
+                        Ok(playlists) => match playlists.get(n) {
+                            Some(playlist) => {
+                                say!("Prefetching {}", playlist.title);
+                                prefetch_playlist(&mut player, playlist).await
+                            },
+                            None => {
+                                say!("No playlist with index {}", n);
+                                Ok(())
+                            },
+                        },
+                        Err(err) => Err(err),
+                    };
+                    if let Err(err) = result {
+                        say!("Failed to prefetch playlist: {}", err);
+                    }
+                },
+                AppEvent::AddToPlaylist(n) => {
+                    if let Err(err) = add_current_to_playlist(&player, n).await {
+                        say!("Failed to add track to playlist: {}", err);
+                    }
+                },
+                AppEvent::ConfirmDownload => { player.confirm_downloads() },
+                AppEvent::CreatePlaylist(title) => {
+                    if let Err(err) = create_playlist(&player, &title).await {
+                        say!("Failed to create playlist: {}", err);
+                    }
+                },
+                AppEvent::RenamePlaylist(n, title) => {
+                    if let Err(err) = rename_playlist(&player, n, &title).await {
+                        say!("Failed to rename playlist: {}", err);
+                    }
+                },
+                AppEvent::DeletePlaylist(n) => {
+                    if let Err(err) = delete_playlist(&player, n).await {
+                        say!("Failed to delete playlist: {}", err);
+                    }
+                },
+                AppEvent::RemoveFromPlaylist(n) => {
+                    if let Err(err) = remove_from_playlist(&mut player, n).await {
+                        say!("Failed to remove track from playlist: {}", err);
+                    }
+                },
+                AppEvent::TakeSnapshot => { player.take_snapshot() },
+                AppEvent::RestoreSnapshot => { player.restore_snapshot() },
+                AppEvent::Status { json, reply } => {
+                    let status = if json { player.status_line().to_json() } else { player.status() };
+                    match reply {
+                        Some(reply) => {
+                            let _ = reply.send(status);
+                        },
+                        None => say!("{}", status),
+                    }
+                },
+                AppEvent::SetQuality(quality) => { player.set_quality(quality) },
+                AppEvent::SetPaused(paused) => { player.set_paused(paused) },
+                AppEvent::TrimStart(time) => { player.trim_current(|trim| trim.start = Some(time)) },
+                AppEvent::TrimEnd(time) => { player.trim_current(|trim| trim.end = Some(time)) },
+                AppEvent::ClearTrim => { player.trim_current(|trim| *trim = trims::Trim::default()) },
+                AppEvent::MpdQuery(query, reply) => {
+                    let _ = reply.send(mpd::answer(&player, query));
+                },
+                AppEvent::Refetch => {
+                    if let Err(err) = refetch_current_track(&mut player).await {
+                        say!("Failed to refetch track: {}", err);
+                    }
+                },
+                AppEvent::Duck(hold, level) => { player.duck(hold, level) },
+                AppEvent::SleepTimer(after) => { player.set_sleep(after) },
+                AppEvent::SleepAtTrackEnd => { player.sleep_at_track_end() },
+                AppEvent::ShowSleep => { player.print_sleep() },
+                AppEvent::CancelSleep => { player.cancel_sleep() },
+                AppEvent::ShowLog(lines) => { log.print(lines) },
+                AppEvent::ShowTimings => { timings.print() },
+                AppEvent::ResumeLastSession => {
+                    if let Err(err) = resume_last_session(&mut player).await {
+                        say!("Failed to resume the last session: {}", err);
+                    }
+                },
+                AppEvent::LoadFavorites => match load_favorites_into_player(&mut player).await {
+                    Ok(()) => failed_load = None,
+                    Err(err) if CONFIG.strict_loading => {
+                        say!("Failed to load liked tracks: {}", err);
+                        player.remember_position();
+                        player.remember_levels();
+                        player.save_listened();
+                        break 'app;
+                    },
+                    Err(err) => {
+                        say!("Failed to load liked tracks, the current queue keeps playing. Type retry to try again: {}", err);
+                        failed_load = Some(AppEvent::LoadFavorites);
+                    },
+                },
+                AppEvent::YnisonState(state) => {
+                    if let Err(err) = follow_ynison(&mut player, state).await {
+                        say!("Failed to follow another device: {}", err);
+                    }
+                },
+                AppEvent::ListDevices => { player.print_devices() },
+                AppEvent::TakePlayback => {
+                    if let Err(err) = take_playback(&mut player).await {
+                        say!("Failed to take playback over: {}", err);
+                    }
+                },
+                AppEvent::GivePlayback(n) => { player.give_playback(n) },
+                AppEvent::PickUpQueue => {
+                    if let Err(err) = pick_up_queue(&mut player, None).await {
+                        say!("Failed to pick up the queue: {}", err);
+                    }
+                },
+                AppEvent::PushQueue => {
+                    if let Err(err) = push_queue(&mut player).await {
+                        say!("Failed to share the queue: {}", err);
+                    }
+                },
+                AppEvent::OpenLink(link) => {
+                    if let Err(err) = open_link(&mut player, link).await {
+                        say!("Failed to open link: {}", err);
+                    }
+                },
+                AppEvent::LoadAlbum(id) => {
+                    if let Err(err) = load_album_into_player(&mut player, id).await {
+                        say!("Failed to load album: {}", err);
+                    }
+                },
+                AppEvent::StartRadio => {
+                    if let Err(err) = start_track_radio(&mut player).await {
+                        say!("Failed to start radio: {}", err);
+                    }
+                },
+                AppEvent::Browse => {
+                    if let Err(err) = browse_landing(&mut player).await {
+                        say!("Failed to load recommendations: {}", err);
+                    }
+                    completions.lock().unwrap().browsed = player.landing_titles();
+                },
+                AppEvent::LoadBrowsed(n) => {
+                    if let Err(err) = load_landing_into_player(&mut player, n).await {
+                        say!("Failed to load recommendation: {}", err);
+                    }
+                },
+                AppEvent::ListPodcasts => {
+                    if let Err(err) = list_podcasts(&mut player).await {
+                        say!("Failed to load podcasts: {}", err);
+                    }
+                },
+                AppEvent::ListAlbums { by_release } => {
+                    if let Err(err) = list_liked_albums(&mut player, by_release).await {
+                        say!("Failed to load albums: {}", err);
+                    }
+                },
+                AppEvent::LoadLikedAlbum(n) => {
+                    if let Err(err) = load_liked_album_into_player(&mut player, n).await {
+                        say!("Failed to load album: {}", err);
+                    }
+                },
+                AppEvent::LoadPodcast(n) => {
+                    if let Err(err) = load_podcast_into_player(&mut player, n).await {
+                        say!("Failed to load podcast: {}", err);
+                    }
+                },
+                AppEvent::ShowLyrics => {
+                    if let Err(err) = show_lyrics(&mut player).await {
+                        say!("Failed to fetch lyrics: {}", err);
+                    }
+                },
+                AppEvent::FollowLyrics => {
+                    if player.toggle_lyrics_follow() {
+                        say!("Following lyrics");
+                    } else {
+                        say!("Stopped following lyrics");
+                    }
+                },
+                AppEvent::ShowCover => {
+                    let mode = match CONFIG.cover_art {
+                        art::ArtMode::Off => art::ArtMode::Auto,
+                        mode => mode,
+                    };
+                    if let Err(err) = show_cover(&player, mode).await {
+                        say!("Failed to fetch cover: {}", err);
+                    }
+                },
+                AppEvent::SearchLyrics(phrase) => {
+                    if let Err(err) = search_lyrics(&mut player, &phrase).await {
+                        say!("Failed to search lyrics: {}", err);
+                    }
+                },
+                AppEvent::ShowHistory => { player.print_history() },
+                AppEvent::ShowTrackInfo => {
+                    if let Err(err) = show_track_info(&player).await {
+                        say!("Failed to check like status: {}", err);
+                    }
+                },
+                AppEvent::OpenArtist => {
+                    if let Err(err) = open_current_artist(&mut player).await {
+                        say!("Failed to load artist: {}", err);
+                    }
+                },
+                AppEvent::LoadArtistTracks => {
+                    if let Err(err) = load_artist_tracks_into_player(&mut player).await {
+                        say!("Failed to load artist tracks: {}", err);
+                    }
+                },
+                AppEvent::LoadArtistAlbum(n) => {
+                    if let Err(err) = load_artist_album_into_player(&mut player, n).await {
+                        say!("Failed to load album: {}", err);
+                    }
+                },
+                AppEvent::ListDownloads => { player.downloads().print() },
+                AppEvent::DownloadTrack(n) => { player.download_track(n) },
+                AppEvent::SaveTracks { n, dir } => { player.save_tracks(n, &dir).await },
+                AppEvent::ExportBundle { dir, indices } => { player.export_bundle(&dir, &indices) },
+                AppEvent::ImportLikes(path) => {
+                    if let Err(err) = import_likes(&player, &path).await {
+                        say!("Failed to import likes: {}", err);
+                    }
+                },
+                AppEvent::Audit(fix) => {
+                    if let Err(err) = audit(&mut player, &fix).await {
+                        say!("Failed to audit the library: {}", err);
+                    }
+                },
+                AppEvent::ConfirmRemoval => { player.confirm_removal() },
+                AppEvent::ExportQueue { format, path } => { player.export_queue(format, &path) },
+                AppEvent::ExportPlaylists { format, dir } => {
+                    if let Err(err) = export_playlists(&player, format, &dir).await {
+                        say!("Failed to export playlists: {}", err);
+                    }
+                },
+                AppEvent::PauseDownload(n) => {
+                    if !player.downloads().pause(n) {
+                        say!("No download with index {}", n);
+                    }
+                },
+                AppEvent::ResumeDownload(n) => {
+                    if !player.downloads().resume(n) {
+                        say!("No download with index {}", n);
+                    }
+                },
+                AppEvent::CancelDownload(n) => {
+                    if !player.downloads().cancel(n) {
+                        say!("No download with index {}", n);
+                    }
+                },
+                AppEvent::PrioritizeDownload(n) => {
+                    if !player.downloads().move_to_front(n) {
+                        say!("No download with index {}", n);
+                    }
+                },
+                AppEvent::CheckAvailability { download } => {
+                    if let Err(err) = check_availability(&mut player, download).await {
+                        say!("Failed to check track availability: {}", err);
+                    }
+                },
+                AppEvent::AvailabilityReport(report) => { player.report_availability(report, false) },
+                AppEvent::Shuffle => { player.shuffle_tracks(&mut rng) },
+                AppEvent::ShowQueue => { player.print_queue() },
+                AppEvent::ShowLibrary => { player.print_library() },
+                AppEvent::SearchQueue(query) => { player.search_queue(query) },
+                AppEvent::SearchNext => { player.search_next(true) },
+                AppEvent::SearchPrev => { player.search_next(false) },
+                AppEvent::ToggleSkipPlayed => {
+                    if player.toggle_skip_played() {
+                        say!("Skipping already played tracks in shuffle mode");
+                    } else {
+                        say!("Playing every track in shuffle mode");
+                    }
+                },
+                AppEvent::ToggleAnnounce => {
+                    if player.toggle_announce() {
+                        say!("Announcing tracks as they start");
+                    } else {
+                        say!("Track announcements off");
+                    }
+                },
+                AppEvent::Dislike => {
+                    if let Err(err) = dislike_current_track(&mut player).await {
+                        say!("Failed to report dislike: {}", err);
+                    }
+                },
+                AppEvent::ShowEq => { player.print_eq() },
+                AppEvent::SetEqPreset(preset) => { player.set_eq_preset(&preset) },
+                AppEvent::ToggleNormalize => {
+                    if player.toggle_normalize() {
+                        say!("Normalizing track loudness");
+                    } else {
+                        say!("Playing tracks at their own loudness");
+                    }
+                },
+                AppEvent::ToggleNoRepeats => {
+                    if player.toggle_no_repeats() {
+                        say!("Not repeating tracks played this session until everything else has played");
+                    } else {
+                        say!("Allowing repeats");
+                    }
+                },
+                AppEvent::SetToken(token) => {
+                    let result = match YandexClient::new(&token) {
+                        Ok(client) => player.reauthorize(&client).await,
+                        Err(err) => Err(err.into()),
+                    };
+                    match result {
+                        Ok(true) => {
+                            save_token(&token, profile.as_deref());
+                            connect_ynison(&mut player, &token, &account, &tx);
+                        },
+                        Ok(false) => {},
+                        Err(err) => say!("The token doesn't work: {}", err),
+                    }
+                },
+                AppEvent::SwitchOnce(mode) => { player.switch_once(mode) },
+                AppEvent::ShowLimits => { player.print_limits() },
+                AppEvent::Unlock(code) => { player.unlock(&code) },
+                AppEvent::ListProfiles => {
+                    let current = profile.as_deref().unwrap_or("default");
+                    for name in std::iter::once("default").chain(CONFIG.profiles.keys().map(String::as_str)) {
+                        let marker = if name == current { "▶" } else { " " };
+                        say!("{} {}", marker, name);
+                    }
+                },
+                AppEvent::SwitchProfile(name) => {
+                    if name.as_ref().is_some_and(|name| !CONFIG.profiles.contains_key(name)) {
+                        say!("No profile named {} in the config", name.unwrap());
+                        continue;
+                    }
+                    player.remember_position();
+                    player.remember_levels();
+                    player.save_listened();
+                    match open_profile(name.as_deref(), args.headless).await {
+                        Ok(opened) => {
+                            player = opened;
+                            profile = name;
+                            account.store(player.uid().0, Ordering::Release);
+                            watch_availability(&player, &account, &tx);
+                            if let Some(token) = config::token(&CONFIG, profile.as_deref()) {
+                                connect_ynison(&mut player, &token, &account, &tx);
+                            }
+                            say!("Switched to {}", profile.as_deref().unwrap_or("the default profile"));
+                        },
+                        Err(err) => say!("Failed to switch profiles: {}", err),
+                    }
+                },
+                AppEvent::Quit => {
+                    player.remember_position();
+                    player.remember_levels();
+                    player.save_listened();
+                    break 'app
+                },
+            }
+        }
+    }
+}
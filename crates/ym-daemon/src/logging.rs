@@ -0,0 +1,111 @@
+use ym_core::config;
+use ym_core::say;
+
+use std::collections::VecDeque;
+use std::fs::{
+    File,
+    OpenOptions,
+};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use tracing_subscriber::{
+    fmt,
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter,
+    Layer,
+};
+
+const KEPT_LINES: usize = 500;
+
+pub fn log_path() -> PathBuf {
+    config::data_dir().join("yandex_music_tui.log")
+}
+
+// Every formatted event goes to the log file and to the recent lines shown by the log command
+#[derive(Clone)]
+pub struct LogWriter {
+    file: Option<Arc<Mutex<File>>>,
+    recent: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LogWriter {
+    fn open() -> Self {
+        let path = log_path();
+        let file = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+        if let Err(err) = &file {
+            say!("Failed to open log file {}: {}", path.display(), err);
+        }
+
+        LogWriter {
+            file: file.ok().map(|file| Arc::new(Mutex::new(file))),
+            recent: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn print(&self, lines: usize) {
+        let recent = self.recent.lock().unwrap();
+        if recent.is_empty() {
+            say!("Nothing logged yet");
+        }
+        for line in recent.iter().skip(recent.len().saturating_sub(lines)) {
+            say!("{}", line);
+        }
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(file) = &self.file {
+            file.lock().unwrap().write_all(buf)?;
+        }
+
+        let mut recent = self.recent.lock().unwrap();
+        for line in String::from_utf8_lossy(buf).lines() {
+            if recent.len() == KEPT_LINES {
+                recent.pop_front();
+            }
+            recent.push_back(line.to_owned());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &self.file {
+            Some(file) => file.lock().unwrap().flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+// RUST_LOG takes precedence over the configured filter, both use the EnvFilter syntax
+pub fn init(filter: &str, layer: impl Layer<tracing_subscriber::Registry> + Send + Sync) -> LogWriter {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(filter))
+        .unwrap_or_else(|err| {
+            say!("Invalid log filter {}: {}", filter, err);
+            EnvFilter::new("info")
+        });
+
+    let writer = LogWriter::open();
+    let make_writer = writer.clone();
+    tracing_subscriber::registry()
+        .with(layer)
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || make_writer.clone())
+                .with_filter(filter)
+        )
+        .init();
+
+    writer
+}
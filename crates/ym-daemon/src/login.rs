@@ -0,0 +1,118 @@
+use ym_core::api::{
+    account_status,
+    YandexClient,
+};
+use ym_core::config::{
+    self,
+    LoginConfig,
+};
+
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+    net::TcpListener,
+};
+
+// The implicit flow puts the token in the fragment, which never reaches the server,
+// so the landing page hands it over in a second request
+const LANDING_PAGE: &str = "<script>location.replace('/token?' + location.hash.slice(1))</script>";
+const DONE_PAGE: &str = "Logged in, you can close this tab";
+
+fn authorize_url(config: &LoginConfig) -> String {
+    let mut url = format!("https://oauth.yandex.ru/authorize?response_type=token&client_id={}", config.client_id);
+    if let Some(port) = config.redirect_port {
+        url += &format!("&redirect_uri=http://localhost:{}/", port);
+    }
+    url
+}
+
+// Takes either the bare token or the whole address the browser ended up on
+fn extract_token(input: &str) -> Option<String> {
+    let input = input.trim();
+    let token = match input.split_once("access_token=") {
+        Some((_, rest)) => rest.split('&').next().unwrap_or_default(),
+        None => input,
+    };
+    Some(token.to_owned()).filter(|token| !token.is_empty())
+}
+
+fn open_in_browser(url: &str) {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let opened = std::process::Command::new(opener)
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !opened {
+        println!("Open it in a browser yourself");
+    }
+}
+
+async fn catch_redirect(port: u16) -> std::io::Result<Option<String>> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut request = vec![0; 8192];
+        let read = stream.read(&mut request).await?;
+        let request = String::from_utf8_lossy(&request[..read]);
+        let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+        let token = path.strip_prefix("/token?").and_then(extract_token);
+        let page = if token.is_some() { DONE_PAGE } else { LANDING_PAGE };
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            page.len(),
+            page,
+        );
+        stream.write_all(response.as_bytes()).await?;
+        if token.is_some() {
+            return Ok(token);
+        }
+    }
+}
+
+fn read_pasted() -> Option<String> {
+    println!("Log in, then paste the address the browser ended up on (or just the token), an empty line quits:");
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).ok()?;
+    extract_token(&line)
+}
+
+// Walks through the OAuth flow until Yandex accepts a token, which is then saved
+pub async fn run(config: &LoginConfig, profile: Option<&str>) -> Option<String> {
+    let url = authorize_url(config);
+    println!("Log in to Yandex at {}", url);
+    open_in_browser(&url);
+
+    loop {
+        let token = match config.redirect_port {
+            Some(port) => match catch_redirect(port).await {
+                Ok(token) => token,
+                Err(err) => {
+                    println!("Failed to listen on port {}: {}", port, err);
+                    read_pasted()
+                },
+            },
+            None => read_pasted(),
+        }?;
+
+        let status = match YandexClient::new(&token) {
+            Ok(client) => account_status(&client).await.map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        match status {
+            Ok(account) => {
+                println!("Logged in as {}", account.login);
+                match config::save_token(&token, profile) {
+                    Ok(location) => println!("Token saved to {}", location),
+                    Err(err) => println!("Failed to save the token: {}", err),
+                }
+                return Some(token);
+            },
+            Err(err) => println!("The token doesn't work: {}", err),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use clap::Parser;
+
+// Headless, the player takes commands from the control socket and MPD clients only
+#[tokio::main]
+async fn main() {
+    ym_daemon::run(ym_daemon::cli::Args::parse(), None).await;
+}
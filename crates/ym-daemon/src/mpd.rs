@@ -0,0 +1,260 @@
+use ym_core::player::Player;
+use crate::{
+    AppEvent,
+    CONFIG,
+};
+
+use std::fmt::Write as _;
+use tokio::sync::mpsc;
+
+use tokio::{
+    io::{
+        AsyncBufReadExt,
+        AsyncWriteExt,
+        BufReader,
+    },
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+    sync::oneshot,
+};
+
+const GREETING: &str = "OK MPD 0.23.0\n";
+
+// Error codes of the ACK lines, as MPD numbers them
+const ACK_PASSWORD: u8 = 3;
+const ACK_PERMISSION: u8 = 4;
+const ACK_UNKNOWN: u8 = 5;
+
+// Answers that need the player state are computed in the main loop
+#[derive(Debug, Clone, Copy)]
+pub enum Query {
+    Status,
+    CurrentSong,
+    PlaylistInfo,
+}
+
+pub fn answer(player: &Player, query: Query) -> String {
+    let mut out = String::new();
+    match query {
+        Query::Status => {
+            let state = match (player.current_index(), player.is_paused()) {
+                (None, _) => "stop",
+                (Some(_), true) => "pause",
+                (Some(_), false) => "play",
+            };
+            let _ = writeln!(out, "volume: {}", player.volume_percent().round() as i32);
+            let _ = writeln!(out, "repeat: 0\nrandom: {}\nsingle: 0\nconsume: 0", player.is_shuffled() as u8);
+            let _ = writeln!(out, "playlist: 1\nplaylistlength: {}", player.queue().len());
+            let _ = writeln!(out, "state: {}", state);
+            if let Some(n) = player.current_index() {
+                let _ = writeln!(out, "song: {}\nsongid: {}", n, n);
+            }
+            if let Some(position) = player.position() {
+                let _ = writeln!(out, "elapsed: {:.3}", position.as_secs_f64());
+                if let Some(duration) = player.duration() {
+                    let _ = writeln!(out, "duration: {:.3}", duration.as_secs_f64());
+                    let _ = writeln!(out, "time: {}:{}", position.as_secs(), duration.as_secs());
+                }
+            }
+        },
+        Query::CurrentSong => {
+            let queue = player.queue();
+            if let Some((n, track)) = queue.current().and_then(|n| Some((n, queue.get(n)?))) {
+                write_song(&mut out, n, track);
+            }
+        },
+        Query::PlaylistInfo => {
+            for (n, track) in player.queue().iter().enumerate() {
+                write_song(&mut out, n, track);
+            }
+        },
+    }
+    out
+}
+
+fn write_song(out: &mut String, n: usize, track: &ym_core::api::Track) {
+    let artist = track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", ");
+    let _ = writeln!(out, "file: yandex:{}", track.id);
+    let _ = writeln!(out, "Title: {}\nArtist: {}", track.full_title(), artist);
+    if let Some(album) = track.albums.first() {
+        let _ = writeln!(out, "Album: {}", album.title);
+    }
+    if let Some(duration) = track.duration {
+        let _ = writeln!(out, "Time: {}\nduration: {:.3}", duration / 1000, duration as f64 / 1000.0);
+    }
+    let _ = writeln!(out, "Pos: {}\nId: {}", n, n);
+}
+
+pub async fn serve(address: &str, tx: mpsc::UnboundedSender<AppEvent>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    if CONFIG.mpd_password.is_none() && !listener.local_addr()?.ip().is_loopback() {
+        tracing::warn!("The MPD server on {} takes commands from anyone who can reach it, set mpd_password", address);
+    }
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_client(stream, tx.clone()));
+                },
+                Err(err) => tracing::error!("Failed to accept an MPD connection: {}", err),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+// Splits on whitespace, keeping "quoted arguments" with backslash escapes together
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut token = String::new();
+        if c == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => token.extend(chars.next()),
+                    c => token.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+async fn query(tx: &mpsc::UnboundedSender<AppEvent>, query: Query) -> Result<String, String> {
+    let (sender, receiver) = oneshot::channel();
+    tx.send(AppEvent::MpdQuery(query, sender)).map_err(|_| "player has quit".to_owned())?;
+    receiver.await.map_err(|_| "player has quit".to_owned())
+}
+
+// Returns the response body, the trailing OK is added by the caller
+async fn execute(tokens: &[String], tx: &mpsc::UnboundedSender<AppEvent>) -> Result<String, String> {
+    let Some(command) = tokens.first() else {
+        return Err("No command given".to_owned());
+    };
+    let arg = tokens.get(1).map(String::as_str);
+    let send = |event| tx.send(event).map(|_| String::new()).map_err(|_| "player has quit".to_owned());
+    match command.as_str() {
+        "status" => query(tx, Query::Status).await,
+        "currentsong" => query(tx, Query::CurrentSong).await,
+        "playlistinfo" => query(tx, Query::PlaylistInfo).await,
+        "play" => send(AppEvent::SetPaused(false)),
+        "stop" => send(AppEvent::SetPaused(true)),
+        "pause" => match arg {
+            Some("1") => send(AppEvent::SetPaused(true)),
+            Some("0") => send(AppEvent::SetPaused(false)),
+            _ => send(AppEvent::TogglePlayback),
+        },
+        "next" => send(AppEvent::NextTrack),
+        "previous" => send(AppEvent::PrevTrack),
+        "setvol" => match arg.and_then(|arg| arg.parse::<f32>().ok()) {
+            Some(volume) => send(AppEvent::SetVolumePercent(volume)),
+            None => Err("Integer expected".to_owned()),
+        },
+        "ping" | "clearerror" => Ok(String::new()),
+        "outputs" => Ok("outputid: 0\noutputname: default\noutputenabled: 1\n".to_owned()),
+        "tagtypes" | "commands" | "notcommands" | "urlhandlers" | "decoders" => Ok(String::new()),
+        _ => Err(format!("unknown command \"{}\"", command)),
+    }
+}
+
+async fn handle_client(stream: TcpStream, tx: mpsc::UnboundedSender<AppEvent>) {
+    let (read, mut write) = stream.into_split();
+    if write.write_all(GREETING.as_bytes()).await.is_err() {
+        return;
+    }
+
+    // Without a password configured every client may run everything
+    let mut authorized = CONFIG.mpd_password.is_none();
+    let mut lines = BufReader::new(read).lines();
+    let mut list: Option<(bool, Vec<Vec<String>>)> = None;
+    while let Ok(Some(line)) = lines.next_line().await {
+        let tokens = tokenize(&line);
+        let command = tokens.first().map(String::as_str).unwrap_or_default();
+
+        let commands = match (command, &mut list) {
+            ("close", _) => return,
+            ("command_list_begin", None) => {
+                list = Some((false, Vec::new()));
+                continue;
+            },
+            ("command_list_ok_begin", None) => {
+                list = Some((true, Vec::new()));
+                continue;
+            },
+            ("command_list_end", Some(_)) => list.take().unwrap(),
+            (_, Some((_, commands))) => {
+                commands.push(tokens);
+                continue;
+            },
+            // Nothing ever changes from a client's point of view until it stops waiting
+            ("idle", None) => {
+                match lines.next_line().await {
+                    Ok(Some(line)) if line.trim() == "noidle" => {},
+                    _ => return,
+                }
+                (false, Vec::new())
+            },
+            _ => (false, vec![tokens]),
+        };
+
+        let (list_ok, commands) = commands;
+        let mut response = String::new();
+        let mut failed = false;
+        for (n, tokens) in commands.iter().enumerate() {
+            let command = tokens.first().map(String::as_str).unwrap_or_default();
+            let result = match command {
+                "password" => match (&CONFIG.mpd_password, tokens.get(1)) {
+                    (Some(password), Some(given)) if password == given => {
+                        authorized = true;
+                        Ok(String::new())
+                    },
+                    (None, _) => Ok(String::new()),
+                    _ => Err((ACK_PASSWORD, "incorrect password".to_owned())),
+                },
+                _ if !authorized && !CONFIG.mpd_allowed.iter().any(|allowed| allowed == command) => {
+                    Err((ACK_PERMISSION, format!("you don't have permission for \"{}\"", command)))
+                },
+                _ => execute(tokens, &tx).await.map_err(|message| (ACK_UNKNOWN, message)),
+            };
+            match result {
+                Ok(body) => {
+                    response.push_str(&body);
+                    if list_ok {
+                        response.push_str("list_OK\n");
+                    }
+                },
+                Err((code, message)) => {
+                    let _ = writeln!(response, "ACK [{}@{}] {{{}}} {}", code, n, command, message);
+                    failed = true;
+                    break;
+                },
+            }
+        }
+        if !failed {
+            response.push_str("OK\n");
+        }
+
+        if write.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use ym_core::say;
+
+use std::collections::{
+    HashMap,
+    VecDeque,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use tracing::{
+    span,
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::Context,
+    registry::LookupSpan,
+    Layer,
+};
+
+const KEPT_PER_SPAN: usize = 50;
+
+struct Started(Instant);
+
+// Records how long each span lived, keyed by span name, which for api calls is the endpoint function
+#[derive(Clone, Default)]
+pub struct Timings {
+    recent: Arc<Mutex<HashMap<&'static str, VecDeque<Duration>>>>,
+}
+
+impl Timings {
+    pub fn print(&self) {
+        let recent = self.recent.lock().unwrap();
+        if recent.is_empty() {
+            say!("No API calls recorded yet");
+        }
+
+        let mut names = recent.keys().copied().collect::<Vec<_>>();
+        names.sort_unstable();
+        for name in names {
+            let durations = &recent[name];
+            let total = durations.iter().sum::<Duration>();
+            let max = durations.iter().max().copied().unwrap_or_default();
+            let last = durations.back().copied().unwrap_or_default();
+            say!(
+                "{}: {} calls, avg {} ms, max {} ms, last {} ms",
+                name,
+                durations.len(),
+                (total / durations.len() as u32).as_millis(),
+                max.as_millis(),
+                last.as_millis(),
+            );
+        }
+    }
+}
+
+impl<S> Layer<S> for Timings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(elapsed) = span.extensions().get::<Started>().map(|started| started.0.elapsed()) else {
+            return;
+        };
+
+        let mut recent = self.recent.lock().unwrap();
+        let durations = recent.entry(span.name()).or_default();
+        if durations.len() == KEPT_PER_SPAN {
+            durations.pop_front();
+        }
+        durations.push_back(elapsed);
+    }
+}
@@ -0,0 +1,49 @@
+mod prompt;
+mod remote;
+mod ui;
+
+use ym_daemon::{
+    bindings::DoublePress,
+    cli::{
+        Args,
+        Command,
+    },
+    parse_command,
+    print_parse_error,
+    Attach,
+    CONFIG,
+};
+
+use clap::Parser;
+
+// The interface runs on its own thread, so waiting for keys never stalls playback
+fn start_ui(attach: Attach) {
+    let mut double_press = DoublePress::new(
+        &CONFIG.double_press,
+        std::time::Duration::from_millis(CONFIG.double_press_window),
+    );
+    let tx = attach.tx.clone();
+    std::thread::spawn(move || {
+        ui::run(|| ui::local(attach), |input| {
+            let delayed_tx = tx.clone();
+            let delayed = move |line: String| {
+                parse_command(&line, &delayed_tx, print_parse_error);
+            };
+            match double_press.press(input, delayed) {
+                Some(line) => parse_command(&line, &tx, print_parse_error),
+                None => false,
+            }
+        });
+    });
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    if let Some(Command::Attach { control }) = args.command {
+        remote::run(control);
+        return;
+    }
+    ym_daemon::run(args, Some(Box::new(start_ui))).await;
+    ui::restore();
+}
@@ -0,0 +1,225 @@
+use ym_core::config;
+use ym_daemon::Completions;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+const HISTORY_SIZE: usize = 1000;
+
+fn history_path() -> PathBuf {
+    config::data_dir().join("history")
+}
+
+// Entered commands, oldest first. Each one is appended to the file as it's entered,
+// quitting may end the process before the interface gets another turn
+pub struct History {
+    entries: Vec<String>,
+    // Where up/down stands while browsing, None while editing a fresh line
+    browsing: Option<usize>,
+    path: PathBuf,
+}
+
+impl History {
+    pub fn load() -> Self {
+        let path = history_path();
+        // Older versions kept the history in rustyline's format, its header is the only difference
+        let mut entries = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty() && *line != "#V2")
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        entries.drain(..entries.len().saturating_sub(HISTORY_SIZE));
+        History { entries, browsing: None, path }
+    }
+
+    pub fn add(&mut self, line: &str) {
+        self.browsing = None;
+        let line = line.trim();
+        if line.is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.entries.push(line.to_owned());
+        if self.entries.len() > HISTORY_SIZE {
+            self.entries.remove(0);
+        }
+        if let Err(err) = self.append(line) {
+            tracing::warn!("Failed to save command history: {}", err);
+        }
+    }
+
+    fn append(&self, line: &str) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+
+    pub fn previous(&mut self) -> Option<&str> {
+        let n = match self.browsing {
+            Some(0) => 0,
+            Some(n) => n - 1,
+            None => self.entries.len().checked_sub(1)?,
+        };
+        self.browsing = Some(n);
+        self.entries.get(n).map(String::as_str)
+    }
+
+    // None once past the newest entry, the line goes back to empty
+    pub fn next(&mut self) -> Option<&str> {
+        let n = self.browsing? + 1;
+        self.browsing = (n < self.entries.len()).then_some(n);
+        self.browsing.and_then(|n| self.entries.get(n)).map(String::as_str)
+    }
+}
+
+// What Tab offers: shown as display, inserted as replacement
+pub struct Candidate {
+    pub display: String,
+    pub replacement: String,
+}
+
+fn plain(value: &str) -> Candidate {
+    Candidate { display: value.to_owned(), replacement: value.to_owned() }
+}
+
+fn arguments(command: &str, completions: &Completions) -> Vec<Candidate> {
+    let fixed = |values: &[&str]| values.iter().map(|&value| plain(value)).collect();
+    let indexed = |titles: &[String]| {
+        titles
+            .iter()
+            .enumerate()
+            .map(|(n, title)| Candidate { display: format!("{}. {}", n, title), replacement: n.to_string() })
+            .collect()
+    };
+    let data = completions.lock().unwrap();
+    match command {
+        "quality" => fixed(&["mp3", "aac"]),
+        "status" => fixed(&["--json"]),
+        "trim" => fixed(&["start", "end", "clear"]),
+        "snapshot" => fixed(&["take", "restore"]),
+        "sleep" => fixed(&["end", "cancel"]),
+        "albums" => fixed(&["date"]),
+        "eq" => data.eq_presets.iter().map(|preset| plain(preset)).collect(),
+        "playlist" | "load-playlist" | "prefetch-playlist" | "add-to-playlist" | "rename-playlist" | "delete-playlist" => {
+            indexed(&data.playlists)
+        },
+        "browse-load" => indexed(&data.browsed),
+        _ => Vec::new(),
+    }
+}
+
+// Returns where the completed word starts and what may replace it.
+// Indexed candidates match on the title too, so typing part of a name completes to its index
+pub fn complete(line: &str, commands: &[&str], completions: &Completions) -> (usize, Vec<Candidate>) {
+    let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let word = line[start..].to_lowercase();
+    let mut before = line[..start].split_whitespace();
+    let candidates = match (before.next(), before.next()) {
+        (None, _) => commands.iter().map(|&command| plain(command)).collect(),
+        (Some(command), None) => arguments(command, completions),
+        _ => Vec::new(),
+    };
+    let candidates = candidates
+        .into_iter()
+        .filter(|candidate| candidate.replacement.starts_with(&word) || candidate.display.to_lowercase().contains(&word))
+        .collect();
+    (start, candidates)
+}
+
+// The longest start all the replacements share, what Tab fills in when there's no single match
+pub fn common_prefix(candidates: &[Candidate]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let len = candidates[1..].iter().fold(first.replacement.len(), |len, candidate| {
+        first.replacement[..len]
+            .char_indices()
+            .zip(candidate.replacement.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(candidate.replacement.len()), |((i, _), _)| i)
+    });
+    &first.replacement[..len]
+}
+
+// The line being typed, the cursor counts chars rather than bytes
+#[derive(Default)]
+pub struct Input {
+    pub text: String,
+    pub cursor: usize,
+}
+
+impl Input {
+    fn offset(&self) -> usize {
+        self.text.char_indices().nth(self.cursor).map_or(self.text.len(), |(i, _)| i)
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let offset = self.offset();
+        self.text.insert(offset, c);
+        self.cursor += 1;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        self.cursor -= 1;
+        let offset = self.offset();
+        self.text.remove(offset);
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let offset = self.offset();
+            self.text.remove(offset);
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    // Like Ctrl-W in a shell, drops the word before the cursor
+    pub fn delete_word(&mut self) {
+        let end = self.offset();
+        let start = self.text[..end].trim_end().rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        self.cursor -= self.text[start..end].chars().count();
+        self.text.replace_range(start..end, "");
+    }
+
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_owned();
+        self.end();
+    }
+
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.offset()]
+    }
+
+    // Puts `replacement` in place of what follows `start` up to the cursor
+    pub fn replace_before_cursor(&mut self, start: usize, replacement: &str) {
+        let end = self.offset();
+        self.text.replace_range(start..end, replacement);
+        self.cursor = self.text[..start + replacement.len()].chars().count();
+    }
+}
@@ -0,0 +1,103 @@
+use crate::ui::{
+    self,
+    Feed,
+};
+
+use ym_core::events::Event;
+use ym_daemon::{
+    control,
+    Completions,
+};
+
+use std::io::{
+    BufRead,
+    BufReader,
+    Write,
+};
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc;
+
+// A connection to the control socket, one reply line for each command line
+struct Connection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Connection {
+    fn open() -> std::io::Result<Self> {
+        let writer = UnixStream::connect(control::socket_path())?;
+        Ok(Connection { reader: BufReader::new(writer.try_clone()?), writer })
+    }
+
+    fn send(&mut self, command: &str) -> std::io::Result<String> {
+        writeln!(self.writer, "{}", command)?;
+        let mut reply = String::new();
+        if self.reader.read_line(&mut reply)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(reply.trim_end().to_owned())
+    }
+}
+
+// Shows a player running elsewhere, what it reports comes over the control socket.
+// Typed commands only reach it with `control`, quitting here detaches and leaves it playing
+pub fn run(control: bool) {
+    let (mut watching, mut polling) = match Connection::open().and_then(|watching| Ok((watching, Connection::open()?))) {
+        Ok(connections) => connections,
+        Err(err) => {
+            println!("No player to attach to: {}", err);
+            return;
+        },
+    };
+    if let Err(err) = writeln!(watching.writer, "watch") {
+        println!("No player to attach to: {}", err);
+        return;
+    }
+    let mut commands = if control { Connection::open().ok() } else { None };
+
+    let (event_tx, events) = mpsc::channel();
+    let replies = event_tx.clone();
+    std::thread::spawn(move || {
+        for line in watching.reader.lines().map_while(Result::ok) {
+            if event_tx.send(Event::Message(line)).is_err() {
+                return;
+            }
+        }
+        let _ = event_tx.send(Event::Message("The player has quit, q to leave".to_owned()));
+    });
+
+    let (status_tx, status) = mpsc::channel();
+    std::thread::spawn(move || {
+        while let Ok(text) = polling.send("status") {
+            if status_tx.send(text).is_err() {
+                return;
+            }
+            std::thread::sleep(ui::STATUS_EVERY);
+        }
+    });
+
+    let feed = Feed { events, status, completions: Completions::default() };
+    ui::run(
+        || feed,
+        |input| {
+            let line = input.trim();
+            if line == "q" {
+                return true;
+            }
+            if line.is_empty() {
+                return false;
+            }
+            let reply = match &mut commands {
+                Some(connection) => match connection.send(line) {
+                    // What the command says comes through the watch feed
+                    Ok(reply) if reply == "ok" => return false,
+                    Ok(reply) => reply,
+                    Err(err) => format!("Failed to reach the player: {}", err),
+                },
+                None => "Attached read-only, start attach with --control to send commands".to_owned(),
+            };
+            let _ = replies.send(Event::Message(reply));
+            false
+        },
+    );
+}
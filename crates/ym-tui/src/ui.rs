@@ -0,0 +1,323 @@
+use crate::prompt::{
+    self,
+    History,
+    Input,
+};
+
+use ym_core::events::Event;
+use ym_daemon::{
+    print_event,
+    show_events,
+    AppEvent,
+    Attach,
+    Completions,
+    COMMANDS,
+    SECRET,
+};
+
+use ratatui::{
+    crossterm::event::{
+        self as terminal,
+        KeyCode,
+        KeyEvent,
+        KeyEventKind,
+        KeyModifiers,
+    },
+    layout::{
+        Constraint,
+        Layout,
+        Position,
+    },
+    style::{
+        Style,
+        Stylize,
+    },
+    text::Line,
+    widgets::Paragraph,
+    DefaultTerminal,
+    Frame,
+};
+
+use tokio::sync::oneshot;
+
+use std::sync::{
+    atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    mpsc,
+};
+use std::time::Duration;
+
+const LOG_SIZE: usize = 5000;
+const TICK: Duration = Duration::from_millis(100);
+pub const STATUS_EVERY: Duration = Duration::from_millis(500);
+
+// Whether the terminal is in raw mode on the alternate screen
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// What the interface shows: the player's output, its status line and what Tab completes
+pub struct Feed {
+    pub events: mpsc::Receiver<Event>,
+    pub status: mpsc::Receiver<String>,
+    pub completions: Completions,
+}
+
+// The player in this process, its output comes here for as long as the interface runs
+pub fn local(attach: Attach) -> Feed {
+    let (event_tx, events) = mpsc::channel();
+    show_events(move |event| {
+        let _ = event_tx.send(event);
+    });
+    let (status_tx, status) = mpsc::channel();
+    let tx = attach.tx;
+    std::thread::spawn(move || loop {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if tx.send(AppEvent::Status { json: false, reply: Some(reply_tx) }).is_err() {
+            return;
+        }
+        // The player answers between commands, a slow one leaves the last status up meanwhile
+        let Ok(text) = reply_rx.blocking_recv() else {
+            return;
+        };
+        if status_tx.send(text).is_err() {
+            return;
+        }
+        std::thread::sleep(STATUS_EVERY);
+    });
+    Feed { events, status, completions: attach.completions }
+}
+
+struct App {
+    log: Vec<String>,
+    // Lines scrolled back from the newest one
+    scroll: usize,
+    status: String,
+    input: Input,
+    history: History,
+    // Candidates of the last Tab that had more than one
+    candidates: Vec<String>,
+    // The command whose argument is being typed without echo
+    secret: Option<&'static str>,
+    completions: Completions,
+}
+
+impl App {
+    fn push(&mut self, event: Event) {
+        let text = match event {
+            Event::Message(text) => text,
+            Event::Art(_) => "Cover art isn't drawn inside the interface".to_owned(),
+        };
+        let lines = text.lines().map(str::to_owned).collect::<Vec<_>>();
+        // Keeps what's on screen in place while reading back
+        if self.scroll > 0 {
+            self.scroll += lines.len();
+        }
+        self.log.extend(lines);
+        if self.log.len() > LOG_SIZE {
+            self.log.drain(..self.log.len() - LOG_SIZE);
+        }
+        self.scroll = self.scroll.min(self.log.len());
+    }
+
+    fn complete(&mut self) {
+        let (start, candidates) = prompt::complete(self.input.before_cursor(), COMMANDS, &self.completions);
+        match candidates.as_slice() {
+            [] => self.candidates.clear(),
+            [single] => {
+                self.input.replace_before_cursor(start, &format!("{} ", single.replacement));
+                self.candidates.clear();
+            },
+            _ => {
+                let prefix = prompt::common_prefix(&candidates);
+                if prefix.len() > self.input.before_cursor().len() - start {
+                    self.input.replace_before_cursor(start, prefix);
+                }
+                self.candidates = candidates.into_iter().map(|candidate| candidate.display).collect();
+            },
+        }
+    }
+
+    // The line to run once Enter is pressed, None while a secret is still to be typed
+    fn enter(&mut self) -> Option<String> {
+        self.candidates.clear();
+        let line = self.input.take();
+        if let Some(command) = self.secret.take() {
+            let secret = line.trim();
+            return (!secret.is_empty()).then(|| format!("{} {}", command, secret));
+        }
+        let mut words = line.split_whitespace();
+        let secret = words.next().and_then(|command| SECRET.iter().copied().find(|&secret| secret == command));
+        match (secret, words.next().is_none()) {
+            (Some(command), true) => {
+                self.secret = Some(command);
+                return None;
+            },
+            // Kept out of the history and the log
+            (Some(command), false) => self.push(Event::Message(format!("> {} ***", command))),
+            (None, _) => {
+                self.history.add(&line);
+                self.push(Event::Message(format!("> {}", line)));
+            },
+        }
+        Some(line)
+    }
+
+    // Returns the line a key completed
+    fn key(&mut self, key: KeyEvent) -> Option<String> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            // Ctrl-C and Ctrl-D quit like they did with the plain prompt
+            KeyCode::Char('c' | 'd') if control => return Some("q".to_owned()),
+            KeyCode::Char('w') if control => self.input.delete_word(),
+            KeyCode::Char('u') if control => {
+                self.input.take();
+            },
+            KeyCode::Char('a') if control => self.input.home(),
+            KeyCode::Char('e') if control => self.input.end(),
+            KeyCode::Char(c) => self.input.insert(c),
+            KeyCode::Backspace => self.input.backspace(),
+            KeyCode::Delete => self.input.delete(),
+            KeyCode::Left => self.input.left(),
+            KeyCode::Right => self.input.right(),
+            KeyCode::Home => self.input.home(),
+            KeyCode::End => self.input.end(),
+            KeyCode::Up if self.secret.is_none() => {
+                if let Some(line) = self.history.previous() {
+                    self.input.set(line);
+                }
+            },
+            KeyCode::Down if self.secret.is_none() => {
+                let line = self.history.next().unwrap_or_default().to_owned();
+                self.input.set(&line);
+            },
+            KeyCode::PageUp => self.scroll = (self.scroll + 10).min(self.log.len()),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Tab if self.secret.is_none() => self.complete(),
+            KeyCode::Esc => {
+                self.secret = None;
+                self.candidates.clear();
+                self.input.take();
+            },
+            KeyCode::Enter => return self.enter(),
+            _ => {},
+        }
+        None
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let hint_rows = (!self.candidates.is_empty()) as u16;
+        let [status, log, hint, input] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(hint_rows),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(Paragraph::new(self.status.as_str()).style(Style::new().reversed()), status);
+
+        let end = self.log.len() - self.scroll;
+        let start = end.saturating_sub(log.height as usize);
+        let lines = self.log[start..end].iter().map(|line| Line::raw(line.as_str())).collect::<Vec<_>>();
+        frame.render_widget(Paragraph::new(lines), log);
+
+        frame.render_widget(Paragraph::new(self.candidates.join("  ")).dim(), hint);
+
+        let (prompt, text) = match self.secret {
+            Some(command) => (format!("{}: ", command), "*".repeat(self.input.text.chars().count())),
+            None => ("> ".to_owned(), self.input.text.clone()),
+        };
+        let x = input.x + (prompt.chars().count() + self.input.cursor) as u16;
+        frame.render_widget(Paragraph::new(format!("{}{}", prompt, text)), input);
+        frame.set_cursor_position(Position::new(x.min(input.right().saturating_sub(1)), input.y));
+    }
+}
+
+// Status on top, what the player reports below it and the command line at the bottom.
+// The feed is only connected once the terminal is set up, so a plain prompt keeps the player's output.
+// `handle` gets every entered line and returns whether the interface should stop
+pub fn run(connect: impl FnOnce() -> Feed, mut handle: impl FnMut(&str) -> bool) {
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            tracing::warn!("Falling back to plain input, failed to set up the terminal: {}", err);
+            return run_plain(handle);
+        },
+    };
+    ACTIVE.store(true, Ordering::SeqCst);
+    let Feed { events, status, completions } = connect();
+
+    let mut app = App {
+        log: Vec::new(),
+        scroll: 0,
+        status: String::new(),
+        input: Input::default(),
+        history: History::load(),
+        candidates: Vec::new(),
+        secret: None,
+        completions,
+    };
+    match event_loop(&mut terminal, &mut app, events, status, &mut handle) {
+        Ok(()) => restore(),
+        Err(err) => {
+            restore();
+            tracing::error!("The interface failed: {}", err);
+            handle("q");
+        },
+    }
+}
+
+// Gives the terminal back, the player may quit and end the process before the interface thread gets to it,
+// so main calls this once the player is done as well
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        // What the player says while shutting down goes to the restored terminal
+        show_events(print_event);
+        ratatui::restore();
+    }
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    events: mpsc::Receiver<Event>,
+    status: mpsc::Receiver<String>,
+    handle: &mut impl FnMut(&str) -> bool,
+) -> std::io::Result<()> {
+    loop {
+        while let Ok(event) = events.try_recv() {
+            app.push(event);
+        }
+        if let Some(text) = status.try_iter().last() {
+            app.status = text;
+        }
+
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if !terminal::poll(TICK)? {
+            continue;
+        }
+        let terminal::Event::Key(key) = terminal::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if let Some(line) = app.key(key) {
+            if handle(&line) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn run_plain(mut handle: impl FnMut(&str) -> bool) {
+    loop {
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).expect("error with stdin");
+        if handle(&input) {
+            break;
+        }
+    }
+}